@@ -5,7 +5,7 @@ use log::debug;
 use nickel_lang::{
     cache::Cache,
     identifier::Ident,
-    term::{MetaValue, RichTerm, Term},
+    term::{record::RecordData, MetaValue, Term},
     typecheck::{linearization::LinearizationState, UnifType},
     types::TypeF,
 };
@@ -102,11 +102,11 @@ impl<'b> Building<'b> {
     pub(super) fn register_fields(
         &mut self,
         current_file: FileId,
-        record_fields: &HashMap<Ident, RichTerm>,
+        record_data: &RecordData,
         record: ItemId,
         env: &mut Environment,
     ) {
-        for (ident, value) in record_fields.iter() {
+        for (ident, value) in record_data.fields.iter() {
             let id = ItemId {
                 file_id: current_file,
                 index: self.id_gen().get_and_advance(),
@@ -1,30 +1,48 @@
 //! Entry point of the program.
+use nickel_lang::cache::InputFormat;
 use nickel_lang::error::{Error, IOError};
 use nickel_lang::eval::cache::CBNCache;
-use nickel_lang::program::{ColorOpt, Program};
+use nickel_lang::program::{
+    self, ColorOpt, ErrorFormat, FieldOverride, FieldPath, Program, TopLevelArg,
+};
 use nickel_lang::repl::query_print;
 #[cfg(feature = "repl")]
 use nickel_lang::repl::rustyline_frontend;
-use nickel_lang::term::{RichTerm, Term};
-use nickel_lang::{serialize, serialize::ExportFormat};
+use nickel_lang::term::{MergePriority, Term};
+use nickel_lang::{
+    serialize,
+    serialize::{ExportFormat, FieldOrder},
+};
 use std::path::{Path, PathBuf};
 use std::{
     fs::{self, File},
-    process,
+    process, thread,
+    time::Duration,
 };
 // use std::ffi::OsStr;
+use codespan::Files;
 use directories::BaseDirs;
 use structopt::StructOpt;
 
 type EC = CBNCache;
 
+/// How often watched files are checked for modifications.
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
 /// Command-line options and subcommands.
 #[derive(StructOpt, Debug)]
 /// The interpreter of the Nickel language.
 struct Opt {
-    /// The input file. Standard input by default
-    #[structopt(short = "f", long, global = true, parse(from_os_str))]
-    file: Option<PathBuf>,
+    /// The input file. Standard input by default. Can be repeated, in which case the files are
+    /// merged from left to right
+    #[structopt(
+        short = "f",
+        long,
+        global = true,
+        number_of_values = 1,
+        parse(from_os_str)
+    )]
+    file: Vec<PathBuf>,
 
     #[cfg(debug_assertions)]
     /// Skips the standard library import. For debugging only. This does not affect REPL
@@ -35,10 +53,35 @@ struct Opt {
     #[structopt(long, global = true, case_insensitive = true, default_value = "auto")]
     color: ColorOpt,
 
+    /// Format of error reports: text, or json for one JSON object per diagnostic and per line.
+    #[structopt(long, global = true, case_insensitive = true, default_value = "text")]
+    error_format: ErrorFormat,
+
     #[structopt(subcommand)]
     command: Option<Command>,
 }
 
+/// Output formats of the `deps` subcommand.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum DepsFormat {
+    List,
+    Json,
+    Dot,
+}
+
+impl std::str::FromStr for DepsFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "list" => Ok(Self::List),
+            "json" => Ok(Self::Json),
+            "dot" => Ok(Self::Dot),
+            _ => Err("possible values are 'list', 'json' or 'dot'."),
+        }
+    }
+}
+
 /// Available subcommands.
 #[derive(StructOpt, Debug)]
 enum Command {
@@ -51,13 +94,87 @@ enum Command {
     },
     /// Exports the result to a different format
     Export {
-        /// Available formats: `raw, json, yaml, toml`. Default format: `json`.
+        /// Available formats: `raw, json, yaml, yaml-stream, toml, nix, dotenv, properties, xml`.
+        /// Default format: `json`.
         #[structopt(long)]
         format: Option<ExportFormat>,
         /// Output file. Standard output by default
         #[structopt(short = "o", long)]
         #[structopt(parse(from_os_str))]
         output: Option<PathBuf>,
+        /// The order of the fields of records in the output: `alphabetical` sorts them by name,
+        /// `definition` keeps the order in which they are written
+        #[structopt(long, default_value = "alphabetical")]
+        field_order: FieldOrder,
+        /// The name of the root element of an XML export
+        #[structopt(long, default_value = "root", parse(try_from_str = parse_xml_root))]
+        xml_root: String,
+        /// Overrides a field before evaluation, as in `--override server.port=8080`. The value is
+        /// parsed as a Nickel expression and merged with the program. Can be repeated
+        #[structopt(long = "override", number_of_values = 1)]
+        overrides: Vec<FieldOverride>,
+        /// The merge priority of overrides: `default`, `force` or a number
+        #[structopt(long, default_value = "force")]
+        override_priority: MergePriority,
+        /// Only exports the field at the given path, as in `--field services.api`. Other fields
+        /// are evaluated only as far as this one depends on them
+        #[structopt(long)]
+        field: Option<FieldPath>,
+        /// Exports several files at once. The result must be a record mapping output paths to
+        /// records `{format = `Yaml, content = ...}`. Every file is validated before anything is
+        /// written
+        #[structopt(long, requires = "out-dir", conflicts_with_all = &["format", "output"])]
+        multi: bool,
+        /// The directory where the files of a multi-file export are written
+        #[structopt(long, parse(from_os_str), requires = "multi")]
+        out_dir: Option<PathBuf>,
+        /// Passes an argument to the top-level function of the program, which must take a record,
+        /// as in `--arg replicas=3`. The value is parsed as a Nickel expression. Can be repeated
+        #[structopt(long = "arg", number_of_values = 1, parse(try_from_str = parse_arg))]
+        args: Vec<TopLevelArg>,
+        /// Same as `--arg`, but the value is passed as a string, as in `--argstr env=prod`
+        #[structopt(long = "argstr", number_of_values = 1, parse(try_from_str = parse_argstr))]
+        argstrs: Vec<TopLevelArg>,
+        /// Exports again each time the program or one of its imports is modified
+        #[structopt(long)]
+        watch: bool,
+    },
+    /// Converts a JSON, YAML, TOML or text file to Nickel source code, printed on the standard
+    /// output
+    Convert {
+        /// Available formats: `json, yaml, toml, text`. Guessed from the extension of the input
+        /// file by default
+        #[structopt(long)]
+        format: Option<InputFormat>,
+    },
+    /// Generates a JSON Schema from the record contract defined by the program
+    ExportSchema {
+        /// Only uses the contract at the given path, as in `--field schemas.Config`
+        #[structopt(long)]
+        field: Option<FieldPath>,
+        /// Output file. Standard output by default
+        #[structopt(short = "o", long)]
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Generates Nickel contracts from a JSON Schema, written in JSON, YAML, TOML or Nickel
+    ImportSchema {
+        /// Available formats: `nickel, json, yaml, toml`. Guessed from the extension of the input
+        /// file by default
+        #[structopt(long)]
+        format: Option<InputFormat>,
+        /// Output file. Standard output by default
+        #[structopt(short = "o", long)]
+        #[structopt(parse(from_os_str))]
+        output: Option<PathBuf>,
+    },
+    /// Prints the files the program depends on, that is its input files and their transitive
+    /// imports, including data files
+    Deps {
+        /// Available formats: `list` for one path per line, `json` for an object mapping each
+        /// file to its direct imports, or `dot` for a Graphviz graph. Default format: `list`
+        #[structopt(long, default_value = "list")]
+        format: DepsFormat,
     },
     /// Prints the metadata attached to an attribute, given as a path
     Query {
@@ -74,7 +191,25 @@ enum Command {
         value: bool,
     },
     /// Typechecks the program but do not run it
-    Typecheck,
+    Typecheck {
+        /// Typechecks again each time the program or one of its imports is modified
+        #[structopt(long)]
+        watch: bool,
+    },
+    /// Formats Nickel source code, preserving comments. Prints the result on the standard output
+    /// by default
+    Format {
+        /// The files to format. Use `--file`, or the standard input, if none is provided
+        #[structopt(parse(from_os_str))]
+        files: Vec<PathBuf>,
+        /// Overwrites the input files with the formatted source
+        #[structopt(short = "i", long)]
+        in_place: bool,
+        /// Only checks that the input is formatted, without writing anything. Fails if some input
+        /// isn't formatted
+        #[structopt(long, conflicts_with = "in-place")]
+        check: bool,
+    },
     /// Starts an REPL session
     Repl {
         #[structopt(long)]
@@ -110,16 +245,30 @@ fn main() {
 
         #[cfg(not(feature = "repl"))]
         eprintln!("error: this executable was not compiled with REPL support");
+    } else if let Some(Command::Format {
+        files,
+        in_place,
+        check,
+    }) = opts.command
+    {
+        let files = if files.is_empty() { opts.file } else { files };
+
+        if !format(files, in_place, check, opts.color, opts.error_format) {
+            process::exit(1);
+        }
     } else {
-        let mut program = opts
-            .file
-            .clone()
-            .map(Program::new_from_file)
-            .unwrap_or_else(Program::new_from_stdin)
-            .unwrap_or_else(|err| {
-                eprintln!("Error when reading input: {}", err);
-                process::exit(1)
-            });
+        let program = if opts.file.is_empty() {
+            Program::new_from_stdin()
+        } else {
+            Program::new_from_files(opts.file.clone())
+        };
+        let mut program = program.unwrap_or_else(|err| {
+            report_input_error(
+                format!("Error when reading input: {}", err),
+                opts.error_format,
+            );
+            process::exit(1)
+        });
 
         #[cfg(debug_assertions)]
         if opts.nostdlib {
@@ -127,13 +276,75 @@ fn main() {
         }
 
         program.set_color(opts.color);
+        program.set_error_format(opts.error_format);
 
         let result = match opts.command {
             Some(Command::PprintAst { transform }) => program.pprint_ast(
                 &mut std::io::BufWriter::new(Box::new(std::io::stdout())),
                 transform,
             ),
-            Some(Command::Export { format, output }) => export(&mut program, format, output),
+            Some(Command::Export {
+                format,
+                output,
+                field_order,
+                xml_root,
+                overrides,
+                override_priority,
+                field,
+                multi,
+                out_dir,
+                args,
+                argstrs,
+                watch: watch_mode,
+            }) => {
+                if let Err(msg) = program.add_args(args.into_iter().chain(argstrs)) {
+                    report_input_error(msg, opts.error_format);
+                    process::exit(1)
+                }
+                program.add_overrides(
+                    overrides
+                        .into_iter()
+                        .map(|ovd| ovd.with_priority(override_priority)),
+                );
+                let out_dir = out_dir.filter(|_| multi);
+                let run = |program: &mut Program<EC>| {
+                    export(
+                        program,
+                        format,
+                        output.as_deref(),
+                        field_order,
+                        &xml_root,
+                        field.as_ref(),
+                        out_dir.as_deref(),
+                    )
+                };
+
+                if watch_mode {
+                    watch(&mut program, run)
+                } else {
+                    run(&mut program)
+                }
+            }
+            Some(Command::Convert { format }) => input_format(format, &opts.file)
+                .and_then(|format| program.convert(format))
+                .map(|converted| print!("{}", converted)),
+            Some(Command::ExportSchema { field, output }) => {
+                export_schema(&mut program, field, output)
+            }
+            Some(Command::ImportSchema { format, output }) => input_format(format, &opts.file)
+                .and_then(|format| program.import_schema(format))
+                .and_then(|contract| match output {
+                    Some(file) => {
+                        fs::write(file, contract).map_err(|err| Error::IOError(IOError::from(err)))
+                    }
+                    None => {
+                        print!("{}", contract);
+                        Ok(())
+                    }
+                }),
+            Some(Command::Deps { format }) => program
+                .import_graph()
+                .map(|graph| print!("{}", format_deps(&graph, format))),
             Some(Command::Query {
                 path,
                 doc,
@@ -159,8 +370,11 @@ fn main() {
                     query_print::write_query_result(&mut std::io::stdout(), &term, attrs).unwrap()
                 })
             }
-            Some(Command::Typecheck) => program.typecheck(),
-            Some(Command::Repl { .. }) => unreachable!(),
+            Some(Command::Typecheck { watch: true }) => {
+                watch(&mut program, |program| program.typecheck())
+            }
+            Some(Command::Typecheck { watch: false }) => program.typecheck(),
+            Some(Command::Repl { .. }) | Some(Command::Format { .. }) => unreachable!(),
             #[cfg(feature = "doc")]
             Some(Command::Doc { ref output }) => output
                 .as_ref()
@@ -186,7 +400,7 @@ fn main() {
 
                     let mut has_file_name = false;
 
-                    if let Some(path) = opts.file {
+                    if let Some(path) = opts.file.first() {
                         if let Some(file_stem) = path.file_stem() {
                             markdown_file.push(file_stem);
                             has_file_name = true;
@@ -213,28 +427,258 @@ fn main() {
         };
 
         if let Err(err) = result {
-            program.report(err);
+            program.report_with_format(err);
             process::exit(1)
         }
     }
 }
 
+fn parse_arg(s: &str) -> Result<TopLevelArg, String> {
+    TopLevelArg::parse(s, false)
+}
+
+fn parse_argstr(s: &str) -> Result<TopLevelArg, String> {
+    TopLevelArg::parse(s, true)
+}
+
+fn parse_xml_root(s: &str) -> Result<String, String> {
+    if serialize::is_xml_name(s) {
+        Ok(String::from(s))
+    } else {
+        Err(format!("`{}` is not a valid XML element name", s))
+    }
+}
+
 fn export(
     program: &mut Program<EC>,
     format: Option<ExportFormat>,
-    output: Option<PathBuf>,
+    output: Option<&Path>,
+    field_order: FieldOrder,
+    xml_root: &str,
+    field: Option<&FieldPath>,
+    out_dir: Option<&Path>,
 ) -> Result<(), Error> {
-    let rt = program.eval_full().map(RichTerm::from)?;
+    let rt = match field {
+        // The annotations of values are needed to export datetimes to TOML, which may also be the
+        // format of any file of a multi-file export
+        _ if format == Some(ExportFormat::Toml) || out_dir.is_some() => {
+            program.eval_full_annotated(field)?
+        }
+        Some(path) => program.eval_full_field(path)?,
+        None => program.eval_full()?,
+    };
+
+    if let Some(dir) = out_dir {
+        let files = serialize::multi_export_files(&rt)?;
+        return serialize::write_multi_export(dir, &files, field_order);
+    }
+
     let format = format.unwrap_or_default();
 
-    serialize::validate(format, &rt)?;
+    // An XML document is validated by building it, which is thus done only once, before creating
+    // the output file
+    let xml = match format {
+        ExportFormat::Xml => Some(serialize::to_xml(&rt, xml_root, field_order)?),
+        _ => {
+            serialize::validate(format, &rt)?;
+            None
+        }
+    };
+
+    let write = |mut writer: Box<dyn std::io::Write>| -> Result<(), Error> {
+        match xml {
+            Some(ref doc) => writer
+                .write_all(doc.as_bytes())
+                .map_err(|err| IOError::from(err).into()),
+            None => serialize::to_writer_with_order(writer, format, &rt, field_order)
+                .map_err(Error::from),
+        }
+    };
 
     if let Some(file) = output {
         let file = fs::File::create(file).map_err(IOError::from)?;
-        serialize::to_writer(file, format, &rt)?;
+        write(Box::new(file))?;
     } else {
-        serialize::to_writer(std::io::stdout(), format, &rt)?;
+        write(Box::new(std::io::stdout()))?;
     }
 
     Ok(())
 }
+
+/// Render an import graph, as returned by [`Program::import_graph`].
+fn format_deps(graph: &[(PathBuf, Vec<PathBuf>)], format: DepsFormat) -> String {
+    let name = |path: &PathBuf| path.to_string_lossy().into_owned();
+
+    match format {
+        DepsFormat::List => graph
+            .iter()
+            .map(|(file, _)| format!("{}\n", name(file)))
+            .collect(),
+        DepsFormat::Json => {
+            let graph: serde_json::Map<String, serde_json::Value> = graph
+                .iter()
+                .map(|(file, imports)| {
+                    (
+                        name(file),
+                        imports.iter().map(name).collect::<Vec<_>>().into(),
+                    )
+                })
+                .collect();
+            // unwrap(): serializing a JSON value can't fail
+            format!("{}\n", serde_json::to_string_pretty(&graph).unwrap())
+        }
+        DepsFormat::Dot => {
+            let mut out = String::from("digraph imports {\n");
+            for (file, imports) in graph {
+                // Quoting with the JSON syntax is valid in DOT
+                let file = serde_json::Value::from(name(file));
+                out.push_str(&format!("  {};\n", file));
+                for import in imports {
+                    out.push_str(&format!(
+                        "  {} -> {};\n",
+                        file,
+                        serde_json::Value::from(name(import))
+                    ));
+                }
+            }
+            out.push_str("}\n");
+            out
+        }
+    }
+}
+
+/// Run a command, and run it again each time the program or one of its imports is modified on
+/// disk. Errors are reported, but don't stop watching.
+fn watch<F>(program: &mut Program<EC>, mut run: F) -> !
+where
+    F: FnMut(&mut Program<EC>) -> Result<(), Error>,
+{
+    loop {
+        if let Err(err) = run(program) {
+            program.report_with_format(err);
+        }
+
+        while !program.reload_modified() {
+            thread::sleep(WATCH_INTERVAL);
+        }
+    }
+}
+
+/// Return the format given on the command line, or guess it from the extension of the first
+/// input file.
+fn input_format(format: Option<InputFormat>, files: &[PathBuf]) -> Result<InputFormat, Error> {
+    format
+        .or_else(|| {
+            files
+                .first()
+                .and_then(|path| InputFormat::from_path_buf(path))
+        })
+        .ok_or_else(|| {
+            Error::IOError(IOError(String::from(
+                "cannot guess the format of the input, use `--format`",
+            )))
+        })
+}
+
+fn export_schema(
+    program: &mut Program<EC>,
+    field: Option<FieldPath>,
+    output: Option<PathBuf>,
+) -> Result<(), Error> {
+    let schema = program.export_schema(field.as_ref())?;
+    let to_io_err = |err: serde_json::Error| Error::IOError(IOError(err.to_string()));
+
+    if let Some(file) = output {
+        let file = fs::File::create(file).map_err(IOError::from)?;
+        serde_json::to_writer_pretty(file, &schema).map_err(to_io_err)
+    } else {
+        serde_json::to_writer_pretty(std::io::stdout(), &schema).map_err(to_io_err)
+    }
+}
+
+/// Format a list of files, or the standard input if `files` is empty. Errors are reported as they
+/// are encountered. Return `false` if at least one input couldn't be formatted, or, in check mode,
+/// wasn't formatted.
+fn format(
+    files: Vec<PathBuf>,
+    in_place: bool,
+    check: bool,
+    color: ColorOpt,
+    error_format: ErrorFormat,
+) -> bool {
+    let inputs: Vec<Option<PathBuf>> = if files.is_empty() {
+        vec![None]
+    } else {
+        files.into_iter().map(Some).collect()
+    };
+    let mut success = true;
+
+    for input in inputs {
+        let name = input
+            .as_ref()
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or_else(|| String::from("<stdin>"));
+        let program: std::io::Result<Program<EC>> = input
+            .clone()
+            .map(Program::new_from_file)
+            .unwrap_or_else(Program::new_from_stdin);
+        let mut program = match program {
+            Ok(program) => program,
+            Err(err) => {
+                report_input_error(
+                    format!("Error when reading input `{}`: {}", name, err),
+                    error_format,
+                );
+                success = false;
+                continue;
+            }
+        };
+        program.set_color(color);
+        program.set_error_format(error_format);
+
+        let result = program.format().and_then(|formatted| {
+            if check {
+                if formatted != program.source() {
+                    eprintln!("`{}` is not formatted", name);
+                    success = false;
+                }
+                Ok(())
+            } else if in_place {
+                match input {
+                    Some(ref path) if formatted != program.source() => {
+                        fs::write(path, formatted).map_err(|e| {
+                            Error::IOError(IOError(format!(
+                                "when writing output file `{}`: {}",
+                                name, e
+                            )))
+                        })
+                    }
+                    Some(_) => Ok(()),
+                    None => Err(Error::IOError(IOError(String::from(
+                        "formatting in place requires an input file",
+                    )))),
+                }
+            } else {
+                print!("{}", formatted);
+                Ok(())
+            }
+        });
+
+        if let Err(err) = result {
+            program.report_with_format(err);
+            success = false;
+        }
+    }
+
+    success
+}
+
+/// Report an error occurring before a program could be created, when reading its input.
+fn report_input_error(msg: String, error_format: ErrorFormat) {
+    match error_format {
+        ErrorFormat::Text => eprintln!("{}", msg),
+        ErrorFormat::Json => program::report_json(
+            &Error::IOError(IOError(msg)).to_structured_diagnostics(&mut Files::new(), None),
+        ),
+    }
+}
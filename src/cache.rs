@@ -3,16 +3,16 @@
 use crate::error::{Error, ImportError, ParseError, ParseErrors, TypecheckError};
 use crate::eval::cache::Cache as EvalCache;
 use crate::parser::lexer::Lexer;
-use crate::position::TermPos;
+use crate::position::{RawSpan, TermPos};
 use crate::stdlib::{self as nickel_stdlib, StdlibModule};
 use crate::term::record::RecordData;
-use crate::term::{RichTerm, SharedTerm, Term};
+use crate::term::{RichTerm, SharedTerm, Term, TraverseOrder};
 use crate::transform::import_resolution;
 use crate::typecheck::type_check;
 use crate::typecheck::{self, Wildcards};
 use crate::types::UnboundTypeVariableError;
 use crate::{eval, parser, transform};
-use codespan::{FileId, Files};
+use codespan::{ByteIndex, FileId, Files};
 use io::Read;
 use std::collections::hash_map;
 use std::collections::{HashMap, HashSet};
@@ -31,20 +31,41 @@ pub enum InputFormat {
     Json,
     Yaml,
     Toml,
+    /// Raw text, such as scripts or certificates, imported as a string.
+    Text,
 }
 
 impl InputFormat {
-    fn from_path_buf(path_buf: &Path) -> Option<InputFormat> {
+    /// Guess the format of a file from its extension.
+    pub fn from_path_buf(path_buf: &Path) -> Option<InputFormat> {
         match path_buf.extension().and_then(OsStr::to_str) {
             Some("ncl") => Some(InputFormat::Nickel),
             Some("json") => Some(InputFormat::Json),
             Some("yaml") | Some("yml") => Some(InputFormat::Yaml),
             Some("toml") => Some(InputFormat::Toml),
+            Some("txt") | Some("md") | Some("sh") | Some("pem") | Some("crt") => {
+                Some(InputFormat::Text)
+            }
             _ => None,
         }
     }
 }
 
+impl std::str::FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "nickel" | "ncl" => Ok(InputFormat::Nickel),
+            "json" => Ok(InputFormat::Json),
+            "yaml" | "yml" => Ok(InputFormat::Yaml),
+            "toml" => Ok(InputFormat::Toml),
+            "text" | "txt" => Ok(InputFormat::Text),
+            _ => Err(format!("unsupported input format {}", s)),
+        }
+    }
+}
+
 /// File and terms cache.
 ///
 /// Manage a file database, which stores a set of sources (the original source code as string) and
@@ -66,6 +87,8 @@ pub struct Cache {
     file_ids: HashMap<OsString, NameIdEntry>,
     /// Map containing for each FileIDs a list of files they import.
     imports: HashMap<FileId, HashSet<FileId>>,
+    /// The format each imported file has been parsed with, used to parse it again on reload.
+    import_formats: HashMap<FileId, InputFormat>,
     /// The table storing parsed terms corresponding to the entries of the file database.
     terms: HashMap<FileId, CachedTerm>,
     /// The list of ids corresponding to the stdlib modules
@@ -219,6 +242,7 @@ impl Cache {
             terms: HashMap::new(),
             wildcards: HashMap::new(),
             imports: HashMap::new(),
+            import_formats: HashMap::new(),
             stdlib_ids: None,
             error_tolerance,
 
@@ -453,15 +477,27 @@ impl Cache {
 
                 Ok((t, parse_errs))
             }
-            InputFormat::Json => serde_json::from_str(self.files.source(file_id))
-                .map(|t| (t, ParseErrors::default()))
-                .map_err(|err| ParseError::from_serde_json(err, file_id, &self.files)),
-            InputFormat::Yaml => serde_yaml::from_str(self.files.source(file_id))
-                .map(|t| (t, ParseErrors::default()))
-                .map_err(|err| (ParseError::from_serde_yaml(err, file_id))),
-            InputFormat::Toml => toml::from_str(self.files.source(file_id))
-                .map(|t| (t, ParseErrors::default()))
-                .map_err(|err| (ParseError::from_toml(err, file_id, &self.files))),
+            InputFormat::Json => {
+                parser::data::parse_json(&self.files, file_id).map(|t| (t, ParseErrors::default()))
+            }
+            InputFormat::Yaml => {
+                parser::data::parse_yaml(&self.files, file_id).map(|t| (t, ParseErrors::default()))
+            }
+            InputFormat::Toml => {
+                parser::data::parse_toml(&self.files, file_id).map(|t| (t, ParseErrors::default()))
+            }
+            InputFormat::Text => {
+                let pos = TermPos::Original(RawSpan {
+                    src_id: file_id,
+                    start: ByteIndex::from(0),
+                    end: ByteIndex::from(buf.len() as u32),
+                });
+
+                Ok((
+                    RichTerm::new(Term::Str(String::from(buf)), pos),
+                    ParseErrors::default(),
+                ))
+            }
         }
     }
 
@@ -762,6 +798,78 @@ impl Cache {
         Ok((term, pending))
     }
 
+    /// Reload the files which have been modified on disk since they were loaded, according to the
+    /// timestamps of the name-id table.
+    ///
+    /// As opposed to loading a file again with [Self::add_file], modified files are updated in
+    /// place and keep their `FileId`, so that the terms importing them stay valid and don't need
+    /// to be processed again. The corresponding entries of the term cache are cleared: reloaded
+    /// files must be processed again with [Self::prepare_reloaded]. Files which can't be read,
+    /// typically because they are being written, are ignored until the next call.
+    ///
+    /// Return the ids of the reloaded files.
+    pub fn reload_modified(&mut self) -> Vec<FileId> {
+        let mut reloaded = Vec::new();
+
+        for (path, entry) in self.file_ids.iter_mut() {
+            let old_timestamp = match entry.timestamp {
+                Some(ts) => ts,
+                None => continue,
+            };
+
+            let new_timestamp = match timestamp(path) {
+                Ok(ts) if ts != old_timestamp => ts,
+                _ => continue,
+            };
+
+            let mut buffer = String::new();
+            if fs::File::open(path)
+                .and_then(|mut file| file.read_to_string(&mut buffer))
+                .is_err()
+            {
+                continue;
+            }
+
+            self.files.update(entry.id, buffer);
+            self.terms.remove(&entry.id);
+            self.wildcards.remove(&entry.id);
+            entry.timestamp = Some(new_timestamp);
+            reloaded.push(entry.id);
+        }
+
+        reloaded
+    }
+
+    /// Parse, resolve the imports of, typecheck and transform files reloaded by
+    /// [Self::reload_modified]. Each phase is performed on all the files before the next one, as
+    /// reloaded files may import each other.
+    pub fn prepare_reloaded(
+        &mut self,
+        file_ids: &[FileId],
+        initial_ctxt: &typecheck::Context,
+    ) -> Result<(), Error> {
+        for file_id in file_ids {
+            let format = match self.import_formats.get(file_id) {
+                Some(format) => *format,
+                None => InputFormat::from_path_buf(Path::new(self.name(*file_id)))
+                    .unwrap_or(InputFormat::Nickel),
+            };
+            self.parse_multi(*file_id, format)?;
+        }
+
+        for file_id in file_ids {
+            self.resolve_imports(*file_id).map_err(|cache_err| {
+                cache_err.unwrap_error("cache::prepare_reloaded(): expected source to be parsed")
+            })?;
+        }
+
+        for file_id in file_ids {
+            self.prepare(*file_id, initial_ctxt)?;
+        }
+
+        Ok(())
+    }
+
     /// Retrieve the name of a source given an id.
     pub fn name(&self, file_id: FileId) -> &OsStr {
         self.files.name(file_id)
@@ -838,6 +946,34 @@ impl Cache {
             .copied()
     }
 
+    /// Return the files imported by an entry of the cache, in order of first appearance. Require
+    /// the imports of the entry to have been resolved.
+    ///
+    /// As opposed to the `imports` table, which only records an import for the first file
+    /// importing it, this returns every import of the entry, including imports of files which were
+    /// already in the cache.
+    pub fn direct_imports(&self, file_id: FileId) -> Vec<FileId> {
+        let mut imports = Vec::new();
+
+        if let Some(term) = self.get_owned(file_id) {
+            // The result is discarded: the traversal is only used to collect the imports
+            let _ = term.traverse(
+                &|rt: RichTerm, imports: &mut Vec<FileId>| -> Result<RichTerm, Void> {
+                    if let Term::ResolvedImport(id) = rt.as_ref() {
+                        if !imports.contains(id) {
+                            imports.push(*id);
+                        }
+                    }
+                    Ok(rt)
+                },
+                &mut imports,
+                TraverseOrder::TopDown,
+            );
+        }
+
+        imports
+    }
+
     /// Retrieve a fresh clone of a cached term.
     pub fn get_owned(&self, file_id: FileId) -> Option<RichTerm> {
         self.terms
@@ -1033,9 +1169,13 @@ pub trait ImportResolver {
     /// resolve nested imports relatively to this parent. Only after this processing the term is
     /// inserted back in the cache. On the other hand, if it has been resolved before, it is
     /// already transformed in the cache and do not need further processing.
+    ///
+    /// The format of the imported file is `format` if it is given explicitly, and is guessed from
+    /// the extension of `path` otherwise.
     fn resolve(
         &mut self,
         path: &OsStr,
+        format: Option<InputFormat>,
         parent: Option<PathBuf>,
         pos: &TermPos,
     ) -> Result<(ResolvedTerm, FileId), ImportError>;
@@ -1050,11 +1190,14 @@ impl ImportResolver for Cache {
     fn resolve(
         &mut self,
         path: &OsStr,
+        format: Option<InputFormat>,
         parent: Option<PathBuf>,
         pos: &TermPos,
     ) -> Result<(ResolvedTerm, FileId), ImportError> {
         let path_buf = with_parent(path, parent.clone());
-        let format = InputFormat::from_path_buf(&path_buf).unwrap_or(InputFormat::Nickel);
+        let format = format
+            .or_else(|| InputFormat::from_path_buf(&path_buf))
+            .unwrap_or(InputFormat::Nickel);
         let id_op = self.get_or_add_file(&path_buf).map_err(|err| {
             ImportError::IOError(
                 path_buf.to_string_lossy().into_owned(),
@@ -1079,6 +1222,7 @@ impl ImportResolver for Cache {
             }
         };
 
+        self.import_formats.insert(file_id, format);
         self.parse_multi(file_id, format)
             .map_err(|err| ImportError::ParseErrors(err, *pos))?;
 
@@ -1131,6 +1275,7 @@ pub mod resolvers {
         fn resolve(
             &mut self,
             _path: &OsStr,
+            _format: Option<InputFormat>,
             _parent: Option<PathBuf>,
             _pos: &TermPos,
         ) -> Result<(ResolvedTerm, FileId), ImportError> {
@@ -1176,6 +1321,7 @@ pub mod resolvers {
         fn resolve(
             &mut self,
             path: &OsStr,
+            _format: Option<InputFormat>,
             _parent: Option<PathBuf>,
             pos: &TermPos,
         ) -> Result<(ResolvedTerm, FileId), ImportError> {
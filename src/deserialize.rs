@@ -1,8 +1,8 @@
 //! Deserialization of an evaluated program to plain Rust types.
 
-use std::collections::HashMap;
 use std::iter::ExactSizeIterator;
 
+use indexmap::IndexMap;
 use serde::de::{
     Deserialize, DeserializeSeed, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
     VariantAccess, Visitor,
@@ -10,7 +10,7 @@ use serde::de::{
 
 use crate::identifier::Ident;
 use crate::term::array::{self, Array};
-use crate::term::{MetaValue, RichTerm, Term};
+use crate::term::{number::Number, MetaValue, RichTerm, Term};
 
 macro_rules! deserialize_number {
     ($method:ident, $type:tt, $visit:ident) => {
@@ -19,7 +19,8 @@ macro_rules! deserialize_number {
             V: Visitor<'de>,
         {
             match unwrap_term(self)? {
-                Term::Num(n) => visitor.$visit(n as $type),
+                Term::Num(Number::Int(n)) => visitor.$visit(n as $type),
+                Term::Num(Number::Float(n)) => visitor.$visit(n as $type),
                 other => Err(RustDeserializationError::InvalidType {
                     expected: "Num".to_string(),
                     occurred: other.type_of().unwrap_or_else(|| "Other".to_string()),
@@ -36,7 +37,8 @@ macro_rules! deserialize_number_round {
             V: Visitor<'de>,
         {
             match unwrap_term(self)? {
-                Term::Num(n) => visitor.$visit(n.round() as $type),
+                Term::Num(Number::Int(n)) => visitor.$visit(n as $type),
+                Term::Num(Number::Float(n)) => visitor.$visit(n.round() as $type),
                 other => Err(RustDeserializationError::InvalidType {
                     expected: "Num".to_string(),
                     occurred: other.type_of().unwrap_or_else(|| "Other".to_string()),
@@ -69,12 +71,25 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         match unwrap_term(self)? {
             Term::Null => visitor.visit_unit(),
             Term::Bool(v) => visitor.visit_bool(v),
-            Term::Num(v) => visitor.visit_f64(v),
+            Term::Num(Number::Int(n)) => {
+                if let Ok(n) = i64::try_from(n) {
+                    visitor.visit_i64(n)
+                } else if let Ok(n) = u64::try_from(n) {
+                    visitor.visit_u64(n)
+                } else {
+                    visitor.visit_i128(n)
+                }
+            }
+            Term::Num(Number::Float(v)) => visitor.visit_f64(v),
             Term::Str(v) => visitor.visit_string(v),
             Term::Enum(v) => visitor.visit_enum(EnumDeserializer {
                 variant: v.into_label(),
                 rich_term: None,
             }),
+            Term::EnumVariant(tag, arg) => visitor.visit_enum(EnumDeserializer {
+                variant: tag.into_label(),
+                rich_term: Some(arg),
+            }),
             Term::Record(record) => visit_record(record.fields, visitor),
             Term::Array(v, _) => visit_array(v, visitor),
             Term::MetaValue(_) => visitor.visit_unit(),
@@ -108,7 +123,8 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
         }
     }
 
-    /// deserialize `RichTerm::Enum` tags or `RichTerm::Record`s with a single item.
+    /// deserialize `RichTerm::Enum` tags, `RichTerm::EnumVariant`s or `RichTerm::Record`s with a
+    /// single item.
     fn deserialize_enum<V>(
         self,
         _name: &str,
@@ -120,6 +136,7 @@ impl<'de> serde::Deserializer<'de> for RichTerm {
     {
         let (variant, rich_term) = match unwrap_term(self)? {
             Term::Enum(ident) => (ident.into_label(), None),
+            Term::EnumVariant(tag, arg) => (tag.into_label(), Some(arg)),
             Term::Record(record) => {
                 let mut iter = record.fields.into_iter();
                 let (variant, value) = match iter.next() {
@@ -405,12 +422,12 @@ where
 }
 
 struct RecordDeserializer {
-    iter: <HashMap<Ident, RichTerm> as IntoIterator>::IntoIter,
+    iter: <IndexMap<Ident, RichTerm> as IntoIterator>::IntoIter,
     rich_term: Option<RichTerm>,
 }
 
 impl RecordDeserializer {
-    fn new(map: HashMap<Ident, RichTerm>) -> Self {
+    fn new(map: IndexMap<Ident, RichTerm>) -> Self {
         RecordDeserializer {
             iter: map.into_iter(),
             rich_term: None,
@@ -453,7 +470,7 @@ impl<'de> MapAccess<'de> for RecordDeserializer {
 }
 
 fn visit_record<'de, V>(
-    record: HashMap<Ident, RichTerm>,
+    record: IndexMap<Ident, RichTerm>,
     visitor: V,
 ) -> Result<V::Value, RustDeserializationError>
 where
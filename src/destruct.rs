@@ -3,9 +3,13 @@
 
 use crate::identifier::Ident;
 use crate::label::Label;
+use crate::mk_fun;
 use crate::position::RawSpan;
+use crate::term::make::{if_then_else, op1, op2};
 use crate::term::record::RecordData;
-use crate::term::{record::RecordAttrs, Contract, MetaValue, RichTerm, Term};
+use crate::term::{
+    number::Number, record::RecordAttrs, BinaryOp, Contract, MetaValue, RichTerm, Term, UnaryOp,
+};
 use crate::types::{TypeF, Types};
 
 /// A match field in a `Destruct` pattern.
@@ -35,20 +39,43 @@ pub enum LastMatch {
 /// A destructuring pattern without the `x @` part.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Destruct {
-    /// A record pattern, the only one implemented for now.
+    /// A record pattern.
     Record {
         matches: Vec<Match>,
         open: bool,
         rest: Option<Ident>,
         span: RawSpan,
     },
-    /// An array destructuring. Not implemented.
-    Array { matches: Vec<Match>, span: RawSpan },
+    /// An array pattern `[a, b, ..rest]`.
+    Array {
+        elems: Vec<(Option<Ident>, Destruct)>,
+        open: bool,
+        rest: Option<Ident>,
+        span: RawSpan,
+    },
+    /// A constant pattern: a number, a string, a boolean or `null`. Only supported in match
+    /// expressions.
+    Constant(RichTerm),
+    /// An enum tag pattern. Only supported in match expressions.
+    Enum(Ident),
+    /// An enum variant pattern `` `tag pat``, matching the value carried by the variant against
+    /// the sub-pattern. Only supported in match expressions.
+    EnumVariant(Ident, Box<(Option<Ident>, Destruct)>),
     /// An empty destructuring. In this case, the pattern is a clasical `let var = something in
     /// body` form.
     Empty,
 }
 
+/// A branch of a match expression, of the form `<pattern> if <guard> => <body>`. The pattern is
+/// either a variable, a wildcard `_` (a `None` identifier with an empty destructuring), or a
+/// destructuring pattern optionally bound to a variable with `x @ <pattern>`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct MatchBranch {
+    pub pattern: (Option<Ident>, Destruct),
+    pub guard: Option<RichTerm>,
+    pub body: RichTerm,
+}
+
 impl Destruct {
     /// generate the metavalue containing the contract representing this pattern.
     pub fn into_contract(self) -> MetaValue {
@@ -58,47 +85,62 @@ impl Destruct {
 
     fn into_contract_with_lbl(self, label: Label) -> MetaValue {
         let open = self.is_open();
+        let contract = match self {
+            Destruct::Array { elems, open, .. } => array_contract(elems.len(), open),
+            _ => Term::Record(RecordData::new(
+                self.inner()
+                    .into_iter()
+                    .map(|m| m.as_meta_field())
+                    .collect(),
+                RecordAttrs { open },
+                None,
+            ))
+            .into(),
+        };
+
         MetaValue {
             contracts: vec![Contract {
-                types: Types(TypeF::Flat(
-                    Term::Record(RecordData::new(
-                        self.inner()
-                            .into_iter()
-                            .map(|m| m.as_meta_field())
-                            .collect(),
-                        RecordAttrs { open },
-                        None,
-                    ))
-                    .into(),
-                )),
+                types: Types(TypeF::Flat(contract)),
                 label,
             }],
             ..Default::default()
         }
     }
 
-    /// Get the inner vector of `Matches` of the pattern. If `Empty` return a empty vector.
+    /// Get the inner vector of `Matches` of a record pattern. Otherwise, return a empty vector.
     pub fn inner(self) -> Vec<Match> {
         match self {
-            Destruct::Record { matches, .. } | Destruct::Array { matches, .. } => matches,
-            Destruct::Empty => vec![],
+            Destruct::Record { matches, .. } => matches,
+            Destruct::Array { .. }
+            | Destruct::Constant(_)
+            | Destruct::Enum(_)
+            | Destruct::EnumVariant(..)
+            | Destruct::Empty => {
+                vec![]
+            }
         }
     }
 
-    // Generate a label for this `Destruct`. if `Empty`, return default label.
+    // Generate a label for this `Destruct`. if it has no span, return default label.
     fn label(&self) -> Label {
         match *self {
             Destruct::Record { span, .. } | Destruct::Array { span, .. } => Label {
                 span,
                 ..Default::default()
             },
-            Destruct::Empty => Label::default(),
+            Destruct::Constant(_)
+            | Destruct::Enum(_)
+            | Destruct::EnumVariant(..)
+            | Destruct::Empty => Label::default(),
         }
     }
 
     /// Is this pattern open? Does it finish with `, ..}` form?
     pub fn is_open(&self) -> bool {
-        matches!(self, Destruct::Record { open: true, .. })
+        matches!(
+            self,
+            Destruct::Record { open: true, .. } | Destruct::Array { open: true, .. }
+        )
     }
 
     /// check if the pattern is empty.
@@ -121,7 +163,7 @@ impl Match {
             //
             // - extra field on the destructuring `d`
             // - missing field on the `id`
-            Match::Assign(id, m, (_, d @ Destruct::Record { .. })) => {
+            Match::Assign(id, m, (_, d @ (Destruct::Record { .. } | Destruct::Array { .. }))) => {
                 let label @ Label { span, .. } = d.label();
                 let span = RawSpan::fuse(id.pos.unwrap(), span).unwrap();
                 let label = Label { span, ..label };
@@ -130,7 +172,57 @@ impl Match {
                     Term::MetaValue(MetaValue::flatten(m, d.into_contract_with_lbl(label))).into(),
                 )
             }
-            Match::Assign(_id, _m, (_, _d @ Destruct::Array { .. })) => unimplemented!(),
+            // The grammar only accepts refutable patterns in match expressions, which don't
+            // generate contracts.
+            Match::Assign(
+                _id,
+                _m,
+                (_, Destruct::Constant(_) | Destruct::Enum(_) | Destruct::EnumVariant(..)),
+            ) => {
+                unreachable!("refutable patterns only appear in match expressions")
+            }
         }
     }
 }
+
+/// Generate the contract checking that a value is an array with `len` elements, or at least `len`
+/// elements if the pattern is `open`. The contracts of the sub-patterns are not part of this
+/// contract: they are applied to the elements when they are bound.
+fn array_contract(len: usize, open: bool) -> RichTerm {
+    let label = Ident::fresh();
+    let value = Ident::fresh();
+
+    let length = op1(UnaryOp::ArrayLength(), Term::Var(value));
+    let expected = Term::Num(Number::from(len));
+    let (length_ok, msg) = if open {
+        (
+            op2(BinaryOp::GreaterOrEq(), length, expected),
+            format!("expected an array of at least {len} elements"),
+        )
+    } else {
+        (
+            op2(BinaryOp::Eq(), length, expected),
+            format!("expected an array of {len} elements"),
+        )
+    };
+    let blame = |msg: String| {
+        op1(
+            UnaryOp::Blame(),
+            op2(BinaryOp::Tag(), Term::Str(msg), Term::Var(label)),
+        )
+    };
+
+    mk_fun!(
+        label,
+        value,
+        if_then_else(
+            op2(
+                BinaryOp::Eq(),
+                op1(UnaryOp::Typeof(), Term::Var(value)),
+                Term::Enum(Ident::from("Array")),
+            ),
+            if_then_else(length_ok, Term::Var(value), blame(msg)),
+            blame(String::from("expected an array")),
+        )
+    )
+}
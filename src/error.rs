@@ -4,9 +4,10 @@
 //! [codespan](https://crates.io/crates/codespan-reporting) diagnostic from them.
 use std::fmt::Write;
 
-use codespan::{FileId, Files};
-use codespan_reporting::diagnostic::{Diagnostic, Label, LabelStyle};
+use codespan::{ByteIndex, FileId, Files};
+use codespan_reporting::diagnostic::{Diagnostic, Label, LabelStyle, Severity};
 use lalrpop_util::ErrorRecovery;
+use serde::Serialize;
 
 use crate::{
     eval::callstack::CallStack,
@@ -113,6 +114,13 @@ pub enum EvalError {
     },
     /// A non-equatable term was compared for equality.
     EqError { eq_pos: TermPos, term: RichTerm },
+    /// No branch of a match expression matched the examined value.
+    NonExhaustiveMatch {
+        /// The examined value.
+        value: RichTerm,
+        /// The position of the match expression.
+        pos: TermPos,
+    },
     /// An unexpected internal error.
     InternalError(String, TermPos),
     /// Errors occurring rarely enough to not deserve a dedicated variant.
@@ -161,6 +169,13 @@ pub enum TypecheckError {
         /* the inferred/annotated type */ Types,
         TermPos,
     ),
+    /// An enum tag was expected to carry a value, but was bare, or the other way around.
+    PayloadMismatch(
+        Ident,
+        /* the expected type */ Types,
+        /* the inferred/annotated type */ Types,
+        TermPos,
+    ),
     /// A additional dynamic tail was not expected to be in the type of an expression.
     ExtraDynTail(
         /* the expected type */ Types,
@@ -244,6 +259,9 @@ pub enum TypecheckError {
         RichTerm, /* the inferred flat type */
         TermPos,
     ),
+    /// The patterns of a statically typed match expression don't cover all the values of the
+    /// type of the matched values.
+    NonExhaustiveMatch(TermPos),
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
@@ -363,6 +381,8 @@ pub enum ParseError {
     /// - a variable is used as both a record and enum row variable, e.g. in the
     ///   signature `forall r. [| ; r |] -> { ; r }`.
     TypeVariableKindMismatch { ty_var: Ident, span: RawSpan },
+    /// The format given to an import (`import "file" as `Format`) is not a supported input format.
+    InvalidImportFormat { format: Ident, span: RawSpan },
 }
 
 /// An error occurring during the resolution of an import.
@@ -386,10 +406,26 @@ pub enum ImportError {
 pub enum SerializationError {
     /// Encountered a null value for a format that doesn't support them.
     UnsupportedNull(ExportFormat, RichTerm),
+    /// Encountered an integer which is out of the range supported by a format, such as an integer
+    /// which doesn't fit in 64 bits for TOML.
+    UnsupportedNumber(ExportFormat, RichTerm),
     /// Tried exporting something else than a `Str` to raw format.
     NotAString(RichTerm),
+    /// Tried exporting something else than an `Array` to a YAML stream.
+    NotAnArray(RichTerm),
+    /// Tried exporting something else than a `Record` to a key-value format such as dotenv.
+    NotARecord(ExportFormat, RichTerm),
+    /// Encountered a value which isn't a scalar in a key-value format such as dotenv.
+    NotAScalar(ExportFormat, RichTerm),
+    /// Encountered a key which can't be written in a key-value format: an invalid dotenv variable
+    /// name, or a properties key defined twice once nested records are flattened.
+    InvalidKey(ExportFormat, String, RichTerm),
     /// A term contains constructs that cannot be serialized.
     NonSerializable(RichTerm),
+    /// The description of a multi-file export is ill-formed.
+    InvalidMultiExport(String, RichTerm),
+    /// A value can't be represented in XML.
+    InvalidXml(String, RichTerm),
     Other(String),
 }
 
@@ -525,6 +561,9 @@ impl ParseError {
                 InternalParseError::TypeVariableKindMismatch { ty_var, span } => {
                     ParseError::TypeVariableKindMismatch { ty_var, span }
                 }
+                InternalParseError::InvalidImportFormat { format, span } => {
+                    ParseError::InvalidImportFormat { format, span }
+                }
             },
         }
     }
@@ -558,13 +597,17 @@ impl ParseError {
         )
     }
 
-    pub fn from_serde_yaml(error: serde_yaml::Error, file_id: FileId) -> Self {
+    pub fn from_yaml(error: &yaml_rust::ScanError, file_id: FileId, files: &Files<String>) -> Self {
         use codespan::{ByteIndex, ByteOffset};
 
-        let start = error
-            .location()
-            .map(|loc| loc.index() as u32)
-            .map(ByteIndex::from);
+        // The positions of yaml-rust are character indices, not byte indices
+        let src = files.source(file_id);
+        let start = src
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(std::iter::once(src.len()))
+            .nth(error.marker().index())
+            .map(|offset| ByteIndex::from(offset as u32));
         ParseError::ExternalFormatError(
             String::from("yaml"),
             error.to_string(),
@@ -1051,6 +1094,22 @@ impl ToDiagnostic<FileId> for EvalError {
                     .with_message("cannot compare values for equality")
                     .with_labels(labels)]
             }
+            EvalError::NonExhaustiveMatch { value, pos } => {
+                let mut labels = Vec::new();
+
+                if let Some(span) = pos.into_opt() {
+                    labels.push(primary(&span).with_message("in this match expression"));
+                }
+
+                labels.push(
+                    secondary_term(value, files)
+                        .with_message("this value doesn't match any branch"),
+                );
+
+                vec![Diagnostic::error()
+                    .with_message("unmatched pattern")
+                    .with_labels(labels)]
+            }
             EvalError::IllegalPolymorphicTailAccess {
                 action,
                 label: l,
@@ -1447,6 +1506,14 @@ impl ToDiagnostic<FileId> for ParseError {
                     String::from("Type variables may be used either as types, polymorphic record tails, or polymorphic enum tails."),
                     String::from("Using the same variable as more than one of these is not permitted.")
                 ]),
+            ParseError::InvalidImportFormat { format, span } => Diagnostic::error()
+                .with_message(format!("unsupported import format `{}`", format))
+                .with_labels(vec![
+                    primary(span),
+                ])
+                .with_notes(vec![
+                    String::from("Supported formats are `Nickel, `Json, `Yaml, `Toml and `Text."),
+                ]),
         };
 
         vec![diagnostic]
@@ -1501,6 +1568,16 @@ impl ToDiagnostic<FileId> for TypecheckError {
                         format!("The type of the expression was inferred to be `{}`, which contains the extra field `{}`", actual, ident),
                     ])]
             ,
+            TypecheckError::PayloadMismatch(ident, expd, actual, span_opt) =>
+                vec![Diagnostic::error()
+                    .with_message(format!("type error: incompatible variants for the tag `{}`", ident))
+                    .with_labels(mk_expr_label(span_opt))
+                    .with_notes(vec![
+                        format!("The type of the expression was expected to be `{}`", expd),
+                        format!("The type of the expression was inferred to be `{}`", actual),
+                        format!("The tag `{}` carries a value in one of these types, but not in the other", ident),
+                    ])]
+            ,
             TypecheckError::ExtraDynTail(expd, actual, span_opt) =>
                 vec![Diagnostic::error()
                     .with_message(String::from("type error: extra dynamic tail `| Dyn`"))
@@ -1693,6 +1770,15 @@ impl ToDiagnostic<FileId> for TypecheckError {
                     ])
                 ]
             }
+            TypecheckError::NonExhaustiveMatch(span_opt) =>
+                vec![Diagnostic::error()
+                    .with_message("non-exhaustive match expression in statically typed code")
+                    .with_labels(mk_expr_label(span_opt))
+                    .with_notes(vec![
+                        String::from("The patterns of this match expression may not match some values of its argument type"),
+                        String::from("Add a last branch matching any value, such as `_ => ...`. If all the patterns are enum patterns, each tag needs a branch without guard matching all its values"),
+                    ])]
+            ,
         }
     }
 }
@@ -1748,12 +1834,89 @@ impl ToDiagnostic<FileId> for SerializationError {
                         .unwrap_or_else(|| String::from("<unevaluated>"))
                 ))
                 .with_labels(vec![primary_term(rt, files)])],
+            SerializationError::NotAnArray(rt) => vec![Diagnostic::error()
+                .with_message(format!(
+                    "yaml-stream export only supports `Array`, got {}",
+                    rt.as_ref()
+                        .type_of()
+                        .unwrap_or_else(|| String::from("<unevaluated>"))
+                ))
+                .with_labels(vec![primary_term(rt, files)])
+                .with_notes(vec![String::from(
+                    "each element of the array is exported as a separate YAML document",
+                )])],
+            SerializationError::NotARecord(format, rt) => vec![Diagnostic::error()
+                .with_message(format!(
+                    "{} export only supports `Record`, got {}",
+                    format,
+                    rt.as_ref()
+                        .type_of()
+                        .unwrap_or_else(|| String::from("<unevaluated>"))
+                ))
+                .with_labels(vec![primary_term(rt, files)])],
+            SerializationError::NotAScalar(format, rt) => {
+                let note = if *format == ExportFormat::Properties {
+                    "nested records are flattened, their fields being written with dotted keys"
+                } else {
+                    "nested records aren't supported"
+                };
+
+                vec![Diagnostic::error()
+                    .with_message(format!(
+                        "{} export only supports `Bool`, `Num`, `Str` and `Enum` values, got {}",
+                        format,
+                        rt.as_ref()
+                            .type_of()
+                            .unwrap_or_else(|| String::from("<unevaluated>"))
+                    ))
+                    .with_labels(vec![primary_term(rt, files)])
+                    .with_notes(vec![String::from(note)])]
+            }
+            SerializationError::InvalidKey(format, key, rt) => {
+                let (label, note) = if *format == ExportFormat::Properties {
+                    (
+                        "this key is already defined by another field",
+                        "nested records are flattened, their fields being written with dotted keys",
+                    )
+                } else {
+                    (
+                        "this key is not a valid variable name",
+                        "variable names are made of ASCII letters, digits and underscores, and can't start with a digit",
+                    )
+                };
+
+                vec![Diagnostic::error()
+                    .with_message(format!("invalid {} key `{}`", format, key))
+                    .with_labels(vec![primary_term(rt, files).with_message(label)])
+                    .with_notes(vec![String::from(note)])]
+            }
             SerializationError::UnsupportedNull(format, rt) => vec![Diagnostic::error()
                 .with_message(format!("{} doesn't support null values", format))
                 .with_labels(vec![primary_term(rt, files)])],
+            SerializationError::UnsupportedNumber(format, rt) => vec![Diagnostic::error()
+                .with_message(format!("{} doesn't support this number", format))
+                .with_labels(vec![primary_term(rt, files)])
+                .with_notes(vec![format!(
+                    "{} only supports integers between {} and {}",
+                    format,
+                    i64::MIN,
+                    i64::MAX
+                )])],
             SerializationError::NonSerializable(rt) => vec![Diagnostic::error()
                 .with_message("non serializable term")
                 .with_labels(vec![primary_term(rt, files)])],
+            SerializationError::InvalidMultiExport(msg, rt) => vec![Diagnostic::error()
+                .with_message("invalid multi-file export")
+                .with_labels(vec![primary_term(rt, files).with_message(msg.clone())])
+                .with_notes(vec![String::from(
+                    "expected a record of the form `{\"path/to/file\" = {format = `Yaml, content = ...}}`",
+                )])],
+            SerializationError::InvalidXml(msg, rt) => vec![Diagnostic::error()
+                .with_message("invalid XML export")
+                .with_labels(vec![primary_term(rt, files).with_message(msg.clone())])
+                .with_notes(vec![String::from(
+                    "fields starting with `@` are written as attributes, and the field `$text` as the text of the element",
+                )])],
             SerializationError::Other(msg) => vec![Diagnostic::error()
                 .with_message("error during serialization")
                 .with_notes(vec![msg.clone()])],
@@ -1802,3 +1965,235 @@ impl ToDiagnostic<FileId> for ReplError {
         }
     }
 }
+
+impl Error {
+    /// Return the kind of the error, as the path of the variant of the innermost error type, such
+    /// as `EvalError::BlameError` or `TypecheckError::TypeMismatch`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Error::EvalError(err) => err.kind(),
+            Error::TypecheckError(err) => err.kind(),
+            Error::ParseErrors(errs) => match errs.errors.as_slice() {
+                [err] => err.kind(),
+                _ => "ParseErrors",
+            },
+            Error::ImportError(err) => err.kind(),
+            Error::SerializationError(err) => err.kind(),
+            Error::IOError(_) => "IOError",
+            Error::ReplError(err) => err.kind(),
+        }
+    }
+}
+
+impl EvalError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            EvalError::BlameError { .. } => "EvalError::BlameError",
+            EvalError::MissingFieldDef(..) => "EvalError::MissingFieldDef",
+            EvalError::TypeError(..) => "EvalError::TypeError",
+            EvalError::ParseError(err) => err.kind(),
+            EvalError::NotAFunc(..) => "EvalError::NotAFunc",
+            EvalError::FieldMissing(..) => "EvalError::FieldMissing",
+            EvalError::NotEnoughArgs(..) => "EvalError::NotEnoughArgs",
+            EvalError::MergeIncompatibleArgs(..) => "EvalError::MergeIncompatibleArgs",
+            EvalError::UnboundIdentifier(..) => "EvalError::UnboundIdentifier",
+            EvalError::InfiniteRecursion(..) => "EvalError::InfiniteRecursion",
+            EvalError::SerializationError(err) => err.kind(),
+            EvalError::DeserializationError(..) => "EvalError::DeserializationError",
+            EvalError::IllegalPolymorphicTailAccess { .. } => {
+                "EvalError::IllegalPolymorphicTailAccess"
+            }
+            EvalError::EqError { .. } => "EvalError::EqError",
+            EvalError::NonExhaustiveMatch { .. } => "EvalError::NonExhaustiveMatch",
+            EvalError::InternalError(..) => "EvalError::InternalError",
+            EvalError::Other(..) => "EvalError::Other",
+        }
+    }
+}
+
+impl TypecheckError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TypecheckError::UnboundIdentifier(..) => "TypecheckError::UnboundIdentifier",
+            TypecheckError::MissingRow(..) => "TypecheckError::MissingRow",
+            TypecheckError::MissingDynTail(..) => "TypecheckError::MissingDynTail",
+            TypecheckError::ExtraRow(..) => "TypecheckError::ExtraRow",
+            TypecheckError::ExtraDynTail(..) => "TypecheckError::ExtraDynTail",
+            TypecheckError::PayloadMismatch(..) => "TypecheckError::PayloadMismatch",
+            TypecheckError::UnboundTypeVariable(..) => "TypecheckError::UnboundTypeVariable",
+            TypecheckError::TypeMismatch(..) => "TypecheckError::TypeMismatch",
+            TypecheckError::RowKindMismatch(..) => "TypecheckError::RowKindMismatch",
+            TypecheckError::RowMismatch(..) => "TypecheckError::RowMismatch",
+            TypecheckError::RowConflict(..) => "TypecheckError::RowConflict",
+            TypecheckError::ArrowTypeMismatch(..) => "TypecheckError::ArrowTypeMismatch",
+            TypecheckError::IncomparableFlatTypes(..) => "TypecheckError::IncomparableFlatTypes",
+            TypecheckError::NonExhaustiveMatch(..) => "TypecheckError::NonExhaustiveMatch",
+        }
+    }
+}
+
+impl ParseError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ParseError::UnexpectedEOF(..) => "ParseError::UnexpectedEOF",
+            ParseError::UnexpectedToken(..) => "ParseError::UnexpectedToken",
+            ParseError::ExtraToken(..) => "ParseError::ExtraToken",
+            ParseError::UnmatchedCloseBrace(..) => "ParseError::UnmatchedCloseBrace",
+            ParseError::InvalidEscapeSequence(..) => "ParseError::InvalidEscapeSequence",
+            ParseError::InvalidAsciiEscapeCode(..) => "ParseError::InvalidAsciiEscapeCode",
+            ParseError::ExternalFormatError(..) => "ParseError::ExternalFormatError",
+            ParseError::UnboundTypeVariables(..) => "ParseError::UnboundTypeVariables",
+            ParseError::InvalidUniRecord(..) => "ParseError::InvalidUniRecord",
+            ParseError::RecursiveLetPattern(..) => "ParseError::RecursiveLetPattern",
+            ParseError::TypeVariableKindMismatch { .. } => "ParseError::TypeVariableKindMismatch",
+            ParseError::InvalidImportFormat { .. } => "ParseError::InvalidImportFormat",
+        }
+    }
+}
+
+impl ImportError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ImportError::IOError(..) => "ImportError::IOError",
+            ImportError::ParseErrors(..) => "ImportError::ParseErrors",
+        }
+    }
+}
+
+impl SerializationError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SerializationError::UnsupportedNull(..) => "SerializationError::UnsupportedNull",
+            SerializationError::UnsupportedNumber(..) => "SerializationError::UnsupportedNumber",
+            SerializationError::NotAString(..) => "SerializationError::NotAString",
+            SerializationError::NotAnArray(..) => "SerializationError::NotAnArray",
+            SerializationError::NotARecord(..) => "SerializationError::NotARecord",
+            SerializationError::NotAScalar(..) => "SerializationError::NotAScalar",
+            SerializationError::InvalidKey(..) => "SerializationError::InvalidKey",
+            SerializationError::NonSerializable(..) => "SerializationError::NonSerializable",
+            SerializationError::InvalidMultiExport(..) => "SerializationError::InvalidMultiExport",
+            SerializationError::InvalidXml(..) => "SerializationError::InvalidXml",
+            SerializationError::Other(..) => "SerializationError::Other",
+        }
+    }
+}
+
+impl ReplError {
+    /// See [`Error::kind`].
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReplError::UnknownCommand(..) => "ReplError::UnknownCommand",
+            ReplError::MissingArg { .. } => "ReplError::MissingArg",
+        }
+    }
+}
+
+/// A diagnostic in a structured form, for tools consuming Nickel errors, such as editors or
+/// continuous integration. This is what `nickel --error-format json` reports.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StructuredDiagnostic {
+    /// The kind of the original error. See [`Error::kind`].
+    pub kind: String,
+    /// One of `bug`, `error`, `warning`, `note` or `help`.
+    pub severity: String,
+    pub message: String,
+    pub notes: Vec<String>,
+    pub labels: Vec<StructuredLabel>,
+}
+
+/// A label of a [`StructuredDiagnostic`], that is a message attached to a snippet of source code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StructuredLabel {
+    /// Primary labels locate the error, while secondary labels give additional context.
+    pub primary: bool,
+    pub message: String,
+    /// The name of the file, usually its path.
+    pub file: String,
+    /// The range of the snippet, in bytes from the beginning of the file.
+    pub range: std::ops::Range<usize>,
+    pub start: Option<Location>,
+    pub end: Option<Location>,
+}
+
+/// A position in a source file, as one-based line and column numbers. Columns are counted in
+/// characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl StructuredDiagnostic {
+    /// Convert a codespan diagnostic generated from an error of the given kind.
+    pub fn new(kind: &str, diagnostic: &Diagnostic<FileId>, files: &Files<String>) -> Self {
+        let location = |file_id, index: usize| {
+            files
+                .location(file_id, ByteIndex::from(index as u32))
+                .ok()
+                .map(|loc| Location {
+                    line: loc.line.to_usize() + 1,
+                    column: loc.column.to_usize() + 1,
+                })
+        };
+
+        StructuredDiagnostic {
+            kind: String::from(kind),
+            severity: match diagnostic.severity {
+                Severity::Bug => "bug",
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note => "note",
+                Severity::Help => "help",
+            }
+            .to_owned(),
+            message: diagnostic.message.clone(),
+            notes: diagnostic.notes.clone(),
+            labels: diagnostic
+                .labels
+                .iter()
+                .map(|label| StructuredLabel {
+                    primary: label.style == LabelStyle::Primary,
+                    message: label.message.clone(),
+                    file: files.name(label.file_id).to_string_lossy().into_owned(),
+                    range: label.range.clone(),
+                    start: location(label.file_id, label.range.start),
+                    end: location(label.file_id, label.range.end),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl Error {
+    /// Convert the error to a list of structured diagnostics. See
+    /// [`ToDiagnostic::to_diagnostic`] for the meaning of the arguments.
+    pub fn to_structured_diagnostics(
+        &self,
+        files: &mut Files<String>,
+        contract_id: Option<FileId>,
+    ) -> Vec<StructuredDiagnostic> {
+        let errors: Vec<(&'static str, Vec<Diagnostic<FileId>>)> = match self {
+            // Report the kind of each parse error
+            Error::ParseErrors(errs) => errs
+                .errors
+                .iter()
+                .map(|err| (err.kind(), err.to_diagnostic(files, contract_id)))
+                .collect(),
+            err => vec![(err.kind(), err.to_diagnostic(files, contract_id))],
+        };
+
+        errors
+            .iter()
+            .flat_map(|(kind, diagnostics)| {
+                diagnostics
+                    .iter()
+                    .map(|d| StructuredDiagnostic::new(kind, d, files))
+            })
+            .collect()
+    }
+}
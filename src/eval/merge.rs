@@ -13,6 +13,9 @@
 //! - Fields that are both in `r1` and `r2` are recursively merged: for a field `f`, the result
 //! contains the binding `f = r1.f & r2.f`
 //!
+//! The fields of the result are ordered as the fields of `r1`, followed by the fields of `r2` that
+//! are not in `r1`.
+//!
 //! As fields are recursively merged, merge needs to operate on any value, not only on records.
 //!
 //! ## On simple values
@@ -62,7 +65,7 @@ use crate::term::{
     BinaryOp, Contract, MetaValue, RichTerm, SharedTerm, Term,
 };
 use crate::transform::Closurizable;
-use std::collections::HashMap;
+use indexmap::IndexMap;
 
 /// Merging mode. Merging is used both to combine standard data and to apply contracts defined as
 /// records.
@@ -150,7 +153,7 @@ pub fn merge<C: Cache>(
             }
         }
         (Term::Num(n1), Term::Num(n2)) => {
-            if (n1 - n2).abs() < f64::EPSILON {
+            if n1 == n2 {
                 Ok(Closure::atomic_closure(RichTerm::new(
                     Term::Num(n1),
                     pos_op.into_inherited(),
@@ -229,6 +232,29 @@ pub fn merge<C: Cache>(
                 ))
             }
         }
+        // Merging two variants with the same tag merges their payloads
+        (Term::EnumVariant(i1, arg1), Term::EnumVariant(i2, arg2)) => {
+            if i1 == i2 {
+                let mut env = Environment::new();
+                let arg = merge_closurize(cache, &mut env, arg1, env1, arg2, env2);
+                Ok(Closure {
+                    body: RichTerm::new(Term::EnumVariant(i1, arg), pos_op.into_inherited()),
+                    env,
+                })
+            } else {
+                Err(EvalError::MergeIncompatibleArgs(
+                    RichTerm {
+                        term: SharedTerm::new(Term::EnumVariant(i1, arg1)),
+                        pos: pos1,
+                    },
+                    RichTerm {
+                        term: SharedTerm::new(Term::EnumVariant(i2, arg2)),
+                        pos: pos2,
+                    },
+                    pos_op,
+                ))
+            }
+        }
         (Term::Array(arr1, _attrs1), Term::Array(arr2, _attrs2))
             if arr1.is_empty() && arr2.is_empty() =>
         {
@@ -387,10 +413,23 @@ pub fn merge<C: Cache>(
                 });
             }
 
+            // The fields of the result come in the order of `r1`, followed by the fields which
+            // are only defined in `r2`.
+            let field_names: Vec<_> = r1
+                .fields
+                .keys()
+                .chain(
+                    r2.fields
+                        .keys()
+                        .filter(|field| !r1.fields.contains_key(*field)),
+                )
+                .cloned()
+                .collect();
+
             let hashmap::SplitResult {
-                left,
-                center,
-                right,
+                mut left,
+                mut center,
+                mut right,
             } = hashmap::split(r1.fields, r2.fields);
 
             match mode {
@@ -408,13 +447,7 @@ pub fn merge<C: Cache>(
                 _ => (),
             };
 
-            let field_names: Vec<_> = left
-                .keys()
-                .chain(center.keys())
-                .chain(right.keys())
-                .cloned()
-                .collect();
-            let mut m = HashMap::with_capacity(left.len() + center.len() + right.len());
+            let mut m = IndexMap::with_capacity(field_names.len());
             let mut env = Environment::new();
 
             // Merging recursive records is the one operation that may override recursive fields. To
@@ -426,19 +459,15 @@ pub fn merge<C: Cache>(
             // The fields in the intersection (center) need a slightly more general treatment to
             // correctly propagate the recursive values down each field: saturation. See
             // [crate::eval::lazy::Thunk::saturate].
-            m.extend(
-                left.into_iter()
-                    .map(|(field, t)| (field, revert_closurize(cache, t, &mut env, &env1))),
-            );
-            m.extend(
-                right
-                    .into_iter()
-                    .map(|(field, t)| (field, revert_closurize(cache, t, &mut env, &env2))),
-            );
-
-            for (field, (t1, t2)) in center.into_iter() {
-                m.insert(
-                    field,
+            for field in field_names.iter() {
+                let t = if let Some(t) = left.swap_remove(field) {
+                    revert_closurize(cache, t, &mut env, &env1)
+                } else if let Some(t) = right.swap_remove(field) {
+                    revert_closurize(cache, t, &mut env, &env2)
+                } else {
+                    // unwrap(): the fields of `field_names` are exactly the fields of the left,
+                    // center and right parts
+                    let (t1, t2) = center.swap_remove(field).unwrap();
                     fields_merge_closurize(
                         cache,
                         &mut env,
@@ -447,8 +476,10 @@ pub fn merge<C: Cache>(
                         t2,
                         &env2,
                         field_names.iter(),
-                    )?,
-                );
+                    )?
+                };
+
+                m.insert(*field, t);
             }
 
             let final_pos = if mode == MergeMode::Standard {
@@ -668,27 +699,27 @@ fn revert_closurize<C: Cache>(
 }
 
 pub mod hashmap {
-    use std::collections::HashMap;
+    use indexmap::IndexMap;
 
     pub struct SplitResult<K, V1, V2> {
-        pub left: HashMap<K, V1>,
-        pub center: HashMap<K, (V1, V2)>,
-        pub right: HashMap<K, V2>,
+        pub left: IndexMap<K, V1>,
+        pub center: IndexMap<K, (V1, V2)>,
+        pub right: IndexMap<K, V2>,
     }
 
-    /// Split two hashmaps m1 and m2 in three parts (left,center,right), where left holds bindings
+    /// Split two maps m1 and m2 in three parts (left,center,right), where left holds bindings
     /// `(key,value)` where key is not in `m2.keys()`, right is the dual (keys of m2 that are not
     /// in m1), and center holds bindings for keys that are both in m1 and m2.
-    pub fn split<K, V1, V2>(m1: HashMap<K, V1>, m2: HashMap<K, V2>) -> SplitResult<K, V1, V2>
+    pub fn split<K, V1, V2>(m1: IndexMap<K, V1>, m2: IndexMap<K, V2>) -> SplitResult<K, V1, V2>
     where
         K: std::hash::Hash + Eq,
     {
-        let mut left = HashMap::new();
-        let mut center = HashMap::new();
+        let mut left = IndexMap::new();
+        let mut center = IndexMap::new();
         let mut right = m2;
 
         for (key, value) in m1 {
-            if let Some(v2) = right.remove(&key) {
+            if let Some(v2) = right.swap_remove(&key) {
                 center.insert(key, (value, v2));
             } else {
                 left.insert(key, value);
@@ -708,8 +739,8 @@ pub mod hashmap {
 
         #[test]
         fn all_left() -> Result<(), String> {
-            let mut m1 = HashMap::new();
-            let m2 = HashMap::<isize, isize>::new();
+            let mut m1 = IndexMap::new();
+            let m2 = IndexMap::<isize, isize>::new();
 
             m1.insert(1, 1);
             let SplitResult {
@@ -718,7 +749,7 @@ pub mod hashmap {
                 right,
             } = split(m1, m2);
 
-            if left.remove(&1) == Some(1)
+            if left.swap_remove(&1) == Some(1)
                 && left.is_empty()
                 && center.is_empty()
                 && right.is_empty()
@@ -731,8 +762,8 @@ pub mod hashmap {
 
         #[test]
         fn all_right() -> Result<(), String> {
-            let m1 = HashMap::<isize, isize>::new();
-            let mut m2 = HashMap::new();
+            let m1 = IndexMap::<isize, isize>::new();
+            let mut m2 = IndexMap::new();
 
             m2.insert(1, 1);
             let SplitResult {
@@ -741,7 +772,7 @@ pub mod hashmap {
                 mut right,
             } = split(m1, m2);
 
-            if right.remove(&1) == Some(1)
+            if right.swap_remove(&1) == Some(1)
                 && right.is_empty()
                 && left.is_empty()
                 && center.is_empty()
@@ -756,8 +787,8 @@ pub mod hashmap {
 
         #[test]
         fn all_center() -> Result<(), String> {
-            let mut m1 = HashMap::new();
-            let mut m2 = HashMap::new();
+            let mut m1 = IndexMap::new();
+            let mut m2 = IndexMap::new();
 
             m1.insert(1, 1);
             m2.insert(1, 2);
@@ -767,7 +798,7 @@ pub mod hashmap {
                 right,
             } = split(m1, m2);
 
-            if center.remove(&1) == Some((1, 2))
+            if center.swap_remove(&1) == Some((1, 2))
                 && center.is_empty()
                 && left.is_empty()
                 && right.is_empty()
@@ -782,8 +813,8 @@ pub mod hashmap {
 
         #[test]
         fn mixed() -> Result<(), String> {
-            let mut m1 = HashMap::new();
-            let mut m2 = HashMap::new();
+            let mut m1 = IndexMap::new();
+            let mut m2 = IndexMap::new();
 
             m1.insert(1, 1);
             m1.insert(2, 1);
@@ -795,9 +826,9 @@ pub mod hashmap {
                 mut right,
             } = split(m1, m2);
 
-            if left.remove(&2) == Some(1)
-                && center.remove(&1) == Some((1, -1))
-                && right.remove(&3) == Some(-1)
+            if left.swap_remove(&2) == Some(1)
+                && center.swap_remove(&1) == Some((1, -1))
+                && right.swap_remove(&3) == Some(-1)
                 && left.is_empty()
                 && center.is_empty()
                 && right.is_empty()
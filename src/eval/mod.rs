@@ -116,6 +116,7 @@ use operation::OperationCont;
 use stack::Stack;
 
 use self::cache::{Cache, CacheIndex};
+use std::collections::HashMap;
 
 impl AsRef<Vec<StackElem>> for CallStack {
     fn as_ref(&self) -> &Vec<StackElem> {
@@ -230,6 +231,109 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
         self.eval_closure(Closure::atomic_closure(wrapper), initial_env)
     }
 
+    /// Same as [VirtualMachine::eval_full], but evaluates a closure.
+    pub fn eval_full_closure(
+        &mut self,
+        clos: Closure,
+        initial_env: &Environment,
+    ) -> Result<RichTerm, EvalError> {
+        let Closure { body, env } = clos;
+        let wrapper = mk_term::op1(UnaryOp::Force(None), body);
+        self.eval_closure(Closure { body: wrapper, env }, initial_env)
+            .map(|(term, env)| subst(&self.cache, term, initial_env, &env))
+    }
+
+    /// Fully evaluate a Nickel term like [VirtualMachine::eval_full], but keep the type annotation
+    /// and the contracts of record fields and array elements around their value, as metavalues.
+    /// The value itself is computed by [VirtualMachine::eval_full_closure]: the annotations are
+    /// only kept for the serializer, which exports strings annotated with `string.Datetime` as
+    /// native TOML datetimes. The annotations aren't closurized, so the result must not be
+    /// evaluated further.
+    pub fn eval_full_annotated(
+        &mut self,
+        t0: RichTerm,
+        initial_env: &Environment,
+    ) -> Result<RichTerm, EvalError> {
+        let (rt, env) = self.eval_closure(Closure::atomic_closure(t0), initial_env)?;
+        let clos = Closure { body: rt, env };
+        let annots = self.collect_annotations(clos.clone(), initial_env)?;
+        let value = self.eval_full_closure(clos, initial_env)?;
+        Ok(annots.apply(value))
+    }
+
+    /// Collect the annotations of the record fields, array elements and enum variant arguments of
+    /// a closure, recursively. The annotations of a record's fields are collected before
+    /// evaluating any of them, as evaluating a field may update the thunks of other fields.
+    fn collect_annotations(
+        &mut self,
+        clos: Closure,
+        initial_env: &Environment,
+    ) -> Result<Annotations, EvalError> {
+        let (rt, env) = self.eval_closure(clos, initial_env)?;
+
+        let annotated = |t: &RichTerm| (t.clone(), field_annotations(&self.cache, t, &env));
+
+        match rt.as_ref() {
+            Term::Record(record) => {
+                let fields: Vec<_> = record
+                    .fields
+                    .iter()
+                    .filter(|(_, t)| !is_empty_optional(&self.cache, t, &env))
+                    .map(|(id, t)| (*id, annotated(t)))
+                    .collect();
+
+                fields
+                    .into_iter()
+                    .map(|(id, (body, meta))| {
+                        let clos = Closure {
+                            body,
+                            env: env.clone(),
+                        };
+                        Ok((id, (meta, self.collect_annotations(clos, initial_env)?)))
+                    })
+                    .collect::<Result<_, EvalError>>()
+                    .map(Annotations::Record)
+            }
+            Term::Array(ts, _) => {
+                let elts: Vec<_> = ts.iter().map(annotated).collect();
+
+                elts.into_iter()
+                    .map(|(body, meta)| {
+                        let clos = Closure {
+                            body,
+                            env: env.clone(),
+                        };
+                        Ok((meta, self.collect_annotations(clos, initial_env)?))
+                    })
+                    .collect::<Result<_, EvalError>>()
+                    .map(Annotations::Array)
+            }
+            Term::EnumVariant(_, arg) => {
+                let clos = Closure {
+                    body: arg.clone(),
+                    env,
+                };
+                self.collect_annotations(clos, initial_env)
+                    .map(|annots| Annotations::EnumVariant(Box::new(annots)))
+            }
+            _ => Ok(Annotations::None),
+        }
+    }
+
+    /// Evaluate a closure to a weak head normal form, but stop at a top-level meta value without
+    /// unwrapping it. Return the result together with its environment, which can be used to
+    /// evaluate the components of the meta value, such as contracts, later.
+    pub fn eval_meta_closure(
+        &mut self,
+        clos: Closure,
+        initial_env: &Environment,
+    ) -> Result<(RichTerm, Environment), EvalError> {
+        self.eval_mode = EvalMode::StopAtMeta;
+        let result = self.eval_closure(clos, initial_env);
+        self.eval_mode = Default::default();
+        result
+    }
+
     /// Evaluate a Nickel Term, stopping when a meta value is encountered at the top-level without
     /// unwrapping it. Then evaluate the underlying value, and substitute variables in order to obtain
     /// a WHNF that is printable.
@@ -575,7 +679,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         ));
                     }
                 }
-                Term::Import(path) => {
+                Term::Import(path, _) => {
                     return Err(EvalError::InternalError(
                         format!("Unresolved import ({})", path.to_string_lossy()),
                         pos,
@@ -682,7 +786,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         self.stack.push_arg(
                             Closure {
                                 body: RichTerm::new(
-                                    Term::Record(RecordData::with_fields(cases.clone())),
+                                    Term::Record(RecordData::with_fields(
+                                        cases.iter().map(|(id, t)| (*id, t.clone())).collect(),
+                                    )),
                                     pos,
                                 ),
                                 env: env.clone(),
@@ -860,7 +966,7 @@ pub fn subst<C: Cache>(
         | v @ Term::Lbl(_)
         | v @ Term::SealingKey(_)
         | v @ Term::Enum(_)
-        | v @ Term::Import(_)
+        | v @ Term::Import(..)
         | v @ Term::ResolvedImport(_) => RichTerm::new(v, pos),
         Term::Let(id, t1, t2, attrs) => {
             let t1 = subst(cache, t1, initial_env, env);
@@ -870,6 +976,7 @@ pub fn subst<C: Cache>(
         }
         p @ Term::LetPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
         p @ Term::FunPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
+        p @ Term::MatchPattern(..) => panic!("Pattern {:?} has not been transformed before evaluation", p),
         Term::App(t1, t2) => {
             let t1 = subst(cache, t1, initial_env, env);
             let t2 = subst(cache, t2, initial_env, env);
@@ -891,6 +998,11 @@ pub fn subst<C: Cache>(
 
             RichTerm::new(Term::Match {cases, default}, pos)
         }
+        Term::EnumVariant(tag, t) => {
+            let t = subst(cache, t, initial_env, env);
+
+            RichTerm::new(Term::EnumVariant(tag, t), pos)
+        }
         Term::Op1(op, t) => {
             let t = subst(cache, t, initial_env, env);
 
@@ -1069,5 +1181,115 @@ pub fn is_empty_optional<C: Cache>(cache: &C, rt: &RichTerm, env: &Environment)
     is_empty_optional_aux(cache, rt, env, false, &mut 8)
 }
 
+/// The annotations of the components of a fully evaluated value, as collected by
+/// [`VirtualMachine::eval_full_annotated`]. Each record field and array element comes with its own
+/// annotations, if any, and with the annotations of its components.
+enum Annotations {
+    Record(HashMap<Ident, (Option<MetaValue>, Annotations)>),
+    Array(Vec<(Option<MetaValue>, Annotations)>),
+    EnumVariant(Box<Annotations>),
+    None,
+}
+
+impl Annotations {
+    /// Wrap the components of a fully evaluated value in metavalues holding their annotations.
+    fn apply(self, rt: RichTerm) -> RichTerm {
+        fn annotate(rt: RichTerm, meta: Option<MetaValue>, annots: Annotations) -> RichTerm {
+            let rt = annots.apply(rt);
+
+            match meta {
+                Some(meta) => {
+                    let pos = rt.pos;
+                    let meta = MetaValue {
+                        value: Some(rt),
+                        ..meta
+                    };
+                    RichTerm::new(Term::MetaValue(meta), pos)
+                }
+                None => rt,
+            }
+        }
+
+        let pos = rt.pos;
+
+        match (self, rt.term.into_owned()) {
+            (Annotations::Record(mut annots), Term::Record(record)) => {
+                let fields = record
+                    .fields
+                    .into_iter()
+                    .map(|(id, t)| match annots.remove(&id) {
+                        Some((meta, annots)) => (id, annotate(t, meta, annots)),
+                        None => (id, t),
+                    })
+                    .collect();
+
+                RichTerm::new(Term::Record(RecordData { fields, ..record }), pos)
+            }
+            (Annotations::Array(annots), Term::Array(ts, attrs)) => {
+                let ts = ts
+                    .into_iter()
+                    .zip(annots)
+                    .map(|(t, (meta, annots))| annotate(t, meta, annots))
+                    .collect();
+
+                RichTerm::new(Term::Array(ts, attrs), pos)
+            }
+            (Annotations::EnumVariant(annots), Term::EnumVariant(tag, arg)) => {
+                RichTerm::new(Term::EnumVariant(tag, annots.apply(arg)), pos)
+            }
+            (_, term) => RichTerm::new(term, pos),
+        }
+    }
+}
+
+/// Collect the type annotation and the contracts of a record field or an array element, following
+/// metavalues and variables. Used by [`VirtualMachine::eval_full_annotated`]. Return `None` if the
+/// field isn't annotated.
+///
+/// As [`is_empty_optional`], this function is a quick and terminating peek: it only follows a
+/// limited number of variables.
+pub fn field_annotations<C: Cache>(
+    cache: &C,
+    rt: &RichTerm,
+    env: &Environment,
+) -> Option<MetaValue> {
+    fn field_annotations_aux<C: Cache>(
+        cache: &C,
+        rt: &RichTerm,
+        env: &Environment,
+        annots: &mut MetaValue,
+        gas: &mut u8,
+    ) {
+        match rt.as_ref() {
+            Term::MetaValue(meta) => {
+                if annots.types.is_none() {
+                    annots.types = meta.types.clone();
+                }
+                annots.contracts.extend(meta.contracts.iter().cloned());
+
+                if let Some(ref next) = meta.value {
+                    field_annotations_aux(cache, next, env, annots, gas)
+                }
+            }
+            // The share normal form transformation binds the value of a metavalue to a variable
+            // using a let-binding around the metavalue.
+            Term::Let(_, _, body, _) => field_annotations_aux(cache, body, env, annots, gas),
+            Term::Var(id) if *gas > 0 => {
+                if let Some(index) = env.get(id) {
+                    cache.get_then(index.clone(), |clos| {
+                        *gas -= 1;
+                        field_annotations_aux(cache, &clos.body, &clos.env, annots, gas)
+                    })
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let mut annots = MetaValue::new();
+    field_annotations_aux(cache, rt, env, &mut annots, &mut 8);
+    (annots.types.is_some() || !annots.contracts.is_empty()).then_some(annots)
+}
+
 #[cfg(test)]
 mod tests;
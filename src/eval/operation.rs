@@ -26,6 +26,7 @@ use crate::{
     term::{
         array::{Array, ArrayAttrs},
         make as mk_term,
+        number::Number,
         record::{self, RecordData},
         BinaryOp, MergePriority, MetaValue, NAryOp, PendingContract, RichTerm, SharedTerm,
         StrChunk, Term, UnaryOp,
@@ -197,7 +198,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Term::Num(_) => "Num",
                     Term::Bool(_) => "Bool",
                     Term::Str(_) => "Str",
-                    Term::Enum(_) => "Enum",
+                    Term::Enum(_) | Term::EnumVariant(..) => "Enum",
                     Term::Fun(..) | Term::Match { .. } => "Fun",
                     Term::Array(..) => "Array",
                     Term::Record(..) | Term::RecRecord(..) => "Record",
@@ -290,11 +291,14 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
             },
             UnaryOp::Embed(_id) => {
-                if let Term::Enum(_) = &*t {
-                    Ok(Closure::atomic_closure(RichTerm {
-                        term: t,
-                        pos: pos_op_inh,
-                    }))
+                if let Term::Enum(_) | Term::EnumVariant(..) = &*t {
+                    Ok(Closure {
+                        body: RichTerm {
+                            term: t,
+                            pos: pos_op_inh,
+                        },
+                        env,
+                    })
                 } else {
                     Err(EvalError::TypeError(
                         String::from("Enum"),
@@ -341,26 +345,17 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: cases_env,
                         })
                         .or(default)
-                        .ok_or_else(||
-                        // ? We should have a dedicated error for unmatched pattern
-                        EvalError::TypeError(
-                            String::from("Enum"),
-                            String::from("match"),
-                            arg_pos,
-                            RichTerm {
-                                term: t,
-                                pos,
-                            },
-                        ))
+                        .ok_or_else(|| EvalError::NonExhaustiveMatch {
+                            value: RichTerm { term: t, pos },
+                            pos: pos_op,
+                        })
                 } else if let Some(clos) = default {
                     Ok(clos)
                 } else {
-                    Err(EvalError::TypeError(
-                        String::from("Enum"),
-                        String::from("match"),
-                        arg_pos,
-                        RichTerm { term: t, pos },
-                    ))
+                    Err(EvalError::NonExhaustiveMatch {
+                        value: RichTerm { term: t, pos },
+                        pos: pos_op,
+                    })
                 }
             }
             UnaryOp::ChangePolarity() => match_sharedterm! {t, with {
@@ -581,6 +576,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     .ok_or_else(|| EvalError::NotEnoughArgs(2, String::from("generate"), pos_op))?;
 
                 if let Term::Num(n) = *t {
+                    let n = n.to_f64();
                     let n_int = n as usize;
                     if n < 0.0 || n.fract() != 0.0 {
                         Err(EvalError::Other(
@@ -599,7 +595,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         // currently, variables).
                         let ts = (0..n_int)
                             .map(|n| {
-                                mk_app!(f_as_var.clone(), Term::Num(n as f64)).closurize(
+                                mk_app!(f_as_var.clone(), Term::Num(n.into())).closurize(
                                     &mut self.cache,
                                     &mut shared_env,
                                     env.clone(),
@@ -748,6 +744,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                             env: shared_env,
                         })
                     }
+                    Term::EnumVariant(_, arg) => Ok(Closure {
+                        body: seq_terms(std::iter::once((None, arg)), pos_op),
+                        env,
+                    }),
                     _ => {
                         if let Some((next, ..)) = self.stack.pop_arg(&self.cache) {
                             Ok(next)
@@ -806,7 +806,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 if let Term::Array(ts, _) = &*t {
                     // A num does not have any free variable so we can drop the environment
                     Ok(Closure {
-                        body: RichTerm::new(Term::Num(ts.len() as f64), pos_op_inh),
+                        body: RichTerm::new(Term::Num(ts.len().into()), pos_op_inh),
                         env: Environment::new(),
                     })
                 } else {
@@ -903,9 +903,9 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             UnaryOp::CharCode() => {
                 if let Term::Str(s) = &*t {
                     if s.len() == 1 {
-                        let code = (s.chars().next().unwrap() as u32) as f64;
+                        let code = s.chars().next().unwrap() as u32;
                         Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Num(code),
+                            Term::Num(code.into()),
                             pos_op_inh,
                         )))
                     } else {
@@ -925,6 +925,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::CharFromCode() => {
                 if let Term::Num(code) = *t {
+                    let code = code.to_f64();
                     if code.fract() != 0.0 {
                         Err(EvalError::Other(format!("charFromCode: expected the agument to be an integer, got the floating-point value {}", code), pos_op))
                     } else if code < 0.0 || code > (u32::MAX as f64) {
@@ -983,7 +984,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 if let Term::Str(s) = &*t {
                     let length = s.graphemes(true).count();
                     Ok(Closure::atomic_closure(RichTerm::new(
-                        Term::Num(length as f64),
+                        Term::Num(length.into()),
                         pos_op_inh,
                     )))
                 } else {
@@ -1015,7 +1016,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             }
             UnaryOp::NumFromStr() => {
                 if let Term::Str(s) = &*t {
-                    let n = s.parse::<f64>().map_err(|_| {
+                    let n = s.parse::<Number>().map_err(|_| {
                         EvalError::Other(format!("numFrom: invalid num literal `{}`", s), pos)
                     })?;
                     Ok(Closure::atomic_closure(RichTerm::new(
@@ -1046,6 +1047,39 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     ))
                 }
             }
+            UnaryOp::EnumIsVariant() => match &*t {
+                Term::Enum(_) | Term::EnumVariant(..) => Ok(Closure::atomic_closure(
+                    RichTerm::new(Term::Bool(matches!(&*t, Term::EnumVariant(..))), pos_op_inh),
+                )),
+                _ => Err(EvalError::TypeError(
+                    String::from("Enum"),
+                    String::from("enum_is_variant"),
+                    arg_pos,
+                    RichTerm { term: t, pos },
+                )),
+            },
+            UnaryOp::EnumGetTag() => match &*t {
+                Term::Enum(tag) | Term::EnumVariant(tag, _) => Ok(Closure::atomic_closure(
+                    RichTerm::new(Term::Enum(*tag), pos_op_inh),
+                )),
+                _ => Err(EvalError::TypeError(
+                    String::from("Enum"),
+                    String::from("enum_get_tag"),
+                    arg_pos,
+                    RichTerm { term: t, pos },
+                )),
+            },
+            UnaryOp::EnumUnwrapVariant() => match_sharedterm! {t, with {
+                    Term::EnumVariant(_, arg) => Ok(Closure { body: arg, env }),
+                } else {
+                    Err(EvalError::TypeError(
+                        String::from("Enum variant"),
+                        String::from("enum_unwrap_variant"),
+                        arg_pos,
+                        RichTerm { term: t, pos },
+                    ))
+                }
+            },
             UnaryOp::StrIsMatch() => {
                 if let Term::Str(s) = &*t {
                     let re = regex::Regex::new(s)
@@ -1130,7 +1164,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
 
                         mk_record!(
                             ("matched", Term::Str(String::from(first_match.as_str()))),
-                            ("index", Term::Num(first_match.start() as f64)),
+                            ("index", Term::Num(first_match.start().into())),
                             (
                                 "groups",
                                 Term::Array(groups, ArrayAttrs::new().closurized())
@@ -1140,7 +1174,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                         //FIXME: what should we return when there's no match?
                         mk_record!(
                             ("matched", Term::Str(String::new())),
-                            ("index", Term::Num(-1.)),
+                            ("index", Term::Num(Number::Int(-1))),
                             (
                                 "groups",
                                 Term::Array(Array::default(), ArrayAttrs::default())
@@ -1220,6 +1254,20 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                                 body: seq_terms(terms, pos_op, cont),
                                 env: shared_env,
                             })
+                        },
+                        Term::EnumVariant(tag, arg) => {
+                            let mut shared_env = Environment::new();
+                            let arg = mk_term::op1(UnaryOp::Force(None), arg)
+                                .closurize(&mut self.cache, &mut shared_env, env);
+                            let cont = RichTerm::new(
+                                Term::EnumVariant(tag, arg.clone()),
+                                pos.into_inherited(),
+                            );
+
+                            Ok(Closure {
+                                body: seq_terms(std::iter::once(arg), pos_op, cont),
+                                env: shared_env,
+                            })
                         }
                     } else Ok(Closure {
                         body: RichTerm { term : t, pos},
@@ -1426,7 +1474,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             BinaryOp::Div() => {
                 if let Term::Num(n1) = *t1 {
                     if let Term::Num(n2) = *t2 {
-                        if n2 == 0.0 {
+                        if n2.is_zero() {
                             Err(EvalError::Other(String::from("division by zero"), pos_op))
                         } else {
                             Ok(Closure::atomic_closure(RichTerm::new(
@@ -1491,7 +1539,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                 if let Term::Num(n1) = *t1 {
                     if let Term::Num(n2) = *t2 {
                         Ok(Closure::atomic_closure(RichTerm::new(
-                            Term::Num(n1.powf(n2)),
+                            Term::Num(n1.pow(n2)),
                             pos_op_inh,
                         )))
                     } else {
@@ -1982,7 +2030,7 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
                     Term::Str(id) => match_sharedterm! {t2, with {
                             Term::Record(record) => {
                                 let mut fields = record.fields;
-                                let fetched = fields.remove(&Ident::from(&id));
+                                let fetched = fields.shift_remove(&Ident::from(&id));
                                 if fetched.is_none()
                                    || matches!(fetched, Some(t) if is_empty_optional(&self.cache, &t, &env2)) {
                                     Err(EvalError::FieldMissing(
@@ -2149,10 +2197,11 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
             },
             BinaryOp::ArrayElemAt() => match (&*t1, &*t2) {
                 (Term::Array(ts, attrs), Term::Num(n)) => {
-                    let n_int = *n as usize;
+                    let n = n.to_f64();
+                    let n_int = n as usize;
                     if n.fract() != 0.0 {
                         Err(EvalError::Other(format!("elemAt: expected the 2nd agument to be an integer, got the floating-point value {}", n), pos_op))
-                    } else if *n < 0.0 || n_int >= ts.len() {
+                    } else if n < 0.0 || n_int >= ts.len() {
                         Err(EvalError::Other(format!("elemAt: index out of bounds. Expected a value between 0 and {}, got {}", ts.len(), n), pos_op))
                     } else {
                         let elem_with_ctr = apply_contracts(
@@ -2547,8 +2596,10 @@ impl<R: ImportResolver, C: Cache> VirtualMachine<R, C> {
 
                 match (&*fst, &*snd, &*thd) {
                     (Term::Str(s), Term::Num(start), Term::Num(end)) => {
-                        let start_int = *start as usize;
-                        let end_int = *end as usize;
+                        let start = start.to_f64();
+                        let end = end.to_f64();
+                        let start_int = start as usize;
+                        let end_int = end as usize;
 
                         if start.fract() != 0.0 {
                             Err(EvalError::Other(format!("substring: expected the 2nd agument (start) to be an integer, got the floating-point value {}", start), pos_op))
@@ -3098,6 +3149,19 @@ fn eq<C: Cache>(
         (Term::Lbl(l1), Term::Lbl(l2)) => Ok(EqResult::Bool(l1 == l2)),
         (Term::SealingKey(s1), Term::SealingKey(s2)) => Ok(EqResult::Bool(s1 == s2)),
         (Term::Enum(id1), Term::Enum(id2)) => Ok(EqResult::Bool(id1 == id2)),
+        (Term::EnumVariant(id1, arg1), Term::EnumVariant(id2, arg2)) => {
+            if id1 == id2 {
+                Ok(gen_eqs(
+                    cache,
+                    std::iter::once((arg1, arg2)),
+                    env,
+                    env1,
+                    env2,
+                ))
+            } else {
+                Ok(EqResult::Bool(false))
+            }
+        }
         (Term::Record(r1), Term::Record(r2)) => {
             let merge::hashmap::SplitResult {
                 left,
@@ -3244,11 +3308,11 @@ mod tests {
         let mut vm: VirtualMachine<DummyResolver, EC> = VirtualMachine::new(DummyResolver {});
 
         vm.stack.push_arg(
-            Closure::atomic_closure(Term::Num(5.0).into()),
+            Closure::atomic_closure(Term::Num(Number::from(5.0)).into()),
             TermPos::None,
         );
         vm.stack.push_arg(
-            Closure::atomic_closure(Term::Num(46.0).into()),
+            Closure::atomic_closure(Term::Num(Number::from(46.0)).into()),
             TermPos::None,
         );
 
@@ -3264,7 +3328,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(46.0).into(),
+                body: Term::Num(Number::from(46.0)).into(),
                 env: Environment::new()
             }
         );
@@ -3276,14 +3340,14 @@ mod tests {
         let cont = OperationCont::Op2First(
             BinaryOp::Plus(),
             Closure {
-                body: Term::Num(6.0).into(),
+                body: Term::Num(Number::from(6.0)).into(),
                 env: Environment::new(),
             },
             TermPos::None,
         );
 
         let mut clos = Closure {
-            body: Term::Num(7.0).into(),
+            body: Term::Num(Number::from(7.0)).into(),
             env: Environment::new(),
         };
         let mut vm = VirtualMachine::new(DummyResolver {});
@@ -3294,7 +3358,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(6.0).into(),
+                body: Term::Num(Number::from(6.0)).into(),
                 env: Environment::new()
             }
         );
@@ -3305,7 +3369,7 @@ mod tests {
                 OperationCont::Op2Second(
                     BinaryOp::Plus(),
                     Closure {
-                        body: Term::Num(7.0).into(),
+                        body: Term::Num(Number::from(7.0)).into(),
                         env: Environment::new(),
                     },
                     TermPos::None,
@@ -3323,7 +3387,7 @@ mod tests {
         let cont: OperationCont = OperationCont::Op2Second(
             BinaryOp::Plus(),
             Closure {
-                body: Term::Num(7.0).into(),
+                body: Term::Num(Number::from(7.0)).into(),
                 env: Environment::new(),
             },
             TermPos::None,
@@ -3332,7 +3396,7 @@ mod tests {
 
         let mut vm: VirtualMachine<DummyResolver, EC> = VirtualMachine::new(DummyResolver {});
         let mut clos = Closure {
-            body: Term::Num(6.0).into(),
+            body: Term::Num(Number::from(6.0)).into(),
             env: Environment::new(),
         };
         vm.stack.push_op_cont(cont, 0, TermPos::None);
@@ -3342,7 +3406,7 @@ mod tests {
         assert_eq!(
             clos,
             Closure {
-                body: Term::Num(13.0).into(),
+                body: Term::Num(Number::from(13.0)).into(),
                 env: Environment::new()
             }
         );
@@ -0,0 +1,439 @@
+//! Source code formatter.
+//!
+//! As opposed to the pretty printer of [`crate::pretty`], which operates on the parsed
+//! [`crate::term::RichTerm`], the formatter never goes through the AST. The AST doesn't
+//! retain comments, nor the layout chosen by the user, so printing it back is only useful for
+//! debugging.
+//!
+//! Instead, the formatter works on a lossless representation of the source, a [`SourceTree`]. It
+//! is the flat stream of tokens produced by the [lexer][crate::parser::lexer], interleaved with
+//! the *trivia* (whitespace and comments) found in between. Concatenating the text of each
+//! element gives back the original source, byte for byte.
+//!
+//! Formatting only ever rewrites trivia. Tokens, including the content of strings, are printed
+//! verbatim, so formatting can't change the meaning of a program. The following rules are
+//! applied:
+//!
+//! - lines are indented by [`INDENT`] spaces per level of nesting of delimiters (braces, brackets,
+//!   parentheses, enum rows and interpolated expressions). A line following a line ending with
+//!   `=`, `=>`, `then` or `else`, or starting with an infix operator or an annotation such as `|`,
+//!   gets one additional level of indentation. A line starting with `else` (respectively `in`) is
+//!   aligned with the line of the matching `then` (respectively `let`).
+//! - runs of spaces inside a line are replaced by a single space,
+//! - trailing whitespace is removed, and consecutive blank lines are collapsed to one,
+//! - comments are kept, and re-indented as the code that follows them,
+//! - the output has no leading blank lines and ends with exactly one newline.
+use crate::error::ParseError;
+use crate::parser::lexer::{Lexer, MultiStringToken, NormalToken, StringToken, Token};
+use codespan::FileId;
+
+/// The number of spaces used for one level of indentation.
+pub const INDENT: usize = 2;
+
+/// An element of a [`SourceTree`].
+#[derive(Debug, PartialEq, Clone)]
+pub enum Element<'input> {
+    /// Whitespace and comments between two tokens.
+    Trivia(&'input str),
+    /// A token, together with the corresponding slice of the source.
+    Token(Token<'input>, &'input str),
+}
+
+impl<'input> Element<'input> {
+    /// The text of this element in the source.
+    pub fn text(&self) -> &'input str {
+        match self {
+            Element::Trivia(text) | Element::Token(_, text) => text,
+        }
+    }
+}
+
+/// A lossless representation of a Nickel source.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceTree<'input> {
+    pub elements: Vec<Element<'input>>,
+}
+
+impl<'input> SourceTree<'input> {
+    /// Lex a source and build the corresponding tree. Comments and whitespace, which are ignored
+    /// by the lexer, are recovered from the gaps between the spans of consecutive tokens.
+    pub fn new(file_id: FileId, source: &'input str) -> Result<Self, ParseError> {
+        let mut elements = Vec::new();
+        let mut last_end = 0;
+
+        for result in Lexer::new(source) {
+            let (start, token, end) = result.map_err(|error| {
+                ParseError::from_lalrpop::<Token>(lalrpop_util::ParseError::User { error }, file_id)
+            })?;
+
+            if start > last_end {
+                elements.push(Element::Trivia(&source[last_end..start]));
+            }
+
+            elements.push(Element::Token(token, &source[start..end]));
+            last_end = end;
+        }
+
+        if last_end < source.len() {
+            elements.push(Element::Trivia(&source[last_end..]));
+        }
+
+        Ok(SourceTree { elements })
+    }
+
+    /// Iterate over the tokens of the tree, ignoring trivia.
+    pub fn tokens(&self) -> impl Iterator<Item = &Token<'input>> {
+        self.elements.iter().filter_map(|elt| match elt {
+            Element::Token(token, _) => Some(token),
+            Element::Trivia(_) => None,
+        })
+    }
+
+    /// Print the tree back to source code, following the formatting rules described in the
+    /// [module documentation][self].
+    pub fn format(&self) -> String {
+        let mut fmt = Formatter::default();
+
+        for (index, elt) in self.elements.iter().enumerate() {
+            match elt {
+                Element::Token(token, text) => fmt.token(token, text),
+                Element::Trivia(text) => {
+                    let next = self.elements[index + 1..].iter().find_map(|elt| match elt {
+                        Element::Token(token, _) => Some(token),
+                        Element::Trivia(_) => None,
+                    });
+                    fmt.trivia(text, next)
+                }
+            }
+        }
+
+        fmt.finish()
+    }
+}
+
+/// Format a Nickel source.
+pub fn format(file_id: FileId, source: &str) -> Result<String, ParseError> {
+    Ok(SourceTree::new(file_id, source)?.format())
+}
+
+/// A block delimited by a pair of delimiters, such as braces.
+struct Block {
+    /// The indentation level of the content of the block.
+    level: usize,
+    /// The indentation levels of the lines of the `then` tokens of the block whose `else` hasn't
+    /// been encountered yet. A line starting with `else` is aligned with the matching `then`.
+    pending_thens: Vec<usize>,
+    /// Same as `pending_thens`, but for `let` and the matching `in`.
+    pending_lets: Vec<usize>,
+}
+
+impl Block {
+    fn new(level: usize) -> Self {
+        Block {
+            level,
+            pending_thens: Vec::new(),
+            pending_lets: Vec::new(),
+        }
+    }
+}
+
+/// The state of the formatter while walking a [`SourceTree`].
+///
+/// By default, a line has the same indentation level as the previous one. The level changes
+/// according to the first token of the line and to the last token of the previous line.
+struct Formatter {
+    out: String,
+    /// The blocks currently open. The first element is the top-level block, which is never
+    /// closed.
+    blocks: Vec<Block>,
+    /// The indentation level of the current line.
+    line_level: usize,
+    /// If the current line starts with an infix operator or a field annotation (see
+    /// [`continues_line`]).
+    line_continues: bool,
+    /// If the last token printed calls for an additional level of indentation on the next line.
+    hanging: bool,
+    /// The indentation level that the last token printed, such as `,` or `in`, calls for on the
+    /// next line, if any.
+    reset_level: Option<usize>,
+}
+
+impl Default for Formatter {
+    fn default() -> Self {
+        Formatter {
+            out: String::new(),
+            blocks: vec![Block::new(0)],
+            line_level: 0,
+            line_continues: false,
+            hanging: false,
+            reset_level: None,
+        }
+    }
+}
+
+impl Formatter {
+    fn current(&mut self) -> &mut Block {
+        // unwrap(): the top-level block is never popped
+        self.blocks.last_mut().unwrap()
+    }
+
+    fn token(&mut self, token: &Token, text: &str) {
+        let line_level = self.line_level;
+        self.reset_level = None;
+
+        match token {
+            Token::Normal(NormalToken::Then) => self.current().pending_thens.push(line_level),
+            Token::Normal(NormalToken::Else) => {
+                self.current().pending_thens.pop();
+            }
+            Token::Normal(NormalToken::Let) => self.current().pending_lets.push(line_level),
+            Token::Normal(NormalToken::In) => {
+                self.reset_level = self.current().pending_lets.pop();
+            }
+            Token::Normal(NormalToken::Comma | NormalToken::Semicolon) => {
+                self.reset_level = Some(self.current().level);
+            }
+            _ if opens_block(token) => {
+                self.blocks.push(Block::new(line_level + 1));
+                self.reset_level = Some(line_level + 1);
+            }
+            _ if closes_block(token) && self.blocks.len() > 1 => {
+                self.blocks.pop();
+            }
+            _ => (),
+        }
+
+        self.hanging = matches!(
+            token,
+            Token::Normal(
+                NormalToken::Equals
+                    | NormalToken::DoubleArrow
+                    | NormalToken::Then
+                    | NormalToken::Else
+            )
+        );
+        self.out.push_str(text);
+    }
+
+    /// Print trivia. Trivia can only appear between tokens in normal mode, as the string lexers
+    /// consume every character of a string. `next` is the token following the trivia, if any.
+    fn trivia(&mut self, text: &str, next: Option<&Token>) {
+        let at_start = self.out.is_empty();
+        let mut lines = text.split('\n');
+        // unwrap(): `split` always returns at least one element
+        let first = lines.next().unwrap().trim();
+        let rest: Vec<&str> = lines.map(str::trim).collect();
+
+        if !first.is_empty() {
+            // The rest of the current line contains a comment
+            if !at_start {
+                self.out.push(' ');
+            }
+            self.out.push_str(first);
+        } else if rest.is_empty() && !at_start && next.is_some() {
+            // Whitespace inside a line
+            self.out.push(' ');
+        }
+
+        if rest.is_empty() {
+            return;
+        }
+
+        // Unless we reached the end of the source, the last line of the trivia is the beginning of
+        // the line of `next`, and is thus blank.
+        let middle = match next {
+            Some(_) => &rest[..rest.len() - 1],
+            None => &rest[..],
+        };
+        let level = self.next_line_level(next);
+        let mut blank_lines = 0;
+
+        for line in middle {
+            if line.is_empty() {
+                blank_lines += 1;
+            } else {
+                self.newline(blank_lines, level);
+                self.out.push_str(line);
+                blank_lines = 0;
+            }
+        }
+
+        // Trailing blank lines are dropped. The final newline is added by `finish`.
+        if let Some(token) = next {
+            self.newline(blank_lines, level);
+            self.line_continues = continues_line(token);
+            self.line_level = level;
+        }
+    }
+
+    /// Start a new line, preceded by at most one blank line, indented at the given level.
+    fn newline(&mut self, blank_lines: usize, level: usize) {
+        if self.out.is_empty() {
+            // No leading blank lines
+        } else if blank_lines > 0 {
+            self.out.push_str("\n\n");
+        } else {
+            self.out.push('\n');
+        }
+
+        self.out.push_str(&" ".repeat(level * INDENT));
+    }
+
+    /// Compute the indentation level of a line starting with the token `next`.
+    fn next_line_level(&self, next: Option<&Token>) -> usize {
+        // unwrap(): the top-level block is never popped
+        let block = self.blocks.last().unwrap();
+
+        match next {
+            Some(token) if closes_block(token) => block.level.saturating_sub(1),
+            Some(Token::Normal(NormalToken::Else)) if !block.pending_thens.is_empty() => {
+                // unwrap(): we just checked that `pending_thens` is not empty
+                *block.pending_thens.last().unwrap()
+            }
+            Some(Token::Normal(NormalToken::In)) if !block.pending_lets.is_empty() => {
+                // unwrap(): we just checked that `pending_lets` is not empty
+                *block.pending_lets.last().unwrap()
+            }
+            _ if self.hanging => self.line_level + 1,
+            Some(token) if continues_line(token) && !self.line_continues => self.line_level + 1,
+            _ => self.reset_level.unwrap_or(self.line_level),
+        }
+    }
+
+    fn finish(mut self) -> String {
+        if !self.out.is_empty() && !self.out.ends_with('\n') {
+            self.out.push('\n');
+        }
+
+        self.out
+    }
+}
+
+/// Determine if a line starting with this token is the continuation of the previous line, such as
+/// the annotations and the definition of a field spanning several lines, or an infix operator.
+fn continues_line(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Normal(
+            NormalToken::Pipe
+                | NormalToken::Equals
+                | NormalToken::Ampersand
+                | NormalToken::RightPipe
+                | NormalToken::DoublePlus
+                | NormalToken::At
+                | NormalToken::DoubleAnd
+                | NormalToken::DoublePipe
+                | NormalToken::SimpleArrow
+        )
+    )
+}
+
+/// Determine if a token opens a block whose content should be indented.
+fn opens_block(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Normal(
+            NormalToken::LBrace
+                | NormalToken::LBracket
+                | NormalToken::LParen
+                | NormalToken::EnumOpen
+        ) | Token::Str(StringToken::Interpolation)
+            | Token::MultiStr(MultiStringToken::Interpolation)
+    )
+}
+
+/// Determine if a token closes a block opened by a token satisfying [`opens_block`]. A closing
+/// brace may end either a record or an interpolated expression.
+fn closes_block(token: &Token) -> bool {
+    matches!(
+        token,
+        Token::Normal(
+            NormalToken::RBrace
+                | NormalToken::RBracket
+                | NormalToken::RParen
+                | NormalToken::EnumClose
+        )
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan::Files;
+
+    fn format_str(source: &str) -> String {
+        let mut files = Files::new();
+        let file_id = files.add("<test>", String::from(source));
+        format(file_id, source).unwrap()
+    }
+
+    /// Check that formatting preserves the tokens of a source, and that it is idempotent.
+    fn assert_format(source: &str, expected: &str) {
+        let mut files = Files::new();
+        let file_id = files.add("<test>", String::from(source));
+        let formatted = format_str(source);
+        assert_eq!(formatted, expected);
+
+        let before: Vec<_> = SourceTree::new(file_id, source)
+            .unwrap()
+            .tokens()
+            .cloned()
+            .collect();
+        let after: Vec<_> = SourceTree::new(file_id, &formatted)
+            .unwrap()
+            .tokens()
+            .cloned()
+            .collect();
+        assert_eq!(before, after);
+        assert_eq!(format_str(&formatted), formatted);
+    }
+
+    #[test]
+    fn lossless() {
+        let source = "  {a=1, # comment\n\n\n  b =   \"x  y\"}  \n";
+        let mut files = Files::new();
+        let file_id = files.add("<test>", String::from(source));
+        let tree = SourceTree::new(file_id, source).unwrap();
+        let text: String = tree.elements.iter().map(Element::text).collect();
+        assert_eq!(text, source);
+    }
+
+    #[test]
+    fn indentation() {
+        assert_format(
+            "{\nfoo = 1,\n      bar = {\nbaz = [\n1,\n2,\n],\n},\n}",
+            "{\n  foo = 1,\n  bar = {\n    baz = [\n      1,\n      2,\n    ],\n  },\n}\n",
+        );
+        assert_format(
+            "let x =\n1 + 1 in\nif x == 2 then\ntrue\nelse\nfalse",
+            "let x =\n  1 + 1 in\nif x == 2 then\n  true\nelse\n  false\n",
+        );
+        assert_format(
+            "{\nf\n| Num -> Num\n= fun x =>\nif x then\nif x then\n1\nelse\n2\nelse\n3,\n}",
+            "{\n  f\n    | Num -> Num\n    = fun x =>\n      if x then\n        if x then\n          1\n        else\n          2\n      else\n        3,\n}\n",
+        );
+    }
+
+    #[test]
+    fn comments() {
+        assert_format(
+            "# header\n\n\n\n{\n    # doc\nfoo = 1,    # trailing   \n}",
+            "# header\n\n{\n  # doc\n  foo = 1, # trailing\n}\n",
+        );
+        assert_format("1 # last", "1 # last\n");
+        assert_format("1\n\n\n# last", "1\n\n# last\n");
+    }
+
+    #[test]
+    fn spaces() {
+        assert_format("\n\n  [1,    2,3]   \n\n", "[1, 2,3]\n");
+    }
+
+    #[test]
+    fn strings_are_verbatim() {
+        assert_format(
+            "{\n    s = m%\"\n        a   b\n          c  \n    \"%,\n  t = \"%{ 1   + 1 }  \",\n}",
+            "{\n  s = m%\"\n        a   b\n          c  \n    \"%,\n  t = \"%{ 1 + 1 }  \",\n}\n",
+        );
+    }
+}
@@ -4,6 +4,7 @@ pub mod destruct;
 pub mod environment;
 pub mod error;
 pub mod eval;
+pub mod format;
 pub mod identifier;
 pub mod label;
 pub mod parser;
@@ -11,9 +12,13 @@ pub mod position;
 pub mod pretty;
 pub mod program;
 pub mod repl;
+pub mod schema;
 pub mod serialize;
 pub mod stdlib;
 pub mod term;
+pub mod term_serializer;
 pub mod transform;
 pub mod typecheck;
 pub mod types;
+
+pub use term_serializer::to_term;
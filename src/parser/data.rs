@@ -0,0 +1,790 @@
+//! Parsing of the data formats which can be imported: JSON, YAML and TOML.
+//!
+//! Contrary to the `Deserialize` implementation of [`RichTerm`], which is used when deserializing
+//! strings at runtime, the parsers of this module attach to each value the span of its definition
+//! in the original file. Error messages, and in particular contract violations, can then point to
+//! the offending part of an imported data file.
+//!
+//! Values which have no direct counterpart in Nickel are mapped as follows:
+//!
+//! - TOML datetimes (offset datetimes, local datetimes, local dates and local times) are imported
+//!   as strings annotated with the `string.Datetime` contract, and are exported back to TOML as
+//!   native datetimes by `nickel export`.
+//! - YAML scalars with a custom tag, such as `!port 80`, or with a standard tag which has no
+//!   counterpart, such as `!!timestamp`, are imported as strings holding the scalar as written.
+//!   The tag itself is dropped, as are the tags of sequences and mappings.
+use crate::error::ParseError;
+use crate::identifier::Ident;
+use crate::label::Label;
+use crate::position::{RawSpan, TermPos};
+use crate::term::{
+    array::{Array, ArrayAttrs},
+    make as mk_term,
+    number::Number,
+    record::RecordData,
+    Contract, MetaValue, RichTerm, Term, UnaryOp,
+};
+use crate::types::{TypeF, Types};
+use codespan::{ByteIndex, FileId, Files};
+use indexmap::IndexMap;
+use serde::de::{Deserialize, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::{collections::HashMap, fmt, iter, rc::Rc};
+use yaml_rust::{
+    parser::{Event, MarkedEventReceiver, Parser},
+    scanner::{Marker, TScalarStyle, TokenType},
+};
+
+fn mk_pos(file_id: FileId, start: usize, end: usize) -> TermPos {
+    TermPos::Original(RawSpan {
+        src_id: file_id,
+        start: ByteIndex::from(start as u32),
+        end: ByteIndex::from(end as u32),
+    })
+}
+
+fn mk_array(elts: Vec<RichTerm>) -> Term {
+    Term::Array(Array::new(Rc::from(elts)), ArrayAttrs::default())
+}
+
+fn mk_record(fields: IndexMap<Ident, RichTerm>) -> Term {
+    Term::Record(RecordData::with_fields(fields))
+}
+
+/// Parse a JSON file.
+pub fn parse_json(files: &Files<String>, file_id: FileId) -> Result<RichTerm, ParseError> {
+    let src = files.source(file_id);
+
+    // The source is first validated by serde_json, which provides good error messages. The term is
+    // then built by a simple parser which assumes that the input is valid, and which delegates the
+    // parsing of strings and numbers to serde_json.
+    serde_json::from_str::<IgnoredAny>(src)
+        .and_then(|_| {
+            JsonParser {
+                src,
+                offset: 0,
+                file_id,
+            }
+            .value()
+        })
+        .map_err(|err| ParseError::from_serde_json(err, file_id, files))
+}
+
+/// A parser for valid JSON which keeps track of the current offset in the source.
+struct JsonParser<'a> {
+    src: &'a str,
+    offset: usize,
+    file_id: FileId,
+}
+
+impl<'a> JsonParser<'a> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.offset).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.offset += 1;
+        }
+    }
+
+    /// Skip a string literal. The current character must be the opening quote.
+    fn skip_string(&mut self) {
+        self.offset += 1;
+
+        while let Some(c) = self.peek() {
+            self.offset += 1;
+
+            match c {
+                b'"' => break,
+                b'\\' => self.offset += 1,
+                _ => (),
+            }
+        }
+    }
+
+    fn value(&mut self) -> Result<RichTerm, serde_json::Error> {
+        self.skip_whitespace();
+        let start = self.offset;
+
+        let term = match self.peek() {
+            Some(b'{') => {
+                let mut fields = IndexMap::new();
+                self.offset += 1;
+                self.skip_whitespace();
+
+                while self.peek() != Some(b'}') {
+                    let key_start = self.offset;
+                    self.skip_string();
+                    let key: String = serde_json::from_str(&self.src[key_start..self.offset])?;
+
+                    self.skip_whitespace();
+                    // Skip the colon
+                    self.offset += 1;
+                    fields.insert(Ident::from(key), self.value()?);
+
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.offset += 1;
+                        self.skip_whitespace();
+                    }
+                }
+
+                self.offset += 1;
+                mk_record(fields)
+            }
+            Some(b'[') => {
+                let mut elts = Vec::new();
+                self.offset += 1;
+                self.skip_whitespace();
+
+                while self.peek() != Some(b']') {
+                    elts.push(self.value()?);
+
+                    self.skip_whitespace();
+                    if self.peek() == Some(b',') {
+                        self.offset += 1;
+                        self.skip_whitespace();
+                    }
+                }
+
+                self.offset += 1;
+                mk_array(elts)
+            }
+            Some(b'"') => {
+                self.skip_string();
+                serde_json::from_str(&self.src[start..self.offset])?
+            }
+            _ => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b']' | b'}' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.offset += 1;
+                }
+
+                serde_json::from_str(&self.src[start..self.offset])?
+            }
+        };
+
+        Ok(RichTerm::new(
+            term,
+            mk_pos(self.file_id, start, self.offset),
+        ))
+    }
+}
+
+/// Parse a YAML file.
+///
+/// A file made of a single document is parsed as the value of this document, whether or not it
+/// starts with an explicit `---` marker, so that the output of `nickel export --format yaml` can
+/// be imported back. A file made of two or more documents is parsed as the array of these
+/// documents.
+pub fn parse_yaml(files: &Files<String>, file_id: FileId) -> Result<RichTerm, ParseError> {
+    let src = files.source(file_id);
+    let mut builder = YamlBuilder {
+        src,
+        file_id,
+        offsets: src
+            .char_indices()
+            .map(|(offset, _)| offset)
+            .chain(iter::once(src.len()))
+            .collect(),
+        stack: Vec::new(),
+        documents: Vec::new(),
+        anchors: HashMap::new(),
+        error: None,
+    };
+
+    Parser::new(src.chars())
+        .load(&mut builder, true)
+        .map_err(|err| ParseError::from_yaml(&err, file_id, files))?;
+
+    if let Some(err) = builder.error {
+        return Err(err);
+    }
+
+    let mut documents = builder.documents;
+    if documents.len() == 1 {
+        Ok(documents.pop().unwrap())
+    } else {
+        Ok(mk_array(documents).into())
+    }
+}
+
+/// A YAML sequence or mapping whose elements are being parsed.
+enum YamlNode {
+    Sequence {
+        start: usize,
+        end: usize,
+        anchor: usize,
+        elts: Vec<RichTerm>,
+    },
+    Mapping {
+        start: usize,
+        end: usize,
+        anchor: usize,
+        fields: IndexMap<Ident, RichTerm>,
+        key: Option<Ident>,
+    },
+}
+
+/// Build terms from the events emitted by the YAML parser.
+struct YamlBuilder<'a> {
+    src: &'a str,
+    file_id: FileId,
+    /// The byte offset of each character of the source. The YAML parser gives positions as
+    /// character indices.
+    offsets: Vec<usize>,
+    stack: Vec<YamlNode>,
+    documents: Vec<RichTerm>,
+    anchors: HashMap<usize, RichTerm>,
+    /// The first error encountered, if any. Subsequent events are ignored.
+    error: Option<ParseError>,
+}
+
+impl<'a> MarkedEventReceiver for YamlBuilder<'a> {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let start = self.offsets[mark.index()];
+
+        match event {
+            Event::Scalar(value, style, anchor, tag) => {
+                // Keys are taken verbatim, as record fields are always strings
+                if let Some(YamlNode::Mapping {
+                    start: map_start,
+                    key: key @ None,
+                    ..
+                }) = self.stack.last_mut()
+                {
+                    *map_start = std::cmp::min(*map_start, start);
+                    *key = Some(Ident::from(value));
+                    return;
+                }
+
+                let end = self.scalar_end(start, mark.col(), &value, style);
+                let pos = mk_pos(self.file_id, start, end);
+
+                match yaml_scalar(value, style, tag) {
+                    Ok(term) => self.push(RichTerm::new(term, pos), anchor, end),
+                    Err(msg) => self.fail(msg, pos),
+                }
+            }
+            Event::SequenceStart(anchor) => self.stack.push(YamlNode::Sequence {
+                start,
+                end: start,
+                anchor,
+                elts: Vec::new(),
+            }),
+            Event::MappingStart(anchor) => self.stack.push(YamlNode::Mapping {
+                start,
+                end: start,
+                anchor,
+                fields: IndexMap::new(),
+                key: None,
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                let (term, node_start, node_end, anchor) = match self.stack.pop().unwrap() {
+                    YamlNode::Sequence {
+                        start,
+                        end,
+                        anchor,
+                        elts,
+                    } => (mk_array(elts), start, end, anchor),
+                    YamlNode::Mapping {
+                        start,
+                        end,
+                        anchor,
+                        fields,
+                        ..
+                    } => (mk_record(fields), start, end, anchor),
+                };
+
+                // The end of a flow collection is its closing bracket. The end of a block
+                // collection is the end of its last element.
+                let end = if self.src[start..].starts_with(&[']', '}'][..]) {
+                    start + 1
+                } else {
+                    node_end
+                };
+
+                self.push(
+                    RichTerm::new(term, mk_pos(self.file_id, node_start, end)),
+                    anchor,
+                    end,
+                );
+            }
+            // An alias keeps the position of the anchored value, which is where the data is
+            // actually defined
+            Event::Alias(id) => match self.anchors.get(&id).cloned() {
+                Some(rt) => {
+                    let len = self.src[start..]
+                        .find(|c: char| c.is_whitespace() || ",[]{}".contains(c))
+                        .unwrap_or(self.src.len() - start);
+                    self.push(rt, 0, start + len);
+                }
+                None => self.fail(
+                    String::from("unknown anchor"),
+                    mk_pos(self.file_id, start, start + 1),
+                ),
+            },
+            _ => (),
+        }
+    }
+}
+
+impl<'a> YamlBuilder<'a> {
+    fn fail(&mut self, msg: String, pos: TermPos) {
+        self.error = Some(ParseError::ExternalFormatError(
+            String::from("yaml"),
+            msg,
+            pos.into_opt(),
+        ));
+    }
+
+    /// Add a parsed value to the current sequence or mapping, or to the list of documents if we
+    /// are at the top-level. `end` is the offset where the value ends in the source.
+    fn push(&mut self, rt: RichTerm, anchor: usize, end: usize) {
+        if anchor != 0 {
+            self.anchors.insert(anchor, rt.clone());
+        }
+
+        match self.stack.last_mut() {
+            Some(YamlNode::Sequence {
+                end: seq_end, elts, ..
+            }) => {
+                *seq_end = std::cmp::max(*seq_end, end);
+                elts.push(rt);
+            }
+            Some(YamlNode::Mapping {
+                end: map_end,
+                fields,
+                key,
+                ..
+            }) => match key.take() {
+                Some(key) => {
+                    *map_end = std::cmp::max(*map_end, end);
+                    fields.insert(key, rt);
+                }
+                None => self.fail(String::from("mapping keys must be strings"), rt.pos),
+            },
+            None => self.documents.push(rt),
+        }
+    }
+
+    /// Determine the end of a scalar starting at `start`.
+    fn scalar_end(&self, start: usize, col: usize, value: &str, style: TScalarStyle) -> usize {
+        let src = &self.src[start..];
+
+        let len = match style {
+            TScalarStyle::SingleQuoted => {
+                let bytes = src.as_bytes();
+                let mut i = 1;
+                loop {
+                    match bytes.get(i) {
+                        // A quote is escaped by doubling it
+                        Some(b'\'') if bytes.get(i + 1) == Some(&b'\'') => i += 2,
+                        Some(b'\'') => break i + 1,
+                        Some(_) => i += 1,
+                        None => break src.len(),
+                    }
+                }
+            }
+            TScalarStyle::DoubleQuoted => {
+                let bytes = src.as_bytes();
+                let mut i = 1;
+                loop {
+                    match bytes.get(i) {
+                        Some(b'\\') => i += 2,
+                        Some(b'"') => break i + 1,
+                        Some(_) => i += 1,
+                        None => break src.len(),
+                    }
+                }
+            }
+            // Block scalars extend over the following lines which are indented at least as much
+            // as the first line of the content, or are empty. `Foled` is how yaml-rust spells the
+            // folded style.
+            TScalarStyle::Literal | TScalarStyle::Foled => {
+                let mut len = 0;
+                let mut offset = 0;
+
+                for (i, line) in src.split('\n').enumerate() {
+                    let content = line.trim_start_matches(' ');
+
+                    if !content.trim().is_empty() {
+                        if i > 0 && line.len() - content.len() < col {
+                            break;
+                        }
+
+                        len = offset + line.trim_end().len();
+                    }
+
+                    offset += line.len() + 1;
+                }
+
+                len
+            }
+            // A plain scalar which spans several lines is approximated by its first line
+            _ if src.starts_with(value) => value.len(),
+            _ => src.split('\n').next().unwrap_or("").trim_end().len(),
+        };
+
+        start + len
+    }
+}
+
+/// Build the term corresponding to a YAML scalar. Plain scalars are interpreted following the
+/// YAML 1.2 core schema, the same way serde_yaml does.
+fn yaml_scalar(value: String, style: TScalarStyle, tag: Option<TokenType>) -> Result<Term, String> {
+    let invalid = |value: &str, expected: &str| {
+        format!("invalid value: string \"{}\", expected {}", value, expected)
+    };
+
+    match tag {
+        Some(TokenType::Tag(handle, suffix)) if handle == "!!" => match suffix.as_ref() {
+            "bool" => value
+                .parse::<bool>()
+                .map(Term::Bool)
+                .map_err(|_| invalid(&value, "a boolean")),
+            "int" => value
+                .parse::<i128>()
+                .map(|n| Term::Num(Number::Int(n)))
+                .map_err(|_| invalid(&value, "an integer")),
+            "float" => value
+                .parse::<f64>()
+                .map(|f| Term::Num(Number::Float(f)))
+                .map_err(|_| invalid(&value, "a float")),
+            "null" => match value.as_ref() {
+                "~" | "null" => Ok(Term::Null),
+                _ => Err(invalid(&value, "null")),
+            },
+            _ => Ok(Term::Str(value)),
+        },
+        None if style == TScalarStyle::Plain => Ok(yaml_plain_scalar(value)),
+        _ => Ok(Term::Str(value)),
+    }
+}
+
+fn yaml_plain_scalar(value: String) -> Term {
+    match value.as_ref() {
+        "~" | "null" => return Term::Null,
+        "true" => return Term::Bool(true),
+        "false" => return Term::Bool(false),
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => {
+            return Term::Num(Number::Float(f64::INFINITY))
+        }
+        "-.inf" | "-.Inf" | "-.INF" => return Term::Num(Number::Float(f64::NEG_INFINITY)),
+        ".nan" | ".NaN" | ".NAN" => return Term::Num(Number::Float(f64::NAN)),
+        _ => (),
+    }
+
+    if let Some(n) = parse_prefixed_int(&value) {
+        return Term::Num(Number::Int(n));
+    }
+
+    // Decimal numbers with leading zeros are strings according to the YAML 1.2 spec
+    let digits = value.trim_start_matches(&['-', '+'][..]);
+    if digits.len() > 1 && digits.starts_with('0') && digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Term::Str(value);
+    }
+
+    if let Ok(n) = value.parse::<i128>() {
+        return Term::Num(Number::Int(n));
+    }
+
+    match value.parse::<f64>() {
+        Ok(f) if f.is_finite() => Term::Num(Number::Float(f)),
+        _ => Term::Str(value),
+    }
+}
+
+/// Parse an hexadecimal, octal or binary integer, such as `0x1f` or `-0o17`.
+fn parse_prefixed_int(s: &str) -> Option<i128> {
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(s) => (true, s),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+
+    let (radix, digits) = if let Some(digits) = s.strip_prefix("0x") {
+        (16, digits)
+    } else if let Some(digits) = s.strip_prefix("0o") {
+        (8, digits)
+    } else if let Some(digits) = s.strip_prefix("0b") {
+        (2, digits)
+    } else {
+        return None;
+    };
+
+    if digits.starts_with(&['-', '+'][..]) {
+        return None;
+    }
+
+    let n = i128::from_str_radix(digits, radix).ok()?;
+    Some(if negative { -n } else { n })
+}
+
+/// Parse a TOML file.
+pub fn parse_toml(files: &Files<String>, file_id: FileId) -> Result<RichTerm, ParseError> {
+    toml::from_str::<TomlValue>(files.source(file_id))
+        .map(|value| value.into_term(file_id))
+        .map_err(|err| ParseError::from_toml(err, file_id, files))
+}
+
+const TOML_DATETIME_FIELD: &str = "$__toml_private_datetime";
+
+/// Import a TOML datetime as a string annotated with the `string.Datetime` contract. The contract
+/// checks the format of the string if it is later overridden, and marks it to be exported back to
+/// TOML as a native datetime.
+fn mk_datetime(s: String, pos: TermPos) -> RichTerm {
+    let types = Types(TypeF::Flat(mk_term::op1(
+        UnaryOp::StaticAccess(Ident::from("Datetime")),
+        Term::Var(Ident::from("string")),
+    )));
+    let mut label = Label {
+        types: Rc::new(types.clone()),
+        ..Default::default()
+    };
+    if let Some(span) = pos.into_opt() {
+        label.span = span;
+    }
+
+    let meta = MetaValue {
+        contracts: vec![Contract { types, label }],
+        value: Some(RichTerm::new(Term::Str(s), pos)),
+        ..Default::default()
+    };
+    RichTerm::new(Term::MetaValue(meta), pos)
+}
+
+/// A TOML value together with its span. The TOML deserializer can't provide the span of tables
+/// defined by a header such as `[table]`, in which case the span is empty.
+struct TomlValue(toml::Spanned<TomlNode>);
+
+enum TomlNode {
+    Scalar(RichTerm),
+    Datetime(String),
+    Array(Vec<TomlValue>),
+    Table(IndexMap<String, TomlValue>),
+}
+
+impl TomlValue {
+    fn into_term(self, file_id: FileId) -> RichTerm {
+        let pos = match self.0.span() {
+            (start, end) if start == end => TermPos::None,
+            (start, end) => mk_pos(file_id, start, end),
+        };
+
+        let term = match self.0.into_inner() {
+            TomlNode::Scalar(rt) => return rt.with_pos(pos),
+            TomlNode::Datetime(s) => return mk_datetime(s, pos),
+            TomlNode::Array(elts) => mk_array(
+                elts.into_iter()
+                    .map(|value| value.into_term(file_id))
+                    .collect(),
+            ),
+            TomlNode::Table(fields) => mk_record(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (Ident::from(key), value.into_term(file_id)))
+                    .collect(),
+            ),
+        };
+
+        RichTerm::new(term, pos)
+    }
+}
+
+impl<'de> Deserialize<'de> for TomlValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        toml::Spanned::deserialize(deserializer).map(TomlValue)
+    }
+}
+
+impl<'de> Deserialize<'de> for TomlNode {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct TomlNodeVisitor;
+
+        impl<'de> Visitor<'de> for TomlNodeVisitor {
+            type Value = TomlNode;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a TOML value")
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Bool(b).into()))
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Num(Number::from(n)).into()))
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Num(Number::from(n)).into()))
+            }
+
+            fn visit_f64<E>(self, f: f64) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Num(Number::Float(f)).into()))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Str(String::from(s)).into()))
+            }
+
+            fn visit_string<E>(self, s: String) -> Result<TomlNode, E> {
+                Ok(TomlNode::Scalar(Term::Str(s).into()))
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<TomlNode, A::Error>
+            where
+                A: SeqAccess<'de>,
+            {
+                let mut elts = Vec::new();
+                while let Some(elt) = seq.next_element()? {
+                    elts.push(elt);
+                }
+                Ok(TomlNode::Array(elts))
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<TomlNode, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut fields = IndexMap::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    // The TOML deserializer represents a datetime as a map with a single
+                    // special key, whose value can't be spanned
+                    if key == TOML_DATETIME_FIELD {
+                        return Ok(TomlNode::Datetime(map.next_value()?));
+                    }
+
+                    fields.insert(key, map.next_value()?);
+                }
+                Ok(TomlNode::Table(fields))
+            }
+        }
+
+        deserializer.deserialize_any(TomlNodeVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parse a source with the given parser, and return the text spanned by the value at the
+    /// given path.
+    fn spanned<'a>(
+        parse: fn(&Files<String>, FileId) -> Result<RichTerm, ParseError>,
+        src: &'a str,
+        path: &[&str],
+    ) -> &'a str {
+        let mut files = Files::new();
+        let file_id = files.add("<test>", String::from(src));
+        let mut rt = parse(&files, file_id).unwrap();
+
+        for field in path {
+            rt = match rt.as_ref() {
+                Term::Record(record) => record.fields.get(&Ident::from(*field)).unwrap().clone(),
+                Term::Array(elts, _) => elts.get(field.parse().unwrap()).unwrap().clone(),
+                t => panic!("expected a record or an array, got {:?}", t),
+            }
+        }
+
+        let span = rt.pos.into_opt().unwrap();
+        &src[span.start.to_usize()..span.end.to_usize()]
+    }
+
+    #[test]
+    fn json_positions() {
+        let src = "{\n  \"a\": [1, \"x\\\"y\", {\"b\": null}],\n  \"c\" : -1.5e3\n}";
+
+        assert_eq!(spanned(parse_json, src, &[]), src);
+        assert_eq!(
+            spanned(parse_json, src, &["a"]),
+            "[1, \"x\\\"y\", {\"b\": null}]"
+        );
+        assert_eq!(spanned(parse_json, src, &["a", "1"]), "\"x\\\"y\"");
+        assert_eq!(spanned(parse_json, src, &["a", "2", "b"]), "null");
+        assert_eq!(spanned(parse_json, src, &["c"]), "-1.5e3");
+    }
+
+    #[test]
+    fn yaml_positions() {
+        let src = "a:\n  - 1\n  - &x 'it''s'\n  - *x\nb: {c: \"d\\\"\", e: [f]}\ng: |\n  h\n\n  i\nj:\n  k: l\n";
+
+        assert_eq!(
+            spanned(parse_yaml, src, &["a"]),
+            "- 1\n  - &x 'it''s'\n  - *x"
+        );
+        assert_eq!(spanned(parse_yaml, src, &["a", "1"]), "'it''s'");
+        assert_eq!(spanned(parse_yaml, src, &["a", "2"]), "'it''s'");
+        assert_eq!(spanned(parse_yaml, src, &["b"]), "{c: \"d\\\"\", e: [f]}");
+        assert_eq!(spanned(parse_yaml, src, &["b", "c"]), "\"d\\\"\"");
+        assert_eq!(spanned(parse_yaml, src, &["b", "e", "0"]), "f");
+        assert_eq!(spanned(parse_yaml, src, &["g"]), "h\n\n  i");
+        assert_eq!(spanned(parse_yaml, src, &["j"]), "k: l");
+    }
+
+    #[test]
+    fn yaml_streams() {
+        assert_eq!(spanned(parse_yaml, "a: 1\n", &["a"]), "1");
+        assert_eq!(spanned(parse_yaml, "---\na: 1\n", &["a"]), "1");
+        assert_eq!(spanned(parse_yaml, "# c\n--- \na: 1\n", &["a"]), "1");
+        assert_eq!(spanned(parse_yaml, "a: 1\n---\nb: 2\n", &["1", "b"]), "2");
+        assert_eq!(
+            spanned(parse_yaml, "---\na: 1\n---\nb: 2\n", &["0", "a"]),
+            "1"
+        );
+    }
+
+    #[test]
+    fn yaml_scalars() {
+        let plain = |s: &str| yaml_plain_scalar(String::from(s));
+
+        assert_eq!(plain("~"), Term::Null);
+        assert_eq!(plain("true"), Term::Bool(true));
+        assert_eq!(plain("0x1f"), Term::Num(Number::Int(31)));
+        assert_eq!(plain("-0o17"), Term::Num(Number::Int(-15)));
+        assert_eq!(plain("0x-1"), Term::Str(String::from("0x-1")));
+        assert_eq!(plain("0123"), Term::Str(String::from("0123")));
+        assert_eq!(plain("12"), Term::Num(Number::Int(12)));
+        assert_eq!(plain("1.5"), Term::Num(Number::Float(1.5)));
+        assert_eq!(plain("-.inf"), Term::Num(Number::Float(f64::NEG_INFINITY)));
+        assert_eq!(plain("inf"), Term::Str(String::from("inf")));
+        assert_eq!(plain("yes"), Term::Str(String::from("yes")));
+
+        let tagged = |s: &str, tag: &str| {
+            yaml_scalar(
+                String::from(s),
+                TScalarStyle::DoubleQuoted,
+                Some(TokenType::Tag(String::from("!!"), String::from(tag))),
+            )
+        };
+
+        assert_eq!(tagged("5", "int"), Ok(Term::Num(Number::Int(5))));
+        assert_eq!(tagged("true", "str"), Ok(Term::Str(String::from("true"))));
+        assert!(tagged("five", "int").is_err());
+        assert_eq!(
+            tagged("2001-12-14 21:59:43.10 -5", "timestamp"),
+            Ok(Term::Str(String::from("2001-12-14 21:59:43.10 -5")))
+        );
+        assert_eq!(
+            yaml_scalar(
+                String::from("80"),
+                TScalarStyle::Plain,
+                Some(TokenType::Tag(String::from("!"), String::from("port"))),
+            ),
+            Ok(Term::Str(String::from("80")))
+        );
+    }
+}
@@ -44,4 +44,6 @@ pub enum ParseError {
     /// - a variable is used as both a record and enum row variable, e.g. in the
     ///   signature `forall r. [| ; r |] -> { ; r }`.
     TypeVariableKindMismatch { ty_var: Ident, span: RawSpan },
+    /// The format given to an import (`import "file" as `Format`) is not a supported input format.
+    InvalidImportFormat { format: Ident, span: RawSpan },
 }
@@ -30,6 +30,7 @@
 //! go back to string mode. In our example, this is the second `}`: at this point, the lexer knows
 //! that the coming characters must be lexed as string tokens, and not as normal tokens.
 use crate::parser::error::{LexicalError, ParseError};
+use crate::term::number::Number;
 use logos::Logos;
 use std::ops::Range;
 
@@ -54,7 +55,7 @@ pub enum NormalToken<'input> {
     #[regex("_?[a-zA-Z][_a-zA-Z0-9-']*")]
     Identifier(&'input str),
     #[regex("[0-9]*\\.?[0-9]+", |lex| lex.slice().parse())]
-    NumLiteral(f64),
+    NumLiteral(Number),
 
     // **IMPORTANT**
     // This regex should be kept in sync with the one for Identifier above.
@@ -148,6 +149,8 @@ pub enum NormalToken<'input> {
     Fun,
     #[token("import")]
     Import,
+    #[token("as")]
+    As,
     #[token("|")]
     Pipe,
     #[token("|>")]
@@ -292,6 +295,12 @@ pub enum NormalToken<'input> {
     NumFromStr,
     #[token("%enum_from_str%")]
     EnumFromStr,
+    #[token("%enum_is_variant%")]
+    EnumIsVariant,
+    #[token("%enum_get_tag%")]
+    EnumGetTag,
+    #[token("%enum_unwrap_variant%")]
+    EnumUnwrapVariant,
 
     #[token("{")]
     LBrace,
@@ -323,7 +332,7 @@ pub enum NormalToken<'input> {
 
 pub const KEYWORDS: &[&str] = &[
     "Dyn", "Num", "Bool", "Str", "Array", "if", "then", "else", "forall", "in", "let", "rec",
-    "match", "null", "true", "false", "fun", "import", "merge", "default", "doc", "optional",
+    "match", "null", "true", "false", "fun", "import", "as", "merge", "default", "doc", "optional",
     "priority", "force",
 ];
 
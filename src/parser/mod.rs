@@ -10,6 +10,7 @@ lalrpop_mod!(
     #[allow(unused_imports)]
     pub grammar, "/parser/grammar.rs");
 
+pub mod data;
 pub mod error;
 pub mod lexer;
 pub mod uniterm;
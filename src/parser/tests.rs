@@ -4,7 +4,7 @@ use crate::identifier::Ident;
 use crate::parser::error::ParseError as InternalParseError;
 use crate::term::make as mk_term;
 use crate::term::Term::*;
-use crate::term::{record, BinaryOp, RichTerm, StrChunk, UnaryOp};
+use crate::term::{number::Number, record, BinaryOp, RichTerm, StrChunk, UnaryOp};
 use crate::{mk_app, mk_match};
 use assert_matches::assert_matches;
 use codespan::Files;
@@ -36,11 +36,12 @@ fn mk_single_chunk(s: &str) -> RichTerm {
 
 #[test]
 fn numbers() {
-    assert_eq!(parse_without_pos("22"), Num(22.0).into());
-    assert_eq!(parse_without_pos("22.0"), Num(22.0).into());
-    assert_eq!(parse_without_pos("22.22"), Num(22.22).into());
-    assert_eq!(parse_without_pos("(22)"), Num(22.0).into());
-    assert_eq!(parse_without_pos("((22))"), Num(22.0).into());
+    // `Number` compares integers and floats by value, so match on the variant explicitly
+    assert_matches!(parse_without_pos("22").as_ref(), Num(Number::Int(22)));
+    assert_matches!(parse_without_pos("22.0").as_ref(), Num(Number::Float(f)) if *f == 22.0);
+    assert_matches!(parse_without_pos("22.22").as_ref(), Num(Number::Float(f)) if *f == 22.22);
+    assert_matches!(parse_without_pos("(22)").as_ref(), Num(Number::Int(22)));
+    assert_matches!(parse_without_pos("((22))").as_ref(), Num(Number::Int(22)));
 }
 
 #[test]
@@ -78,14 +79,19 @@ fn strings() {
 fn plus() {
     assert_eq!(
         parse_without_pos("3 + 4"),
-        Op2(BinaryOp::Plus(), Num(3.0).into(), Num(4.).into()).into()
+        Op2(
+            BinaryOp::Plus(),
+            Num(Number::from(3.0)).into(),
+            Num(Number::from(4.)).into()
+        )
+        .into()
     );
     assert_eq!(
         parse_without_pos("(true + false) + 4"),
         Op2(
             BinaryOp::Plus(),
             Op2(BinaryOp::Plus(), Bool(true).into(), Bool(false).into()).into(),
-            Num(4.).into(),
+            Num(Number::from(4.)).into(),
         )
         .into()
     );
@@ -101,7 +107,11 @@ fn booleans() {
 fn ite() {
     assert_eq!(
         parse_without_pos("if true then 3 else 4"),
-        mk_app!(mk_term::op1(UnaryOp::Ite(), Bool(true)), Num(3.0), Num(4.0))
+        mk_app!(
+            mk_term::op1(UnaryOp::Ite(), Bool(true)),
+            Num(Number::from(3.0)),
+            Num(Number::from(4.0))
+        )
     );
 }
 
@@ -109,12 +119,16 @@ fn ite() {
 fn applications() {
     assert_eq!(
         parse_without_pos("1 true 2"),
-        mk_app!(Num(1.0), Bool(true), Num(2.0))
+        mk_app!(Num(Number::from(1.0)), Bool(true), Num(Number::from(2.0)))
     );
 
     assert_eq!(
         parse_without_pos("1 (2 3) 4"),
-        mk_app!(Num(1.0), mk_app!(Num(2.0), Num(3.0)), Num(4.0))
+        mk_app!(
+            Num(Number::from(1.0)),
+            mk_app!(Num(Number::from(2.0)), Num(Number::from(3.0))),
+            Num(Number::from(4.0))
+        )
     );
 }
 
@@ -180,16 +194,16 @@ fn enum_terms() {
             "match with raw tags",
             "match { `foo => true, `bar => false, _ => 456, } 123",
             mk_app!(
-                mk_match!(("foo", Bool(true)), ("bar", Bool(false)) ; Num(456.)),
-                Num(123.)
+                mk_match!(("foo", Bool(true)), ("bar", Bool(false)) ; Num(Number::from(456.))),
+                Num(Number::from(123.))
             ),
         ),
         (
             "match with string tags",
             "match { `\"one:two\" => true, `\"three four\" => false, _ => 13 } 1",
             mk_app!(
-                mk_match!(("one:two", Bool(true)), ("three four", Bool(false)) ; Num(13.)),
-                Num(1.)
+                mk_match!(("one:two", Bool(true)), ("three four", Bool(false)) ; Num(Number::from(13.))),
+                Num(Number::from(1.))
             ),
         ),
     ];
@@ -220,9 +234,9 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("b"), Num(2.).into()),
-                    (Ident::from("c"), Num(3.).into()),
+                    (Ident::from("a"), Num(Number::from(1.)).into()),
+                    (Ident::from("b"), Num(Number::from(2.)).into()),
+                    (Ident::from("c"), Num(Number::from(3.)).into()),
                 ]
                 .into_iter()
                 .collect()
@@ -238,15 +252,22 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("d"), Num(42.).into()),
+                    (Ident::from("a"), Num(Number::from(1.)).into()),
+                    (Ident::from("d"), Num(Number::from(42.)).into()),
                 ]
                 .into_iter()
                 .collect()
             ),
             vec![(
-                StrChunks(vec![StrChunk::expr(RichTerm::from(Num(123.)))]).into(),
-                mk_app!(mk_term::op1(UnaryOp::Ite(), Num(4.)), Num(5.), Num(6.))
+                StrChunks(vec![StrChunk::expr(RichTerm::from(Num(Number::from(
+                    123.
+                ))))])
+                .into(),
+                mk_app!(
+                    mk_term::op1(UnaryOp::Ite(), Num(Number::from(4.))),
+                    Num(Number::from(5.)),
+                    Num(Number::from(6.))
+                )
             )],
             None,
         )
@@ -258,8 +279,8 @@ fn record_terms() {
         RecRecord(
             record::RecordData::with_fields(
                 vec![
-                    (Ident::from("a"), Num(1.).into()),
-                    (Ident::from("\"%}%"), Num(2.).into()),
+                    (Ident::from("a"), Num(Number::from(1.)).into()),
+                    (Ident::from("\"%}%"), Num(Number::from(2.)).into()),
                 ]
                 .into_iter()
                 .collect()
@@ -320,7 +341,7 @@ fn string_lexing() {
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Str(StringToken::Literal("1 + ")),
                 Token::Str(StringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::Int(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::Str(StringToken::Literal(" + 2")),
                 Token::Normal(NormalToken::DoubleQuote),
@@ -335,7 +356,7 @@ fn string_lexing() {
                 Token::Str(StringToken::Interpolation),
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Str(StringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::Int(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::Normal(NormalToken::DoubleQuote),
                 Token::Normal(NormalToken::RBrace),
@@ -367,7 +388,7 @@ fn string_lexing() {
                 Token::Normal(NormalToken::SymbolicStringStart(3)),
                 Token::MultiStr(MultiStringToken::Literal("text ")),
                 Token::MultiStr(MultiStringToken::Interpolation),
-                Token::Normal(NormalToken::NumLiteral(1.0)),
+                Token::Normal(NormalToken::NumLiteral(Number::Int(1))),
                 Token::Normal(NormalToken::RBrace),
                 Token::MultiStr(MultiStringToken::Literal(" etc.")),
                 Token::MultiStr(MultiStringToken::End),
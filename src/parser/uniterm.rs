@@ -8,7 +8,7 @@ use crate::{
     position::{RawSpan, TermPos},
     term::{record::RecordAttrs, Contract, MergePriority, MetaValue, RichTerm, SharedTerm, Term},
     types::{
-        EnumRows, EnumRowsIteratorItem, RecordRow, RecordRows, RecordRowsF, TypeF, Types,
+        EnumRows, EnumRowsF, RecordRow, RecordRows, RecordRowsF, TypeF, Types,
         UnboundTypeVariableError, VarKind,
     },
 };
@@ -573,26 +573,26 @@ impl FixTypeVars for EnumRows {
         bound_vars: BoundVarEnv,
         span: RawSpan,
     ) -> Result<(), ParseError> {
-        // An enum row doesn't contain any subtypes (beside other enum rows). No term variable can
-        // appear in it, so we don't have to traverse for fixing type variables properly.
-        //
-        // However, the second task of the fix_type_vars phase is to determine the variable kind of
-        // forall binders: here, we do need to check if the fail of this enum is an enum row type
-        // variable.
-        let mut iter = self
-            .iter()
-            .skip_while(|item| matches!(item, EnumRowsIteratorItem::Row(_)));
-        match iter.next() {
-            Some(EnumRowsIteratorItem::TailVar(id)) => {
+        match self.0 {
+            EnumRowsF::Empty => Ok(()),
+            // We can't have a contract in tail position, so we don't fix `TailVar`. However, we
+            // have to set the correct kind for the corresponding forall binder.
+            EnumRowsF::TailVar(ref id) => {
                 if let Some(cell) = bound_vars.get(id) {
                     cell.set_or_check_equal(VarKind::EnumRows)
                         .map_err(|_| ParseError::TypeVariableKindMismatch { ty_var: *id, span })?;
                 }
                 Ok(())
             }
-            // unreachable(): we consumed all the rows item via the `take_while()` call above
-            Some(EnumRowsIteratorItem::Row(_)) => unreachable!(),
-            None => Ok(()),
+            EnumRowsF::Extend {
+                ref mut row,
+                ref mut tail,
+            } => {
+                if let Some(ref mut ty) = row.typ {
+                    ty.fix_type_vars_env(bound_vars.clone(), span)?;
+                }
+                tail.fix_type_vars_env(bound_vars, span)
+            }
         }
     }
 }
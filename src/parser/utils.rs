@@ -1,16 +1,16 @@
 //! Various helpers and companion code for the parser are put here to keep the grammar definition
 //! uncluttered.
-use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt::Debug;
 use std::rc::Rc;
 
 use codespan::FileId;
+use indexmap::{map::Entry, IndexMap};
 
 use super::error::ParseError;
 
 use crate::{
-    destruct::Destruct,
+    destruct::{Destruct, MatchBranch},
     eval::operation::RecPriority,
     identifier::Ident,
     label::Label,
@@ -58,13 +58,6 @@ pub enum StringEndDelimiter {
     Special,
 }
 
-/// Distinguish between a normal case `id => exp` and a default case `_ => exp`.
-#[derive(Clone, Debug)]
-pub enum MatchCase {
-    Normal(Ident, RichTerm),
-    Default(RichTerm),
-}
-
 /// Left hand side of a record field declaration.
 #[derive(Clone, Debug)]
 pub enum FieldPathElem {
@@ -293,7 +286,7 @@ pub fn elaborate_field_path(
         let pos = TermPos::Original(RawSpan::fuse(id_span, acc_span).unwrap());
         match path_elem {
             FieldPathElem::Ident(id) => {
-                let mut fields = HashMap::new();
+                let mut fields = IndexMap::new();
                 fields.insert(id, acc);
 
                 RichTerm::new(Term::Record(RecordData::with_fields(fields)), pos)
@@ -302,7 +295,7 @@ pub fn elaborate_field_path(
                 let static_access = exp.term.as_ref().try_str_chunk_as_static_str();
                 if let Some(static_access) = static_access {
                     let id = Ident::new_with_pos(static_access, exp.pos);
-                    let mut fields = HashMap::new();
+                    let mut fields = IndexMap::new();
                     fields.insert(id, acc);
                     RichTerm::new(Term::Record(RecordData::with_fields(fields)), pos)
                 } else {
@@ -322,10 +315,10 @@ pub fn build_record<I>(fields: I, attrs: RecordAttrs) -> Term
 where
     I: IntoIterator<Item = (FieldPathElem, RichTerm)> + Debug,
 {
-    let mut static_fields = HashMap::new();
+    let mut static_fields = IndexMap::new();
     let mut dynamic_fields = Vec::new();
 
-    fn insert_static_field(static_fields: &mut HashMap<Ident, RichTerm>, id: Ident, t: RichTerm) {
+    fn insert_static_field(static_fields: &mut IndexMap<Ident, RichTerm>, id: Ident, t: RichTerm) {
         match static_fields.entry(id) {
             Entry::Occupied(mut occpd) => {
                 // temporary putting null in the entry to take the previous value.
@@ -467,7 +460,11 @@ pub fn mk_let(
     span: RawSpan,
 ) -> Result<RichTerm, ParseError> {
     let result = match pat {
-        d @ (Destruct::Record { .. } | Destruct::Array { .. }) => {
+        d @ (Destruct::Record { .. }
+        | Destruct::Array { .. }
+        | Destruct::Constant(_)
+        | Destruct::Enum(_)
+        | Destruct::EnumVariant(..)) => {
             if rec {
                 return Err(ParseError::RecursiveLetPattern(span));
             }
@@ -489,6 +486,44 @@ pub fn mk_let(
     Ok(result)
 }
 
+/// Generate the application of `t1` to `t2`. If `t1` is an enum tag, the result is rather an enum
+/// variant carrying `t2` as a value, as in `` `Some 5``.
+pub fn mk_app_or_variant(t1: RichTerm, t2: RichTerm) -> RichTerm {
+    match t1.as_ref() {
+        Term::Enum(tag) => Term::EnumVariant(*tag, t2).into(),
+        _ => mk_app!(t1, t2),
+    }
+}
+
+/// Generate a match expression from its branches. If the branches only match enum tags, apart from
+/// a catch-all `_` case, and don't have guards, the result is a simple [`Term::Match`] where the
+/// catch-all case is the default case, wherever it appears. Otherwise, the result is a
+/// [`Term::MatchPattern`], whose branches are tried in order.
+pub fn mk_match(branches: Vec<MatchBranch>) -> Term {
+    let is_enum_match = branches.iter().all(|branch| {
+        branch.guard.is_none()
+            && matches!(branch.pattern, (None, Destruct::Enum(_) | Destruct::Empty))
+    });
+
+    if !is_enum_match {
+        return Term::MatchPattern(branches);
+    }
+
+    let mut cases = HashMap::with_capacity(branches.len());
+    let mut default = None;
+
+    for branch in branches {
+        match branch.pattern {
+            (_, Destruct::Enum(id)) => cases.insert(id, branch.body),
+            // If there are multiple default cases, the last one silently
+            // erases the others. We should have a dedicated error for that
+            _ => default.replace(branch.body),
+        };
+    }
+
+    Term::Match { cases, default }
+}
+
 /// Determine the minimal level of indentation of a multi-line string.
 ///
 /// The result is determined by computing the minimum indentation level among all lines, where the
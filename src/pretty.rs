@@ -1,7 +1,11 @@
+use crate::cache::InputFormat;
 use crate::destruct::{self, Destruct};
+use crate::identifier::Ident;
 use crate::parser::lexer::KEYWORDS;
-use crate::term::{BinaryOp, MetaValue, RichTerm, Term, UnaryOp};
-use crate::types::{EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types};
+use crate::term::{number::Number, BinaryOp, MetaValue, RichTerm, Term, UnaryOp};
+use crate::types::{
+    EnumRowF, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types,
+};
 pub use pretty::{DocAllocator, DocBuilder, Pretty};
 use regex::Regex;
 use std::collections::HashMap;
@@ -30,6 +34,16 @@ fn min_interpolate_sign(text: &str) -> usize {
         .unwrap_or(1)
 }
 
+/// Whether a string can be printed as a multiline string and parsed back to the same value. The
+/// parser strips the common indentation of multiline strings, so at least one line must not be
+/// indented.
+fn is_multiline_compatible(s: &str) -> bool {
+    s.contains('\n')
+        && !s.contains('\r')
+        && s.split('\n')
+            .any(|line| !line.is_empty() && !line.starts_with([' ', '\t']))
+}
+
 fn sorted_map<K: Ord, V>(m: &'_ HashMap<K, V>) -> Vec<(&'_ K, &'_ V)> {
     let mut ret: Vec<(&K, &V)> = m.iter().collect();
     ret.sort_by_key(|(k, _)| *k);
@@ -48,7 +62,7 @@ where
         if reg.is_match(id.as_ref()) && !KEYWORDS.contains(&id.as_ref()) {
             self.as_string(id)
         } else {
-            self.as_string(id).double_quotes()
+            self.escaped_string(id.as_ref()).double_quotes()
         }
     }
 
@@ -56,14 +70,27 @@ where
         let s = s
             .replace('\\', "\\\\")
             .replace("%{", "\\%{")
-            .replace('\"', "\\\"");
+            .replace('\"', "\\\"")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r")
+            .replace('\t', "\\t");
         self.text(s)
     }
 
+    /// Print a multiline string, with the delimiters on their own line. The string should satisfy
+    /// [`is_multiline_compatible`].
     fn multiline_string(&'a self, s: &str) -> DocBuilder<'a, Self, A> {
         let delimiter = "%".repeat(min_interpolate_sign(s));
-        self.hardline()
-            .append(self.intersperse(s.lines().map(|d| self.text(d.to_owned())), self.hardline()))
+        self.nil()
+            .append(self.concat(s.split('\n').map(|line| {
+                // Avoid trailing whitespace: empty lines aren't indented
+                if line.is_empty() {
+                    self.text("\n")
+                } else {
+                    self.hardline().append(self.text(line.to_owned()))
+                }
+            })))
+            .append(self.hardline())
             .enclose(format!("m{delimiter}\""), format!("\"{delimiter}"))
     }
 
@@ -83,7 +110,7 @@ where
                     self.text("|")
                         .append(self.space())
                         .append(self.text("doc"))
-                        .append(self.hardline())
+                        .append(self.space())
                         .append({
                             if doc.contains('\n') {
                                 self.multiline_string(doc)
@@ -91,7 +118,11 @@ where
                                 self.escaped_string(doc).double_quotes()
                             }
                         })
-                        .append(self.line())
+                        .append(if mv.contracts.is_empty() {
+                            self.nil()
+                        } else {
+                            self.line()
+                        })
                 })
                 .unwrap_or_else(|| self.nil())
         } else {
@@ -124,6 +155,22 @@ where
         .group()
     }
 
+    /// Print a pattern `alias @ pat`. A pattern without alias nor destructuring is printed as the
+    /// wildcard `_`.
+    fn pattern(&'a self, alias: &Option<Ident>, pat: &Destruct) -> DocBuilder<'a, Self, A> {
+        match (alias, pat) {
+            (None, Destruct::Empty) => self.text("_"),
+            (Some(id), Destruct::Empty) => self.as_string(id),
+            (None, pat) => pat.pretty(self),
+            (Some(id), pat) => self
+                .as_string(id)
+                .append(self.space())
+                .append(self.text("@"))
+                .append(self.space())
+                .append(pat.pretty(self)),
+        }
+    }
+
     fn atom(&'a self, rt: &RichTerm) -> DocBuilder<'a, Self, A> {
         if rt.as_ref().is_atom() {
             rt.to_owned().pretty(self)
@@ -149,6 +196,13 @@ where
             ArrayHead() => allocator.text("%head%").append(allocator.space()),
             DeepSeq(_) => allocator.text("%deep_seq%").append(allocator.space()),
             Typeof() => allocator.text("%typeof%").append(allocator.space()),
+            EnumIsVariant() => allocator
+                .text("%enum_is_variant%")
+                .append(allocator.space()),
+            EnumGetTag() => allocator.text("%enum_get_tag%").append(allocator.space()),
+            EnumUnwrapVariant() => allocator
+                .text("%enum_unwrap_variant%")
+                .append(allocator.space()),
             BoolNot() => allocator.text("!"),
             BoolAnd() => allocator.space().append(allocator.text("&&")),
             BoolOr() => allocator.space().append(allocator.text("||")),
@@ -215,32 +269,38 @@ where
                 ..
             } => allocator
                 .intersperse(
-                    matches.iter().map(|m| match m {
-                        destruct::Match::Simple(id, meta) => allocator
+                    matches.iter().map(|m| {
+                        let (id, meta) = match m {
+                            destruct::Match::Simple(id, meta)
+                            | destruct::Match::Assign(id, meta, _) => (id, meta),
+                        };
+                        allocator
                             .as_string(id)
                             .append(allocator.space())
-                            .append(match meta.clone() {
-                                MetaValue {
-                                    types,
-                                    contracts,
-                                    priority: crate::term::MergePriority::Bottom,
-                                    value: Some(value),
-                                    ..
-                                } => allocator
-                                    .text("?")
+                            .append(allocator.metadata(
+                                &MetaValue {
+                                    types: meta.types.clone(),
+                                    contracts: meta.contracts.clone(),
+                                    ..Default::default()
+                                },
+                                false,
+                            ))
+                            .append(match &meta.value {
+                                Some(value) => allocator
+                                    .space()
+                                    .append(allocator.text("?"))
                                     .append(allocator.space())
-                                    .append(allocator.atom(&value))
-                                    .append(allocator.metadata(
-                                        &MetaValue {
-                                            types,
-                                            contracts,
-                                            ..Default::default()
-                                        },
-                                        false,
-                                    )),
-                                m => allocator.metadata(&m, false),
-                            }),
-                        _ => unimplemented!(),
+                                    .append(allocator.atom(value)),
+                                None => allocator.nil(),
+                            })
+                            .append(match m {
+                                destruct::Match::Assign(_, _, (alias, pat)) => allocator
+                                    .space()
+                                    .append(allocator.text("="))
+                                    .append(allocator.space())
+                                    .append(allocator.pattern(alias, pat)),
+                                destruct::Match::Simple(..) => allocator.nil(),
+                            })
                     }),
                     allocator.text(",").append(allocator.space()),
                 )
@@ -258,8 +318,39 @@ where
                     allocator.nil()
                 })
                 .braces(),
+            Destruct::Array {
+                elems, open, rest, ..
+            } => allocator
+                .intersperse(
+                    elems
+                        .iter()
+                        .map(|(alias, pat)| allocator.pattern(alias, pat)),
+                    allocator.text(",").append(allocator.space()),
+                )
+                .append(if *open {
+                    if elems.is_empty() {
+                        allocator.nil()
+                    } else {
+                        allocator.text(",").append(allocator.space())
+                    }
+                    .append(allocator.text(".."))
+                    .append(if let Some(rest) = rest {
+                        allocator.as_string(rest)
+                    } else {
+                        allocator.nil()
+                    })
+                } else {
+                    allocator.nil()
+                })
+                .brackets(),
+            Destruct::Constant(c) => allocator.atom(c),
+            Destruct::Enum(id) => allocator.text("`").append(allocator.quote_if_needed(id)),
+            Destruct::EnumVariant(id, pat) => allocator
+                .text("`")
+                .append(allocator.quote_if_needed(id))
+                .append(allocator.space())
+                .append(allocator.pattern(&pat.0, &pat.1)),
             Destruct::Empty => allocator.nil(),
-            _ => unimplemented!(),
         }
     }
 }
@@ -277,6 +368,7 @@ where
             Null => allocator.text("null"),
             Bool(v) => allocator.as_string(v),
             Num(v) => allocator.as_string(v),
+            Str(v) if is_multiline_compatible(v) => allocator.multiline_string(v),
             Str(v) => allocator.escaped_string(v).double_quotes(),
             StrChunks(chunks) => {
                 let multiline = chunks.len() > 1;
@@ -367,9 +459,14 @@ where
                     .append(rt.to_owned().pretty(allocator).nest(2))
             }
             Lbl(_lbl) => allocator.text("# <label>").append(allocator.hardline()),
-            Let(id, rt, body, _ty) => allocator
+            Let(id, rt, body, attrs) => allocator
                 .text("let")
                 .append(allocator.space())
+                .append(if attrs.rec {
+                    allocator.text("rec").append(allocator.space())
+                } else {
+                    allocator.nil()
+                })
                 .append(allocator.as_string(id))
                 .append(if let MetaValue(ref mv) = rt.as_ref() {
                     allocator.space().append(allocator.metadata(mv, false))
@@ -378,19 +475,22 @@ where
                 })
                 .append(allocator.space())
                 .append(allocator.text("="))
-                .append(allocator.line())
                 .append(
-                    if let MetaValue(crate::term::MetaValue {
-                        value: Some(rt), ..
-                    }) = rt.as_ref()
-                    {
-                        rt
-                    } else {
-                        rt
-                    }
-                    .to_owned()
-                    .pretty(allocator)
-                    .nest(2),
+                    allocator
+                        .line()
+                        .append(
+                            if let MetaValue(crate::term::MetaValue {
+                                value: Some(rt), ..
+                            }) = rt.as_ref()
+                            {
+                                rt
+                            } else {
+                                rt
+                            }
+                            .to_owned()
+                            .pretty(allocator),
+                        )
+                        .nest(2),
                 )
                 .append(allocator.line())
                 .append(allocator.text("in"))
@@ -470,33 +570,53 @@ where
             },
             Var(id) => allocator.as_string(id),
             Enum(id) => allocator.text("`").append(allocator.quote_if_needed(id)),
+            EnumVariant(id, arg) => allocator
+                .text("`")
+                .append(allocator.quote_if_needed(id))
+                .append(allocator.line())
+                .append(allocator.atom(arg))
+                .group(),
+            Record(record) if record.fields.is_empty() && !record.attrs.open => {
+                allocator.text("{}")
+            }
             Record(record) => allocator
                 .line()
                 .append(allocator.intersperse(
-                    sorted_map(&record.fields).iter().map(|&(id, rt)| {
+                    record.fields.iter().map(|(id, rt)| {
                         allocator
                             .quote_if_needed(id)
                             .append(allocator.space())
                             .append(if let MetaValue(mv) = rt.as_ref() {
-                                allocator
-                                    .metadata(mv, true)
-                                    .append(allocator.space())
-                                    .append(
-                                        mv.value
-                                            .clone()
-                                            .map(|v| {
-                                                allocator
-                                                    .text("=")
-                                                    .append(allocator.space())
-                                                    .append(v.pretty(allocator))
-                                            })
-                                            .unwrap_or_else(|| allocator.nil()),
-                                    )
+                                allocator.metadata(mv, true).append(
+                                    mv.value
+                                        .clone()
+                                        .map(|v| {
+                                            allocator
+                                                .space()
+                                                .append(allocator.text("="))
+                                                .append(allocator.space())
+                                                .append(v.pretty(allocator))
+                                        })
+                                        .unwrap_or_else(|| allocator.nil()),
+                                )
                             } else {
-                                allocator
-                                    .text("=")
-                                    .append(allocator.line())
-                                    .append(rt.to_owned().pretty(allocator).nest(2))
+                                let value = rt.to_owned().pretty(allocator);
+                                match rt.as_ref() {
+                                    // Keep the opening delimiter of records and arrays on the line
+                                    // of the field
+                                    Record(..) | Array(..) => {
+                                        allocator.text("=").append(allocator.space()).append(value)
+                                    }
+                                    // Same for multiline strings, whose content is indented
+                                    Str(s) if is_multiline_compatible(s) => allocator
+                                        .text("=")
+                                        .append(allocator.space())
+                                        .append(value.nest(2)),
+                                    _ => allocator
+                                        .text("=")
+                                        .append(allocator.line().append(value).nest(2))
+                                        .group(),
+                                }
                             })
                             .append(allocator.text(","))
                     }),
@@ -519,9 +639,10 @@ where
                 .line()
                 .append(
                     allocator.intersperse(
-                        sorted_map(&record.fields)
+                        record
+                            .fields
                             .iter()
-                            .map(|&(id, rt)| {
+                            .map(|(id, rt)| {
                                 allocator
                                     .quote_if_needed(id)
                                     .append(allocator.space())
@@ -616,6 +737,34 @@ where
                     .braces()
                     .group(),
             ),
+            MatchPattern(branches) => allocator.text("match").append(allocator.space()).append(
+                allocator
+                    .intersperse(
+                        branches.iter().map(|branch| {
+                            let (alias, pat) = &branch.pattern;
+                            allocator
+                                .pattern(alias, pat)
+                                .append(branch.guard.clone().map_or(allocator.nil(), |guard| {
+                                    allocator
+                                        .space()
+                                        .append(allocator.text("if"))
+                                        .append(allocator.space())
+                                        .append(guard.pretty(allocator))
+                                }))
+                                .append(allocator.space())
+                                .append(allocator.text("=>"))
+                                .append(allocator.space())
+                                .append(branch.body.clone().pretty(allocator))
+                                .append(allocator.text(","))
+                        }),
+                        allocator.line(),
+                    )
+                    .nest(2)
+                    .append(allocator.line_())
+                    .braces()
+                    .group(),
+            ),
+            Array(fields, _) if fields.is_empty() => allocator.text("[]"),
             Array(fields, _) => allocator
                 // NOTE: the Array attributes are ignored here.
                 .line()
@@ -649,7 +798,7 @@ where
                     .append(op.pretty(allocator))
                     .append(rtl.to_owned().pretty(allocator))
             } else {
-                if (&BinaryOp::Sub(), &Num(0.0)) == (op, rtl.as_ref()) {
+                if (&BinaryOp::Sub(), &Num(Number::Int(0))) == (op, rtl.as_ref()) {
                     allocator.text("-")
                 } else if let crate::term::OpPos::Prefix = op.pos() {
                     op.pretty(allocator)
@@ -684,10 +833,25 @@ where
             Sealed(_i, _rt, _lbl) => allocator.text("#<sealed>").append(allocator.hardline()),
 
             MetaValue(mv) => mv.to_owned().pretty(allocator),
-            Import(f) => allocator
-                .text("import")
-                .append(allocator.space())
-                .append(allocator.as_string(f.to_string_lossy()).double_quotes()),
+            Import(f, format) => {
+                let import = allocator
+                    .text("import")
+                    .append(allocator.space())
+                    .append(allocator.as_string(f.to_string_lossy()).double_quotes());
+
+                match format {
+                    Some(format) => import
+                        .append(allocator.text(" as `"))
+                        .append(allocator.text(match format {
+                            InputFormat::Nickel => "Nickel",
+                            InputFormat::Json => "Json",
+                            InputFormat::Yaml => "Yaml",
+                            InputFormat::Toml => "Toml",
+                            InputFormat::Text => "Text",
+                        })),
+                    None => import,
+                }
+            }
             ResolvedImport(id) => allocator.text(format!("import <file_id: {:?}>", id)),
             ParseError(_) => allocator.text("#<PARSE ERROR!>"),
         }
@@ -724,8 +888,20 @@ where
                 .append(allocator.text(";"))
                 .append(allocator.space())
                 .append(allocator.as_string(id)),
-            EnumRowsF::Extend { row, tail } => {
-                let builder = allocator.text("`").append(allocator.quote_if_needed(&row));
+            EnumRowsF::Extend {
+                row: EnumRowF { id, typ },
+                tail,
+            } => {
+                let builder = allocator.text("`").append(allocator.quote_if_needed(&id));
+                let builder = match typ {
+                    Some(ty) if ty.fmt_is_atom() => builder
+                        .append(allocator.space())
+                        .append(ty.pretty(allocator)),
+                    Some(ty) => builder
+                        .append(allocator.space())
+                        .append(ty.pretty(allocator).nest(2).parens()),
+                    None => builder,
+                };
                 let builder = if let EnumRowsF::Extend { .. } = tail.0 {
                     builder
                         .append(allocator.text(","))
@@ -21,17 +21,23 @@
 //! functions in [`crate::cache`] (see [`crate::cache::Cache::mk_eval_env`]).
 //! Each such value is added to the initial environment before the evaluation of the program.
 use crate::cache::*;
-use crate::error::{Error, ToDiagnostic};
+use crate::error::{Error, StructuredDiagnostic, ToDiagnostic};
 use crate::eval::cache::Cache as EvalCache;
 use crate::eval::VirtualMachine;
 use crate::identifier::Ident;
 use crate::parser::lexer::Lexer;
-use crate::term::{RichTerm, Term};
-use crate::{eval, parser};
-use codespan::FileId;
+use crate::position::{RawSpan, TermPos};
+use crate::term::{
+    make as mk_term, record::RecordData, BinaryOp, MergePriority, MetaValue, RichTerm, Term,
+    UnaryOp,
+};
+use crate::{eval, mk_app, parser, typecheck};
+use codespan::{ByteIndex, FileId};
 use codespan_reporting::term::termcolor::{Ansi, ColorChoice, StandardStream};
+use indexmap::IndexMap;
 use std::ffi::OsString;
-use std::io::{self, Cursor, Read};
+use std::io::{self, Cursor, Read, Write};
+use std::path::PathBuf;
 use std::result::Result;
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -54,6 +60,217 @@ impl std::str::FromStr for ColorOpt {
     }
 }
 
+/// The format of error reports.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ErrorFormat {
+    /// Human-readable diagnostics, with code snippets.
+    Text,
+    /// One [`StructuredDiagnostic`](crate::error::StructuredDiagnostic) per line, serialized as
+    /// JSON.
+    Json,
+}
+
+impl std::str::FromStr for ErrorFormat {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            _ => Err("possible values are 'text' or 'json'."),
+        }
+    }
+}
+
+/// A path to a field of the program, as in `services.api."k8s.io"`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FieldPath(pub Vec<Ident>);
+
+impl FieldPath {
+    /// Parse a dotted path from the beginning of `s`, stopping at the end of the input or at the
+    /// first unquoted `=`. Return the path and the rest of the input. Path elements may be
+    /// enclosed in double quotes when they contain a dot or an equal sign.
+    fn parse_prefix(s: &str) -> Result<(Self, &str), String> {
+        let mut path = Vec::new();
+        let mut rest = s;
+
+        loop {
+            let (elt, tail) = if let Some(quoted) = rest.strip_prefix('"') {
+                let end = quoted
+                    .find('"')
+                    .ok_or_else(|| format!("unterminated quoted field name in `{}`", s))?;
+                (&quoted[..end], &quoted[end + 1..])
+            } else {
+                let end = rest.find(['.', '=']).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            };
+
+            if elt.is_empty() {
+                return Err(format!("empty field name in `{}`", s));
+            }
+            path.push(Ident::from(elt));
+
+            match tail.strip_prefix('.') {
+                Some(tail) => rest = tail,
+                None if tail.is_empty() || tail.starts_with('=') => {
+                    return Ok((FieldPath(path), tail))
+                }
+                None => return Err(format!("unexpected `{}` in `{}`", tail, s)),
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for FieldPath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match FieldPath::parse_prefix(s)? {
+            (path, "") => Ok(path),
+            _ => Err(format!("expected a field path, got `{}`", s)),
+        }
+    }
+}
+
+impl std::fmt::Display for FieldPath {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path: Vec<_> = self.0.iter().map(Ident::label).collect();
+        write!(f, "{}", path.join("."))
+    }
+}
+
+/// An override of a field of the program, as in `server.port=8080`. The value is Nickel source
+/// code, which is merged with the top-level term of the program at the given priority before
+/// evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldOverride {
+    /// The path to the overridden field.
+    pub path: Vec<Ident>,
+    /// The source code of the new value.
+    pub value: String,
+    /// The priority of the new value.
+    pub priority: MergePriority,
+}
+
+impl FieldOverride {
+    /// Parse an override of the form `path.to.field=value`. See [`FieldPath`] for the syntax of
+    /// the path.
+    pub fn parse(s: &str, priority: MergePriority) -> Result<Self, String> {
+        match FieldPath::parse_prefix(s)? {
+            (FieldPath(path), tail) if tail.starts_with('=') => Ok(FieldOverride {
+                path,
+                value: String::from(&tail[1..]),
+                priority,
+            }),
+            _ => Err(format!("expected `path=value`, got `{}`", s)),
+        }
+    }
+
+    /// Return the same override, with a different priority.
+    pub fn with_priority(self, priority: MergePriority) -> Self {
+        FieldOverride { priority, ..self }
+    }
+
+    /// The name of the source holding the value of the override, used in error messages.
+    fn source_name(&self) -> String {
+        format!("<override {}>", FieldPath(self.path.clone()))
+    }
+
+    /// Parse and prepare the value of the override, and build the record `{path.to.field |
+    /// priority = value}` to be merged with the program.
+    fn prepare(
+        &self,
+        cache: &mut Cache,
+        type_ctxt: &typecheck::Context,
+    ) -> Result<RichTerm, Error> {
+        let file_id = cache.add_tmp(self.source_name(), self.value.clone());
+        cache.prepare(file_id, type_ctxt)?;
+        // unwrap(): the term has just been prepared
+        let value = cache.get_owned(file_id).unwrap();
+        let pos = value.pos.into_inherited();
+
+        let field = RichTerm::new(
+            Term::MetaValue(MetaValue {
+                priority: self.priority,
+                value: Some(value),
+                ..Default::default()
+            }),
+            pos,
+        );
+
+        Ok(self.path.iter().rev().fold(field, |acc, id| {
+            RichTerm::new(
+                Term::Record(RecordData::with_fields(IndexMap::from([(*id, acc)]))),
+                pos,
+            )
+        }))
+    }
+}
+
+impl std::str::FromStr for FieldOverride {
+    type Err = String;
+
+    /// Parse an override with the `force` priority. See [`FieldOverride::parse`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        FieldOverride::parse(s, MergePriority::Top)
+    }
+}
+
+/// A named argument of the top-level function of a program, as in `replicas=3`. The top-level
+/// term is applied to the record of all the arguments before evaluation.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TopLevelArg {
+    pub name: Ident,
+    /// The Nickel source code of the value, or a string literal if `is_str` is set.
+    pub value: String,
+    pub is_str: bool,
+}
+
+impl TopLevelArg {
+    /// Parse an argument of the form `name=value`.
+    pub fn parse(s: &str, is_str: bool) -> Result<Self, String> {
+        match s.split_once('=') {
+            Some((name, value)) if !name.is_empty() => Ok(TopLevelArg {
+                name: Ident::from(name),
+                value: String::from(value),
+                is_str,
+            }),
+            _ => Err(format!("expected `name=value`, got `{}`", s)),
+        }
+    }
+
+    /// The name of the source holding the value of the argument, used in error messages.
+    fn source_name(&self) -> String {
+        format!("<arg {}>", self.name)
+    }
+
+    /// Parse and prepare the value of the argument. A string value is still added to the cache, so
+    /// that error messages can point to it.
+    fn prepare(
+        &self,
+        cache: &mut Cache,
+        type_ctxt: &typecheck::Context,
+    ) -> Result<RichTerm, Error> {
+        let file_id = cache.add_tmp(self.source_name(), self.value.clone());
+
+        if self.is_str {
+            let span = RawSpan {
+                src_id: file_id,
+                start: ByteIndex(0),
+                end: ByteIndex(self.value.len() as u32),
+            };
+            Ok(RichTerm::new(
+                Term::Str(self.value.clone()),
+                TermPos::Original(span),
+            ))
+        } else {
+            cache.prepare(file_id, type_ctxt)?;
+            // unwrap(): the term has just been prepared
+            Ok(cache.get_owned(file_id).unwrap())
+        }
+    }
+}
+
 /// A Nickel program.
 ///
 /// Manage a file database, which stores the original source code of the program and eventually the
@@ -61,10 +278,21 @@ impl std::str::FromStr for ColorOpt {
 pub struct Program<EC: EvalCache> {
     /// The id of the program source in the file database.
     main_id: FileId,
+    /// The ids of additional input files, which are merged from left to right with the main one.
+    merged_ids: Vec<FileId>,
     /// The state of the Nickel virtual machine.
     vm: VirtualMachine<Cache, EC>,
     /// The color option to use when reporting errors.
     color_opt: ColorOpt,
+    /// The format to use when reporting errors.
+    error_format: ErrorFormat,
+    /// The field overrides to apply before evaluation.
+    overrides: Vec<FieldOverride>,
+    /// The arguments of the top-level function, if any.
+    args: Vec<TopLevelArg>,
+    /// Imported files reloaded by [`Self::reload_modified`], which must be processed again before
+    /// the next evaluation.
+    reloaded_ids: Vec<FileId>,
 }
 
 impl<EC: EvalCache> Program<EC> {
@@ -74,14 +302,35 @@ impl<EC: EvalCache> Program<EC> {
     }
 
     pub fn new_from_file(path: impl Into<OsString>) -> std::io::Result<Self> {
+        Self::new_from_files(std::iter::once(path))
+    }
+
+    /// Create a program from several files, which are merged from left to right, as in `file1 &
+    /// file2 & ...`. The first file is the main one, used by commands operating on source code.
+    pub fn new_from_files<P>(paths: impl IntoIterator<Item = P>) -> std::io::Result<Self>
+    where
+        P: Into<OsString>,
+    {
         let mut cache = Cache::new(ErrorTolerance::Strict);
-        let main_id = cache.add_file(path)?;
+        let mut ids = paths
+            .into_iter()
+            .map(|path| cache.add_file(path))
+            .collect::<std::io::Result<Vec<_>>>()?
+            .into_iter();
+        let main_id = ids.next().ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidInput, "no input file")
+        })?;
         let vm = VirtualMachine::new(cache);
 
         Ok(Self {
             main_id,
+            merged_ids: ids.collect(),
             vm,
             color_opt: ColorOpt::Auto,
+            error_format: ErrorFormat::Text,
+            overrides: Vec::new(),
+            args: Vec::new(),
+            reloaded_ids: Vec::new(),
         })
     }
 
@@ -97,15 +346,84 @@ impl<EC: EvalCache> Program<EC> {
 
         Ok(Self {
             main_id,
+            merged_ids: Vec::new(),
             vm,
             color_opt: ColorOpt::Auto,
+            error_format: ErrorFormat::Text,
+            overrides: Vec::new(),
+            args: Vec::new(),
+            reloaded_ids: Vec::new(),
         })
     }
 
+    /// Add field overrides, which are merged in order with the top-level term of the program
+    /// before evaluation.
+    pub fn add_overrides(&mut self, overrides: impl IntoIterator<Item = FieldOverride>) {
+        self.overrides.extend(overrides);
+    }
+
+    /// Add arguments to the top-level function of the program. The main term is then applied to
+    /// the record `{name1 = value1, ..}` before being merged with other input files. Fail if an
+    /// argument is given several times, whether as a Nickel expression or as a string.
+    pub fn add_args(&mut self, args: impl IntoIterator<Item = TopLevelArg>) -> Result<(), String> {
+        for arg in args {
+            if self.args.iter().any(|other| other.name == arg.name) {
+                return Err(format!(
+                    "the argument `{}` is given more than once",
+                    arg.name
+                ));
+            }
+
+            self.args.push(arg);
+        }
+
+        Ok(())
+    }
+
     /// Retrieve the parsed term and typecheck it, and generate a fresh initial environment. Return
-    /// both.
+    /// both. If there are top-level arguments, the term is first applied to them. If there are
+    /// several input files or field overrides, the term is then merged with each of them, in this
+    /// order.
     fn prepare_eval(&mut self) -> Result<(RichTerm, eval::Environment), Error> {
-        self.vm.prepare_eval(self.main_id)
+        self.prepare_reloaded()?;
+        let (t, initial_env) = self.vm.prepare_eval(self.main_id)?;
+
+        if self.args.is_empty() && self.merged_ids.is_empty() && self.overrides.is_empty() {
+            return Ok((t, initial_env));
+        }
+
+        let cache = self.vm.import_resolver_mut();
+        // unwrap(): the stdlib has been loaded by `prepare_eval`
+        let type_ctxt = cache.mk_type_ctxt().unwrap();
+        let t = if self.args.is_empty() {
+            t
+        } else {
+            let fields = self
+                .args
+                .iter()
+                .map(|arg| Ok((arg.name, arg.prepare(cache, &type_ctxt)?)))
+                .collect::<Result<IndexMap<_, _>, Error>>()?;
+            let pos = t.pos.into_inherited();
+            mk_app!(t, Term::Record(RecordData::with_fields(fields))).with_pos(pos)
+        };
+        let t = self.merged_ids.iter().try_fold(t, |acc, id| {
+            cache.prepare(*id, &type_ctxt)?;
+            // unwrap(): the term has just been prepared
+            Ok::<_, Error>(mk_term::op2(
+                BinaryOp::Merge(),
+                acc,
+                cache.get_owned(*id).unwrap(),
+            ))
+        })?;
+        let t = self.overrides.iter().try_fold(t, |acc, ovd| {
+            Ok::<_, Error>(mk_term::op2(
+                BinaryOp::Merge(),
+                acc,
+                ovd.prepare(cache, &type_ctxt)?,
+            ))
+        })?;
+
+        Ok((t, initial_env))
     }
 
     /// Parse if necessary, typecheck and then evaluate the program.
@@ -122,6 +440,51 @@ impl<EC: EvalCache> Program<EC> {
         self.vm.eval_full(t, &initial_env).map_err(|e| e.into())
     }
 
+    /// Same as `eval_full`, but only evaluates and returns the field at the given path. Other
+    /// fields are evaluated only as far as this field depends on them.
+    pub fn eval_full_field(&mut self, path: &FieldPath) -> Result<RichTerm, Error> {
+        let (t, initial_env) = self.prepare_eval()?;
+        let t = path.0.iter().fold(t, |acc, id| {
+            let pos = acc.pos.into_inherited();
+            mk_term::op1(UnaryOp::StaticAccess(*id), acc).with_pos(pos)
+        });
+        self.vm.reset();
+        self.vm.eval_full(t, &initial_env).map_err(|e| e.into())
+    }
+
+    /// Same as `eval_full`, or `eval_full_field` if a path is given, but keep the annotations of
+    /// record fields and array elements in the result. Used by the TOML export to recover
+    /// datetimes. See [`crate::eval::VirtualMachine::eval_full_annotated`].
+    pub fn eval_full_annotated(&mut self, path: Option<&FieldPath>) -> Result<RichTerm, Error> {
+        let (t, initial_env) = self.prepare_eval()?;
+        let t = path
+            .iter()
+            .flat_map(|path| path.0.iter())
+            .fold(t, |acc, id| {
+                let pos = acc.pos.into_inherited();
+                mk_term::op1(UnaryOp::StaticAccess(*id), acc).with_pos(pos)
+            });
+        self.vm.reset();
+        self.vm
+            .eval_full_annotated(t, &initial_env)
+            .map_err(|e| e.into())
+    }
+
+    /// Generate a JSON Schema from the contract defined by the program, or by the field at the
+    /// given path. See [`crate::schema`].
+    pub fn export_schema(&mut self, path: Option<&FieldPath>) -> Result<serde_json::Value, Error> {
+        let (t, initial_env) = self.prepare_eval()?;
+        let t = path
+            .iter()
+            .flat_map(|path| path.0.iter())
+            .fold(t, |acc, id| {
+                let pos = acc.pos.into_inherited();
+                mk_term::op1(UnaryOp::StaticAccess(*id), acc).with_pos(pos)
+            });
+        self.vm.reset();
+        crate::schema::to_json_schema(&mut self.vm, t, &initial_env).map_err(|e| e.into())
+    }
+
     /// Same as `eval_full`, but does not substitute all variables.
     pub fn eval_deep(&mut self) -> Result<RichTerm, Error> {
         let (t, initial_env) = self.prepare_eval()?;
@@ -129,29 +492,112 @@ impl<EC: EvalCache> Program<EC> {
         self.vm.eval_deep(t, &initial_env).map_err(|e| e.into())
     }
 
-    /// Wrapper for [`query`].
+    /// Wrapper for [`query_term`]. As for [`Self::eval_full`], the queried term is the main term
+    /// applied to the top-level arguments and merged with the other input files and overrides.
     pub fn query(&mut self, path: Option<String>) -> Result<Term, Error> {
-        let initial_env = self.vm.prepare_stdlib()?;
-        query(&mut self.vm, self.main_id, &initial_env, path)
+        let (t, initial_env) = self.prepare_eval()?;
+        query_term(&mut self.vm, t, &initial_env, path)
     }
 
     /// Load, parse, and typecheck the program and the standard library, if not already done.
     pub fn typecheck(&mut self) -> Result<(), Error> {
-        self.vm.import_resolver_mut().parse(self.main_id)?;
+        self.prepare_reloaded()?;
+        let file_ids: Vec<_> = std::iter::once(self.main_id)
+            .chain(self.merged_ids.iter().copied())
+            .collect();
+
+        for file_id in file_ids.iter() {
+            self.vm.import_resolver_mut().parse(*file_id)?;
+        }
         self.vm.import_resolver_mut().load_stdlib()?;
         let initial_env = self.vm.import_resolver().mk_type_ctxt().expect("program::typecheck(): stdlib has been loaded but was not found in cache on mk_types_env()");
-        self.vm
-            .import_resolver_mut()
-            .resolve_imports(self.main_id)
-            .map_err(|cache_err| {
-                cache_err.unwrap_error("program::typecheck(): expected source to be parsed")
-            })?;
-        self.vm
-            .import_resolver_mut()
-            .typecheck(self.main_id, &initial_env)
-            .map_err(|cache_err| {
-                cache_err.unwrap_error("program::typecheck(): expected source to be parsed")
+
+        for file_id in file_ids {
+            self.vm
+                .import_resolver_mut()
+                .resolve_imports(file_id)
+                .map_err(|cache_err| {
+                    cache_err.unwrap_error("program::typecheck(): expected source to be parsed")
+                })?;
+            self.vm
+                .import_resolver_mut()
+                .typecheck(file_id, &initial_env)
+                .map_err(|cache_err| {
+                    cache_err.unwrap_error("program::typecheck(): expected source to be parsed")
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Resolve the imports of the program, and return its import graph: every file of the
+    /// program, starting with the input files, together with the files it imports directly.
+    /// Imported data files (JSON, YAML or TOML) are included as well. Files are identified by
+    /// their normalized path.
+    pub fn import_graph(&mut self) -> Result<Vec<(PathBuf, Vec<PathBuf>)>, Error> {
+        let inputs: Vec<_> = std::iter::once(self.main_id)
+            .chain(self.merged_ids.iter().copied())
+            .collect();
+        let cache = self.vm.import_resolver_mut();
+
+        for file_id in inputs.iter() {
+            cache.parse(*file_id)?;
+            cache.resolve_imports(*file_id).map_err(|cache_err| {
+                cache_err.unwrap_error("program::import_graph(): expected source to be parsed")
             })?;
+        }
+
+        let mut graph: Vec<(FileId, Vec<FileId>)> = Vec::new();
+        let mut queue: std::collections::VecDeque<FileId> = inputs.into_iter().collect();
+
+        while let Some(file_id) = queue.pop_front() {
+            if graph.iter().any(|(id, _)| *id == file_id) {
+                continue;
+            }
+
+            let imports = cache.direct_imports(file_id);
+            queue.extend(imports.iter().copied());
+            graph.push((file_id, imports));
+        }
+
+        let path = |id: FileId| PathBuf::from(cache.name(id));
+        Ok(graph
+            .into_iter()
+            .map(|(id, imports)| (path(id), imports.into_iter().map(path).collect()))
+            .collect())
+    }
+
+    /// Reload the input files and the imported files which have been modified on disk since
+    /// they were loaded. The next evaluation or typechecking only parses and processes again the
+    /// reloaded files. Return `true` if at least one file was reloaded.
+    pub fn reload_modified(&mut self) -> bool {
+        let reloaded = self.vm.import_resolver_mut().reload_modified();
+        // Input files are processed anew by each evaluation
+        let imports = reloaded
+            .iter()
+            .filter(|id| **id != self.main_id && !self.merged_ids.contains(id));
+
+        for id in imports {
+            if !self.reloaded_ids.contains(id) {
+                self.reloaded_ids.push(*id);
+            }
+        }
+
+        !reloaded.is_empty()
+    }
+
+    /// Process the files reloaded by [`Self::reload_modified`]. They must be processed before the
+    /// files importing them, which refer to them through their unchanged `FileId`.
+    fn prepare_reloaded(&mut self) -> Result<(), Error> {
+        if self.reloaded_ids.is_empty() {
+            return Ok(());
+        }
+
+        let cache = self.vm.import_resolver_mut();
+        cache.load_stdlib()?;
+        // unwrap(): the stdlib has just been loaded
+        let type_ctxt = cache.mk_type_ctxt().unwrap();
+        cache.prepare_reloaded(&self.reloaded_ids, &type_ctxt)?;
+        self.reloaded_ids.clear();
         Ok(())
     }
 
@@ -163,6 +609,29 @@ impl<EC: EvalCache> Program<EC> {
         report(self.vm.import_resolver_mut(), error, self.color_opt)
     }
 
+    /// Report an error in the format set by [`Program::set_error_format`]: either as
+    /// human-readable diagnostics, as [`Program::report`] does, or as JSON (see [`report_json`]).
+    pub fn report_with_format<E>(&mut self, error: E)
+    where
+        E: Into<Error>,
+    {
+        match self.error_format {
+            ErrorFormat::Text => self.report(error.into()),
+            ErrorFormat::Json => {
+                let diagnostics = self.structured_diagnostics(&error.into());
+                report_json(&diagnostics);
+            }
+        }
+    }
+
+    /// Convert an error to structured diagnostics, which can be serialized or processed further
+    /// by tools embedding Nickel.
+    pub fn structured_diagnostics(&mut self, error: &Error) -> Vec<StructuredDiagnostic> {
+        let cache = self.vm.import_resolver_mut();
+        let contracts_id = cache.id_of("<stdlib/contract.ncl>");
+        error.to_structured_diagnostics(cache.files_mut(), contracts_id)
+    }
+
     /// Build an error report as a string and return it.
     pub fn report_as_str<E>(&mut self, error: E) -> String
     where
@@ -200,6 +669,53 @@ impl<EC: EvalCache> Program<EC> {
         self.color_opt = c;
     }
 
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.error_format = format;
+    }
+
+    /// Format the source of the program, preserving comments. The source must be syntactically
+    /// valid. See [`crate::format`].
+    pub fn format(&mut self) -> Result<String, Error> {
+        let cache = self.vm.import_resolver_mut();
+        cache.parse(self.main_id)?;
+        let source = cache.files().source(self.main_id);
+        Ok(crate::format::format(self.main_id, source)?)
+    }
+
+    /// Return the original source of the program.
+    pub fn source(&self) -> &str {
+        self.vm.import_resolver().files().source(self.main_id)
+    }
+
+    /// Parse the program as data in the given format, typically JSON, YAML or TOML, and print it
+    /// back as Nickel source code.
+    pub fn convert(&mut self, format: InputFormat) -> Result<String, Error> {
+        let rt = self
+            .vm
+            .import_resolver()
+            .parse_nocache_multi(self.main_id, format)?
+            .0;
+        Ok(render(rt))
+    }
+
+    /// Read the program as a JSON Schema and print the corresponding Nickel contract. The schema
+    /// is usually written in JSON or YAML, but a Nickel program is evaluated and used as well.
+    pub fn import_schema(&mut self, format: InputFormat) -> Result<String, Error> {
+        let rt = match format {
+            InputFormat::Nickel => self.eval_full()?,
+            format => {
+                self.vm
+                    .import_resolver()
+                    .parse_nocache_multi(self.main_id, format)?
+                    .0
+            }
+        };
+        crate::serialize::validate(crate::serialize::ExportFormat::Json, &rt)?;
+        let schema = serde_json::to_value(&rt)
+            .map_err(|err| crate::error::SerializationError::Other(err.to_string()))?;
+        Ok(render(crate::schema::import::from_json_schema(&schema)))
+    }
+
     pub fn pprint_ast(
         &mut self,
         out: &mut std::io::BufWriter<Box<dyn std::io::Write>>,
@@ -248,6 +764,19 @@ pub fn query<EC: EvalCache>(
 ) -> Result<Term, Error> {
     vm.import_resolver_mut()
         .prepare(file_id, &initial_env.type_ctxt)?;
+    // unwrap(): the term has just been prepared
+    let rt = vm.import_resolver().get_owned(file_id).unwrap();
+    query_term(vm, rt, &initial_env.eval_env, path)
+}
+
+/// Same as [`query`], but query a term which has already been prepared instead of a file of the
+/// cache.
+pub fn query_term<EC: EvalCache>(
+    vm: &mut VirtualMachine<Cache, EC>,
+    rt: RichTerm,
+    initial_env: &eval::Environment,
+    path: Option<String>,
+) -> Result<Term, Error> {
     let t = if let Some(p) = path {
         // Parsing `y.path`. We `seq` it to force the evaluation of the underlying value,
         // which can be then showed to the user. The newline gives better messages in case of
@@ -259,7 +788,6 @@ pub fn query<EC: EvalCache>(
 
         // Substituting `y` for `t`
         let mut env = eval::Environment::new();
-        let rt = vm.import_resolver().get_owned(file_id).unwrap();
         eval::env_add(
             &mut vm.cache,
             &mut env,
@@ -269,11 +797,11 @@ pub fn query<EC: EvalCache>(
         );
         eval::subst(&vm.cache, new_term, &eval::Environment::new(), &env)
     } else {
-        vm.import_resolver().get_owned(file_id).unwrap()
+        rt
     };
 
     vm.reset();
-    Ok(vm.eval_meta(t, &initial_env.eval_env)?.into())
+    Ok(vm.eval_meta(t, initial_env)?.into())
 }
 
 /// Pretty-print an error.
@@ -303,6 +831,22 @@ where
     };
 }
 
+/// Report structured diagnostics on the standard error, as one JSON object per line.
+pub fn report_json(diagnostics: &[StructuredDiagnostic]) {
+    let mut stderr = io::stderr().lock();
+    let result = diagnostics.iter().try_for_each(|d| {
+        serde_json::to_writer(&mut stderr, d)?;
+        writeln!(stderr).map_err(serde_json::Error::io)
+    });
+
+    if let Err(err) = result {
+        panic!(
+            "Program::report_json: could not print an error on stderr: {}",
+            err
+        )
+    }
+}
+
 impl From<ColorOpt> for ColorChoice {
     fn from(c: ColorOpt) -> Self {
         match c {
@@ -429,13 +973,27 @@ mod doc {
     }
 }
 
+/// Pretty print a term as Nickel source code.
+fn render(rt: RichTerm) -> String {
+    use crate::pretty::*;
+    use pretty::BoxAllocator;
+
+    let doc: DocBuilder<_, ()> = rt.pretty(&BoxAllocator);
+    let mut out = Vec::new();
+    // unwrap(): writing to a vector in memory can't fail
+    doc.render(80, &mut out).unwrap();
+    out.push(b'\n');
+    // unwrap(): the pretty printer only outputs valid UTF-8
+    String::from_utf8(out).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::error::EvalError;
     use crate::eval::cache::CBNCache;
     use crate::position::TermPos;
-    use crate::term::array::ArrayAttrs;
+    use crate::term::{array::ArrayAttrs, number::Number};
     use assert_matches::assert_matches;
     use std::io::Cursor;
 
@@ -473,11 +1031,11 @@ mod tests {
 
         // [2, "ab", [1, [3]]]
         let expd = mk_array!(
-            Term::Num(2_f64),
+            Term::Num(Number::from(2.0)),
             Term::Str(String::from("ab")),
             mk_array!(
-                Term::Num(1_f64),
-                mk_array!(Term::Num(3_f64); ArrayAttrs::new().closurized());
+                Term::Num(Number::from(1.0)),
+                mk_array!(Term::Num(Number::from(3.0)); ArrayAttrs::new().closurized());
                 ArrayAttrs::new().closurized()
             );
             ArrayAttrs::new().closurized()
@@ -489,7 +1047,7 @@ mod tests {
         // Records are parsed as RecRecords, so we need to build one by hand
         let expd = mk_record!((
             "foo",
-            mk_record!(("bar", mk_record!(("baz", Term::Num(2.0)))))
+            mk_record!(("bar", mk_record!(("baz", Term::Num(Number::from(2.0))))))
         ));
         assert_eq!(t.without_pos(), expd);
 
@@ -509,4 +1067,268 @@ mod tests {
             Err(Error::ParseErrors(_))
         );
     }
+
+    fn eval_full_with_overrides(s: &str, overrides: &[&str]) -> Result<RichTerm, Error> {
+        let mut p: Program<EC> = Program::new_from_source(Cursor::new(s), "<test>").unwrap();
+        p.add_overrides(
+            overrides
+                .iter()
+                .map(|ovd| ovd.parse::<FieldOverride>().unwrap()),
+        );
+        p.eval_full()
+    }
+
+    #[test]
+    fn parse_override() {
+        assert_eq!(
+            FieldOverride::parse("foo.\"bar.baz\"=1 + 1", MergePriority::Bottom),
+            Ok(FieldOverride {
+                path: vec![Ident::from("foo"), Ident::from("bar.baz")],
+                value: String::from("1 + 1"),
+                priority: MergePriority::Bottom,
+            })
+        );
+        assert!(FieldOverride::parse("foo.bar", MergePriority::Top).is_err());
+        assert!(FieldOverride::parse("foo..bar=1", MergePriority::Top).is_err());
+        assert!(FieldOverride::parse("=1", MergePriority::Top).is_err());
+    }
+
+    #[test]
+    fn parse_field_path() {
+        assert_eq!(
+            "foo.\"bar=baz\".qux".parse::<FieldPath>(),
+            Ok(FieldPath(vec![
+                Ident::from("foo"),
+                Ident::from("bar=baz"),
+                Ident::from("qux")
+            ]))
+        );
+        assert!("foo.bar=1".parse::<FieldPath>().is_err());
+        assert!("foo.".parse::<FieldPath>().is_err());
+        assert!("\"foo".parse::<FieldPath>().is_err());
+    }
+
+    #[test]
+    fn evaluation_field() {
+        let mut p: Program<EC> = Program::new_from_source(
+            Cursor::new(
+                "{foo.bar = {baz = qux}, qux = 1, broken | Num = \"a\", other = 1 + \"a\"}",
+            ),
+            "<test>",
+        )
+        .unwrap();
+
+        let path = "foo.bar".parse().unwrap();
+        let t = p.eval_full_field(&path).unwrap();
+        assert_eq!(
+            t.without_pos(),
+            crate::mk_record!(("baz", Term::Num(Number::from(1.0))))
+        );
+
+        assert_matches!(
+            p.eval_full_field(&"foo.baz".parse().unwrap()),
+            Err(Error::EvalError(EvalError::FieldMissing(..)))
+        );
+        assert_matches!(
+            p.eval_full_field(&"broken".parse().unwrap()),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+    }
+
+    #[test]
+    fn top_level_args() {
+        let eval_with_args = |args: &[(&str, bool)]| {
+            let mut p: Program<EC> = Program::new_from_source(
+                Cursor::new("fun {foo | Num, bar | Str} => {baz = foo + 1, qux = bar}"),
+                "<test>",
+            )
+            .unwrap();
+            p.add_args(
+                args.iter()
+                    .map(|(arg, is_str)| TopLevelArg::parse(arg, *is_str).unwrap()),
+            )
+            .unwrap();
+            p.eval_full()
+        };
+
+        let t = eval_with_args(&[("foo=1 + 1", false), ("bar=a=b", true)]).unwrap();
+        assert_eq!(
+            t.without_pos(),
+            crate::mk_record!(
+                ("baz", Term::Num(Number::from(3.0))),
+                ("qux", Term::Str(String::from("a=b")))
+            )
+        );
+
+        assert_matches!(
+            eval_with_args(&[("foo=\"a\"", false), ("bar=b", true)]),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+        assert_matches!(
+            eval_with_args(&[("foo=1", true), ("bar=b", true)]),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+
+        let mut p: Program<EC> =
+            Program::new_from_source(Cursor::new("fun {foo} => foo"), "<test>").unwrap();
+        assert!(p
+            .add_args([
+                TopLevelArg::parse("foo=1", false).unwrap(),
+                TopLevelArg::parse("foo=2", true).unwrap(),
+            ])
+            .is_err());
+        assert!(TopLevelArg::parse("=1", false).is_err());
+        assert!(TopLevelArg::parse("foo", true).is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        use crate::mk_record;
+
+        let t = eval_full_with_overrides(
+            "{foo.bar = 1, foo.baz | default = 2, qux = foo.bar}",
+            &["foo.bar=3", "foo.baz=\"a\""],
+        )
+        .unwrap();
+        let expd = mk_record!(
+            (
+                "foo",
+                mk_record!(
+                    ("bar", Term::Num(Number::from(3.0))),
+                    ("baz", Term::Str(String::from("a")))
+                )
+            ),
+            ("qux", Term::Num(Number::from(3.0)))
+        );
+        assert_eq!(t.without_pos(), expd);
+
+        assert_matches!(
+            eval_full_with_overrides("{foo | Num = 1}", &["foo=\"a\""]),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+        assert_matches!(
+            eval_full_with_overrides("{foo = 1}", &["foo=1 +"]),
+            Err(Error::ParseErrors(_))
+        );
+    }
+
+    #[test]
+    fn structured_diagnostics() {
+        let mut p: Program<EC> =
+            Program::new_from_source(Cursor::new("{\n  foo | Num = \"a\"\n}"), "<test>").unwrap();
+        let err = p.eval_full().unwrap_err();
+        let diagnostics = p.structured_diagnostics(&err);
+
+        let diagnostic = &diagnostics[0];
+        assert_eq!(diagnostic.kind, "EvalError::BlameError");
+        assert_eq!(diagnostic.severity, "error");
+        let label = diagnostic
+            .labels
+            .iter()
+            .find(|label| label.primary)
+            .unwrap();
+        assert_eq!(label.file, "<test>");
+        assert_eq!(label.range, 16..19);
+        assert_eq!(
+            label.start,
+            Some(crate::error::Location {
+                line: 2,
+                column: 15
+            })
+        );
+
+        let mut p: Program<EC> =
+            Program::new_from_source(Cursor::new("(1 : Str) + ("), "<test>").unwrap();
+        let err = p.typecheck().unwrap_err();
+        let kinds: Vec<String> = p
+            .structured_diagnostics(&err)
+            .into_iter()
+            .map(|d| d.kind)
+            .collect();
+        assert_eq!(kinds, vec!["ParseError::UnexpectedEOF"]);
+    }
+
+    #[test]
+    fn reload_modified() {
+        use std::time::Duration;
+
+        // Write a file, making sure that its timestamp changes
+        fn write(path: &std::path::Path, content: &str) {
+            let before = crate::cache::timestamp(path).ok();
+            loop {
+                std::fs::write(path, content).unwrap();
+                if crate::cache::timestamp(path).ok() != before {
+                    break;
+                }
+                std::thread::sleep(Duration::from_millis(10));
+            }
+        }
+
+        let dir = std::env::temp_dir().join(format!("nickel-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.ncl");
+        write(&main, "{foo = import \"foo.ncl\", bar = 1}");
+        write(&dir.join("foo.ncl"), "{baz = import \"baz.json\"}");
+        write(&dir.join("baz.json"), "1");
+
+        let mut p: Program<EC> = Program::new_from_file(&main).unwrap();
+        let to_json = |t: RichTerm| serde_json::to_value(t).unwrap();
+        assert_eq!(
+            to_json(p.eval_full().unwrap()),
+            serde_json::json!({"foo": {"baz": 1}, "bar": 1})
+        );
+        assert!(!p.reload_modified());
+
+        write(&dir.join("baz.json"), "2");
+        assert!(p.reload_modified());
+        assert_eq!(
+            to_json(p.eval_full().unwrap()),
+            serde_json::json!({"foo": {"baz": 2}, "bar": 1})
+        );
+
+        write(&dir.join("foo.ncl"), "{baz | Str = import \"baz.json\"}");
+        assert!(p.reload_modified());
+        assert_matches!(
+            p.eval_full(),
+            Err(Error::EvalError(EvalError::BlameError { .. }))
+        );
+
+        write(&dir.join("baz.json"), "\"a\"");
+        write(&main, "{foo = import \"foo.ncl\", bar = 2}");
+        assert!(p.reload_modified());
+        assert_eq!(
+            to_json(p.eval_full().unwrap()),
+            serde_json::json!({"foo": {"baz": "a"}, "bar": 2})
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn import_graph() {
+        let dir = std::env::temp_dir().join(format!("nickel-deps-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let main = dir.join("main.ncl");
+        std::fs::write(&main, "[import \"a.ncl\", import \"b.ncl\"]").unwrap();
+        std::fs::write(dir.join("a.ncl"), "[import \"c.json\", import \"b.ncl\"]").unwrap();
+        std::fs::write(dir.join("b.ncl"), "let x = import \"c.json\" in x").unwrap();
+        std::fs::write(dir.join("c.json"), "1").unwrap();
+
+        let mut p: Program<EC> = Program::new_from_file(&main).unwrap();
+        let graph = p.import_graph().unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let name = |path: &PathBuf| path.file_name().unwrap().to_string_lossy().into_owned();
+        let graph: Vec<(String, Vec<String>)> = graph
+            .iter()
+            .map(|(file, imports)| (name(file), imports.iter().map(name).collect()))
+            .collect();
+        let expected: Vec<(String, Vec<String>)> = vec![
+            ("main.ncl".into(), vec!["a.ncl".into(), "b.ncl".into()]),
+            ("a.ncl".into(), vec!["c.json".into(), "b.ncl".into()]),
+            ("b.ncl".into(), vec!["c.json".into()]),
+            ("c.json".into(), vec![]),
+        ];
+        assert_eq!(graph, expected);
+    }
 }
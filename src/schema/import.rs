@@ -0,0 +1,397 @@
+//! Generation of Nickel contracts from JSON Schemas.
+//!
+//! This is the reverse of [`to_json_schema`](super::to_json_schema): a JSON Schema, such as the
+//! ones published for Kubernetes resources or CI configurations, is translated to a Nickel term
+//! which can then be pretty-printed and used as a contract:
+//!
+//! - `properties` are translated to record contracts, where the `description` of a property
+//!   becomes its `doc`, its `default` value a default value, and properties which aren't
+//!   `required` are `optional`. Record contracts are open unless `additionalProperties` is
+//!   `false`. Objects without `properties` are translated to dictionaries.
+//! - `type` is translated to the corresponding type: `Str`, `Num`, `Bool`, `Array`, or the
+//!   `num.Int` contract for `integer`.
+//! - `enum` and `const` are translated to an enum type if all the values are strings, and to a
+//!   contract checking that the value is one of the given values otherwise.
+//! - `$ref`s to `#/definitions/<name>` or `#/$defs/<name>` are translated to an access to a
+//!   recursive `definitions` binding, which holds the contract of each definition. Definitions can
+//!   thus refer to each other, or to themselves.
+//!
+//! Other keywords, and schemas which can't be expressed by the above (such as `anyOf` or a list of
+//! types), are translated to `Dyn`. The generated code refers to the standard library and to
+//! `definitions`, which may be shadowed by a property of the same name.
+use crate::{
+    identifier::Ident,
+    label::Label,
+    mk_app, mk_fun,
+    term::{
+        array::{Array, ArrayAttrs},
+        make as mk_term,
+        record::RecordData,
+        BinaryOp, Contract, LetAttrs, MergePriority, MetaValue, RichTerm, Term, UnaryOp,
+    },
+    types::{EnumRowF, EnumRows, EnumRowsF, TypeF, Types},
+};
+
+use indexmap::IndexMap;
+use serde_json::{Map, Value};
+
+/// The name of the recursive binding holding the contracts of the schema's definitions.
+pub const DEFINITIONS: &str = "definitions";
+
+/// Generate a Nickel contract from a JSON Schema. The result is a term evaluating to a contract,
+/// preceded by a binding of the schema's definitions if there are any.
+pub fn from_json_schema(schema: &Value) -> RichTerm {
+    let body = contract_term(types(schema));
+    let definitions: IndexMap<Ident, RichTerm> = ["definitions", "$defs"]
+        .iter()
+        .filter_map(|key| schema.get(key).and_then(Value::as_object))
+        .flat_map(|defs| defs.iter())
+        .map(|(name, def)| {
+            let value = contract_term(types(def));
+            let term = match description(def) {
+                Some(doc) => Term::MetaValue(MetaValue {
+                    doc: Some(doc),
+                    value: Some(value),
+                    ..Default::default()
+                })
+                .into(),
+                None => value,
+            };
+            (Ident::from(name), term)
+        })
+        .collect();
+
+    if definitions.is_empty() {
+        body
+    } else {
+        let attrs = LetAttrs {
+            rec: true,
+            ..Default::default()
+        };
+        Term::Let(
+            Ident::from(DEFINITIONS),
+            Term::Record(RecordData::with_fields(definitions)).into(),
+            body,
+            attrs,
+        )
+        .into()
+    }
+}
+
+/// Translate a schema to a type. Contracts which aren't types are represented as flat types.
+fn types(schema: &Value) -> Types {
+    let schema = match schema {
+        Value::Object(schema) => schema,
+        // `true` accepts everything, and `false` is seldom used outside of
+        // `additionalProperties`, which is handled separately.
+        _ => return Types(TypeF::Dyn),
+    };
+
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        return reference_type(reference);
+    }
+
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return enum_type(values);
+    }
+
+    if let Some(value) = schema.get("const") {
+        return enum_type(std::slice::from_ref(value));
+    }
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => Types(TypeF::Str),
+        Some("number") => Types(TypeF::Num),
+        Some("integer") => Types(TypeF::Flat(mk_term::op1(
+            UnaryOp::StaticAccess(Ident::from("Int")),
+            mk_term::var("num"),
+        ))),
+        Some("boolean") => Types(TypeF::Bool),
+        Some("null") => Types(TypeF::Flat(mk_app!(
+            mk_term::op1(
+                UnaryOp::StaticAccess(Ident::from("from_predicate")),
+                mk_term::var("contract")
+            ),
+            mk_fun!(
+                "value",
+                mk_term::op2(BinaryOp::Eq(), mk_term::var("value"), Term::Null)
+            )
+        ))),
+        Some("array") => Types(TypeF::Array(Box::new(
+            schema.get("items").map(types).unwrap_or(Types(TypeF::Dyn)),
+        ))),
+        Some("object") => object_type(schema),
+        None if schema.contains_key("properties") => object_type(schema),
+        _ => Types(TypeF::Dyn),
+    }
+}
+
+/// Translate the schema of an object to a record contract if it has properties, or to a
+/// dictionary otherwise.
+fn object_type(schema: &Map<String, Value>) -> Types {
+    let additional = schema.get("additionalProperties");
+    let properties = match schema.get("properties").and_then(Value::as_object) {
+        Some(properties) => properties,
+        None => {
+            return match additional {
+                Some(Value::Bool(false)) => {
+                    Types(TypeF::Flat(Term::Record(RecordData::empty()).into()))
+                }
+                Some(additional) => Types(TypeF::Dict(Box::new(types(additional)))),
+                None => Types(TypeF::Dict(Box::new(Types(TypeF::Dyn)))),
+            }
+        }
+    };
+
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|required| required.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let fields = properties
+        .iter()
+        .map(|(name, property)| {
+            let field = field(property, required.contains(&name.as_str()));
+            (Ident::from(name), field)
+        })
+        .collect();
+
+    let mut record = RecordData::with_fields(fields);
+    record.attrs.open = !matches!(additional, Some(Value::Bool(false)));
+    Types(TypeF::Flat(Term::Record(record).into()))
+}
+
+/// Translate the schema of a record field to a metavalue.
+fn field(schema: &Value, required: bool) -> RichTerm {
+    let default = schema
+        .get("default")
+        .and_then(|default| serde_json::from_value::<RichTerm>(default.clone()).ok());
+    let contract = match types(schema) {
+        // A field must have at least one annotation to be printed as valid Nickel source
+        Types(TypeF::Dyn) if default.is_some() || !required => None,
+        types => Some(Contract {
+            types,
+            label: Label::default(),
+        }),
+    };
+
+    Term::MetaValue(MetaValue {
+        doc: description(schema),
+        contracts: contract.into_iter().collect(),
+        opt: !required && default.is_none(),
+        priority: if default.is_some() {
+            MergePriority::Bottom
+        } else {
+            MergePriority::Neutral
+        },
+        value: default,
+        ..Default::default()
+    })
+    .into()
+}
+
+/// Translate a `$ref` to an access to the corresponding definition. References outside of the
+/// schema's definitions aren't supported.
+fn reference_type(reference: &str) -> Types {
+    let name = reference
+        .strip_prefix("#/definitions/")
+        .or_else(|| reference.strip_prefix("#/$defs/"))
+        .filter(|name| !name.contains('/'));
+
+    match name {
+        Some(name) => {
+            // Unescape the JSON pointer, see RFC 6901
+            let name = name.replace("~1", "/").replace("~0", "~");
+            Types(TypeF::Flat(mk_term::op1(
+                UnaryOp::StaticAccess(Ident::from(name)),
+                mk_term::var(DEFINITIONS),
+            )))
+        }
+        None => Types(TypeF::Dyn),
+    }
+}
+
+/// Translate the values of an `enum` to an enum type if they are all strings, or to a contract
+/// checking that the value is one of them otherwise.
+fn enum_type(values: &[Value]) -> Types {
+    let tags: Option<Vec<&str>> = values.iter().map(Value::as_str).collect();
+
+    match tags {
+        Some(tags) if !tags.is_empty() => {
+            let rows = tags
+                .into_iter()
+                .rev()
+                .fold(EnumRows(EnumRowsF::Empty), |tail, tag| {
+                    EnumRows(EnumRowsF::Extend {
+                        row: EnumRowF {
+                            id: Ident::from(tag),
+                            typ: None,
+                        },
+                        tail: Box::new(tail),
+                    })
+                });
+            Types(TypeF::Enum(rows))
+        }
+        _ => {
+            let values: Array = values
+                .iter()
+                .filter_map(|value| serde_json::from_value::<RichTerm>(value.clone()).ok())
+                .collect();
+            Types(TypeF::Flat(mk_app!(
+                mk_term::op1(
+                    UnaryOp::StaticAccess(Ident::from("from_predicate")),
+                    mk_term::var("contract")
+                ),
+                mk_fun!(
+                    "value",
+                    mk_app!(
+                        mk_term::op1(
+                            UnaryOp::StaticAccess(Ident::from("elem")),
+                            mk_term::var("array")
+                        ),
+                        mk_term::var("value"),
+                        Term::Array(values, ArrayAttrs::default())
+                    )
+                )
+            )))
+        }
+    }
+}
+
+/// Return the term of a contract. Flat types are already terms, while other types are wrapped in
+/// a function applying them.
+fn contract_term(types: Types) -> RichTerm {
+    match types {
+        Types(TypeF::Flat(t)) => t,
+        types => mk_fun!(
+            "label",
+            "value",
+            Term::MetaValue(MetaValue {
+                contracts: vec![Contract {
+                    types,
+                    label: Label::default(),
+                }],
+                value: Some(mk_term::var("value")),
+                ..Default::default()
+            })
+        ),
+    }
+}
+
+fn description(schema: &Value) -> Option<String> {
+    schema
+        .get("description")
+        .and_then(Value::as_str)
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cache::InputFormat;
+    use crate::eval::cache::CBNCache;
+    use crate::program::Program;
+    use serde_json::{json, Value};
+    use std::io::Cursor;
+
+    fn import(schema: Value) -> String {
+        let mut p: Program<CBNCache> =
+            Program::new_from_source(Cursor::new(schema.to_string()), "<schema>").unwrap();
+        p.import_schema(InputFormat::Json).unwrap()
+    }
+
+    /// Check a value against the contract generated from a schema.
+    fn accepts(schema: &Value, value: &str) -> bool {
+        let source = format!("({}) | ({})", value, import(schema.clone()));
+        let mut p: Program<CBNCache> =
+            Program::new_from_source(Cursor::new(source), "<test>").unwrap();
+        p.eval_full().is_ok()
+    }
+
+    #[test]
+    fn records() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "description": "The name"},
+                "port": {"type": "integer", "default": 80},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "labels": {"type": "object", "additionalProperties": {"type": "string"}},
+                "inner": {
+                    "properties": {"enabled": {"type": "boolean"}},
+                    "additionalProperties": false,
+                },
+            },
+            "required": ["name"],
+        });
+
+        assert!(accepts(&schema, "{name = \"a\"}"));
+        assert!(accepts(
+            &schema,
+            "{name = \"a\", port = 1, tags = [\"b\"], labels = {c = \"d\"}, other = null}"
+        ));
+        assert!(accepts(&schema, "{name = \"a\", inner = {enabled = true}}"));
+        assert!(!accepts(&schema, "{port = 1}"));
+        assert!(!accepts(&schema, "{name = \"a\", port = 1.5}"));
+        assert!(!accepts(&schema, "{name = \"a\", labels = {c = 1}}"));
+        assert!(!accepts(&schema, "{name = \"a\", inner = {other = 1}}"));
+        assert!(import(schema).contains("doc \"The name\""));
+    }
+
+    #[test]
+    fn enums() {
+        let schema = json!({
+            "properties": {
+                "mode": {"enum": ["dev", "prod"]},
+                "level": {"enum": [1, 2]},
+                "nothing": {"type": "null"},
+            },
+        });
+
+        assert!(accepts(&schema, "{mode = `dev, level = 2, nothing = null}"));
+        assert!(!accepts(&schema, "{mode = `test}"));
+        assert!(!accepts(&schema, "{level = 3}"));
+        assert!(!accepts(&schema, "{nothing = 1}"));
+    }
+
+    #[test]
+    fn definitions() {
+        let schema = json!({
+            "properties": {"tree": {"$ref": "#/definitions/Tree"}},
+            "definitions": {
+                "Tree": {
+                    "properties": {
+                        "value": {"$ref": "#/$defs/Value"},
+                        "children": {"type": "array", "items": {"$ref": "#/definitions/Tree"}},
+                    },
+                },
+            },
+            "$defs": {"Value": {"type": "number"}},
+        });
+
+        assert!(accepts(
+            &schema,
+            "{tree = {value = 1, children = [{value = 2, children = []}]}}"
+        ));
+        assert!(!accepts(&schema, "{tree = {children = [{value = \"a\"}]}}"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let schema = json!({
+            "$schema": super::super::SCHEMA_VERSION,
+            "type": "object",
+            "properties": {
+                "name": {"type": "string", "description": "The name"},
+                "port": {"type": "number", "default": 80},
+                "mode": {"type": "string", "enum": ["dev", "prod"]},
+            },
+            "required": ["name"],
+            "additionalProperties": false,
+        });
+
+        let mut p: Program<CBNCache> =
+            Program::new_from_source(Cursor::new(import(schema.clone())), "<test>").unwrap();
+        assert_eq!(p.export_schema(None).unwrap(), schema);
+    }
+}
@@ -0,0 +1,502 @@
+//! Generation of JSON Schemas from Nickel contracts.
+//!
+//! A Nickel configuration is usually checked against a record contract, such as:
+//!
+//! ```nickel
+//! {
+//!   name | Str
+//!        | doc "The name of the service",
+//!   port | Num | default = 80,
+//!   tags | Array Str | optional,
+//! }
+//! ```
+//!
+//! This module translates such contracts to an equivalent [JSON Schema](https://json-schema.org/)
+//! (draft 7), so that the same configurations can be validated by tools which don't know about
+//! Nickel. Contracts are walked lazily using the virtual machine: metavalues are inspected without
+//! being unwrapped, and only the parts of the contract needed to compute the schema are evaluated.
+//!
+//! The translation handles record contracts, types (`Num`, `Str`, `Bool`, `Dyn`, arrays,
+//! dictionaries, enums and records) and field metadata (`doc`, `optional` and default values).
+//! Other contracts, typically custom predicates, can't be translated: they are replaced by a
+//! permissive schema, which accepts any value, together with a description saying so. Default
+//! values which can't be evaluated on their own, typically because they depend on other fields,
+//! are left out.
+//!
+//! The reverse direction, generating Nickel contracts from a JSON Schema, is implemented in
+//! [`import`].
+use crate::{
+    cache::ImportResolver,
+    error::EvalError,
+    eval::{cache::Cache, Closure, Environment, VirtualMachine},
+    term::{record::RecordData, MergePriority, MetaValue, RichTerm, Term},
+    types::{EnumRowsF, RecordRowsF, TypeF, Types},
+};
+
+use serde_json::{json, Map, Value};
+
+pub mod import;
+
+/// The version of JSON Schema of generated schemas.
+pub const SCHEMA_VERSION: &str = "http://json-schema.org/draft-07/schema#";
+
+/// The maximal nesting depth of generated schemas. Recursive contracts are cut at this depth and
+/// replaced by a permissive schema.
+pub const MAX_DEPTH: usize = 64;
+
+/// Generate a JSON Schema from a contract. The contract is typically a record contract, but may
+/// be any term with metadata.
+pub fn to_json_schema<R: ImportResolver, C: Cache>(
+    vm: &mut VirtualMachine<R, C>,
+    contract: RichTerm,
+    initial_env: &Environment,
+) -> Result<Value, EvalError> {
+    let mut builder = SchemaBuilder { vm, initial_env };
+    let mut schema = builder.field(Closure::atomic_closure(contract), 0)?.schema;
+    schema.insert(String::from("$schema"), json!(SCHEMA_VERSION));
+    Ok(Value::Object(schema))
+}
+
+/// The schema of a record field, and whether it is required.
+struct FieldSchema {
+    schema: Map<String, Value>,
+    required: bool,
+}
+
+struct SchemaBuilder<'vm, 'env, R: ImportResolver, C: Cache> {
+    vm: &'vm mut VirtualMachine<R, C>,
+    initial_env: &'env Environment,
+}
+
+impl<'vm, 'env, R: ImportResolver, C: Cache> SchemaBuilder<'vm, 'env, R, C> {
+    /// Compute the schema of a field of a record contract, from its metadata and its value.
+    fn field(&mut self, clos: Closure, depth: usize) -> Result<FieldSchema, EvalError> {
+        if depth > MAX_DEPTH {
+            return Ok(FieldSchema {
+                schema: permissive("recursive contract, cut at the maximal depth"),
+                required: false,
+            });
+        }
+
+        self.vm.reset();
+        let (rt, env) = self.vm.eval_meta_closure(clos, self.initial_env)?;
+
+        let MetaValue {
+            doc,
+            types,
+            contracts,
+            opt,
+            priority,
+            value,
+        } = match rt.as_ref() {
+            Term::MetaValue(meta) => meta.clone(),
+            _ => MetaValue {
+                value: Some(rt),
+                ..Default::default()
+            },
+        };
+
+        let required = value.is_none() && !opt;
+        let mut schemas = types
+            .iter()
+            .chain(contracts.iter())
+            .map(|ctr| self.types(&ctr.types, &env, depth + 1))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut default = None;
+
+        if let Some(value) = value {
+            // A value which can't be evaluated on its own, such as a default depending on another
+            // field without definition, is left out of the schema.
+            self.vm.reset();
+            let evaluated = self.vm.eval_meta_closure(
+                Closure {
+                    body: value,
+                    env: env.clone(),
+                },
+                self.initial_env,
+            );
+
+            if let Ok((value, value_env)) = evaluated {
+                match value.as_ref() {
+                    // A record defined in a contract is merged with the actual value, so it acts
+                    // as a nested record contract.
+                    Term::Record(record) if priority != MergePriority::Bottom => {
+                        schemas.push(self.record(record, &value_env, depth + 1)?)
+                    }
+                    _ => {
+                        self.vm.reset();
+                        let value = self.vm.eval_full_closure(
+                            Closure {
+                                body: value,
+                                env: value_env,
+                            },
+                            self.initial_env,
+                        );
+                        // A value which can't be serialized, such as a function, can't be a
+                        // default value of a JSON Schema.
+                        default = value
+                            .ok()
+                            .and_then(|value| serde_json::to_value(&value).ok());
+                    }
+                }
+            }
+        }
+
+        let mut schema = all_of(schemas);
+
+        if let Some(doc) = doc {
+            schema.insert(String::from("description"), Value::String(doc));
+        }
+        if let Some(default) = default {
+            schema.insert(String::from("default"), default);
+        }
+
+        Ok(FieldSchema { schema, required })
+    }
+
+    /// Compute the schema of an evaluated record contract.
+    fn record(
+        &mut self,
+        record: &RecordData,
+        env: &Environment,
+        depth: usize,
+    ) -> Result<Map<String, Value>, EvalError> {
+        // Sort the fields for a deterministic output
+        let mut fields: Vec<_> = record.fields.iter().collect();
+        fields.sort_by_key(|(id, _)| *id);
+
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+
+        for (id, rt) in fields {
+            let field = self.field(
+                Closure {
+                    body: rt.clone(),
+                    env: env.clone(),
+                },
+                depth,
+            )?;
+
+            if field.required {
+                required.push(json!(id.label()));
+            }
+            properties.insert(String::from(id.label()), Value::Object(field.schema));
+        }
+
+        let mut schema = Map::new();
+        schema.insert(String::from("type"), json!("object"));
+        schema.insert(String::from("properties"), Value::Object(properties));
+        if !required.is_empty() {
+            schema.insert(String::from("required"), Value::Array(required));
+        }
+        schema.insert(
+            String::from("additionalProperties"),
+            Value::Bool(record.attrs.open),
+        );
+
+        Ok(schema)
+    }
+
+    /// Compute the schema of a type or of a contract annotation. `env` is the environment of the
+    /// annotated term, used to evaluate user-defined contracts.
+    fn types(
+        &mut self,
+        types: &Types,
+        env: &Environment,
+        depth: usize,
+    ) -> Result<Map<String, Value>, EvalError> {
+        if depth > MAX_DEPTH {
+            return Ok(permissive("recursive contract, cut at the maximal depth"));
+        }
+
+        let schema = match &types.0 {
+            TypeF::Dyn => Map::new(),
+            TypeF::Num => type_schema("number"),
+            TypeF::Bool => type_schema("boolean"),
+            TypeF::Str => type_schema("string"),
+            TypeF::Array(elts) => {
+                let mut schema = type_schema("array");
+                let items = self.types(elts, env, depth + 1)?;
+                schema.insert(String::from("items"), Value::Object(items));
+                schema
+            }
+            TypeF::Dict(elts) => {
+                let mut schema = type_schema("object");
+                let items = self.types(elts, env, depth + 1)?;
+                schema.insert(String::from("additionalProperties"), Value::Object(items));
+                schema
+            }
+            TypeF::Enum(erows) => {
+                let mut tags = Vec::new();
+                let mut variants = Vec::new();
+                let mut erows = erows;
+
+                let open = loop {
+                    match &erows.0 {
+                        EnumRowsF::Empty => break false,
+                        EnumRowsF::Extend { row, tail } => {
+                            match &row.typ {
+                                Some(ty) => {
+                                    // A variant is serialized as an object with a single field,
+                                    // named after the tag.
+                                    let payload = self.types(ty, env, depth + 1)?;
+                                    let mut properties = Map::new();
+                                    properties.insert(
+                                        String::from(row.id.label()),
+                                        Value::Object(payload),
+                                    );
+
+                                    let mut schema = type_schema("object");
+                                    schema.insert(
+                                        String::from("properties"),
+                                        Value::Object(properties),
+                                    );
+                                    schema
+                                        .insert(String::from("required"), json!([row.id.label()]));
+                                    schema.insert(
+                                        String::from("additionalProperties"),
+                                        Value::Bool(false),
+                                    );
+                                    variants.push(Value::Object(schema));
+                                }
+                                None => tags.push(json!(row.id.label())),
+                            }
+                            erows = tail;
+                        }
+                        EnumRowsF::TailVar(_) => break true,
+                    }
+                };
+
+                // An open enum can be any string, or any value if it has variants
+                if open {
+                    return Ok(if variants.is_empty() {
+                        type_schema("string")
+                    } else {
+                        Map::new()
+                    });
+                }
+
+                let mut tags_schema = type_schema("string");
+                if variants.is_empty() {
+                    tags_schema.insert(String::from("enum"), Value::Array(tags));
+                    return Ok(tags_schema);
+                }
+
+                if !tags.is_empty() {
+                    tags_schema.insert(String::from("enum"), Value::Array(tags));
+                    variants.push(Value::Object(tags_schema));
+                }
+
+                let mut schema = Map::new();
+                schema.insert(String::from("oneOf"), Value::Array(variants));
+                schema
+            }
+            TypeF::Record(rrows) => {
+                let mut properties = Map::new();
+                let mut required = Vec::new();
+                let mut rrows = rrows;
+
+                let open = loop {
+                    match &rrows.0 {
+                        RecordRowsF::Empty => break false,
+                        RecordRowsF::Extend { row, tail } => {
+                            let field = self.types(&row.types, env, depth + 1)?;
+                            properties.insert(String::from(row.id.label()), Value::Object(field));
+                            required.push(json!(row.id.label()));
+                            rrows = tail;
+                        }
+                        RecordRowsF::TailVar(_) | RecordRowsF::TailDyn => break true,
+                    }
+                };
+
+                let mut schema = type_schema("object");
+                schema.insert(String::from("properties"), Value::Object(properties));
+                if !required.is_empty() {
+                    schema.insert(String::from("required"), Value::Array(required));
+                }
+                schema.insert(String::from("additionalProperties"), Value::Bool(open));
+                schema
+            }
+            TypeF::Flat(rt) => {
+                self.vm.reset();
+                let (contract, contract_env) = self.vm.eval_closure(
+                    Closure {
+                        body: rt.clone(),
+                        env: env.clone(),
+                    },
+                    self.initial_env,
+                )?;
+
+                match contract.as_ref() {
+                    Term::Record(record) => self.record(record, &contract_env, depth)?,
+                    _ => permissive(&format!("contract `{}` can't be translated", types)),
+                }
+            }
+            TypeF::Sym
+            | TypeF::Arrow(..)
+            | TypeF::Var(_)
+            | TypeF::Forall { .. }
+            | TypeF::Wildcard(_) => permissive(&format!("type `{}` can't be translated", types)),
+        };
+
+        Ok(schema)
+    }
+}
+
+/// Build the schema `{"type": name}`.
+fn type_schema(name: &str) -> Map<String, Value> {
+    let mut schema = Map::new();
+    schema.insert(String::from("type"), json!(name));
+    schema
+}
+
+/// Build a schema accepting any value, with a description.
+fn permissive(description: &str) -> Map<String, Value> {
+    let mut schema = Map::new();
+    schema.insert(String::from("description"), json!(description));
+    schema
+}
+
+/// Combine several schemas, which must all be satisfied.
+fn all_of(mut schemas: Vec<Map<String, Value>>) -> Map<String, Value> {
+    match schemas.len() {
+        0 => Map::new(),
+        1 => schemas.pop().unwrap(),
+        _ => {
+            let mut schema = Map::new();
+            schema.insert(
+                String::from("allOf"),
+                Value::Array(schemas.into_iter().map(Value::Object).collect()),
+            );
+            schema
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::eval::cache::CBNCache;
+    use crate::program::Program;
+    use std::io::Cursor;
+
+    fn schema_of(s: &str) -> Value {
+        let mut p: Program<CBNCache> = Program::new_from_source(Cursor::new(s), "<test>").unwrap();
+        p.export_schema(None).unwrap()
+    }
+
+    #[test]
+    fn types() {
+        assert_eq!(
+            schema_of("{foo | Num, bar | Array Str, baz | {_: Bool}, qux | [| `a, `b |]}"),
+            json!({
+                "$schema": SCHEMA_VERSION,
+                "type": "object",
+                "properties": {
+                    "bar": {"type": "array", "items": {"type": "string"}},
+                    "baz": {"type": "object", "additionalProperties": {"type": "boolean"}},
+                    "foo": {"type": "number"},
+                    "qux": {"type": "string", "enum": ["a", "b"]},
+                },
+                "required": ["bar", "baz", "foo", "qux"],
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn metadata() {
+        assert_eq!(
+            schema_of(
+                "{foo | Num | doc \"The foo\" | default = 1, bar | Str | optional, baz | {a: Num; Dyn} | default = {a = 1}}"
+            ),
+            json!({
+                "$schema": SCHEMA_VERSION,
+                "type": "object",
+                "properties": {
+                    "bar": {"type": "string"},
+                    "baz": {
+                        "type": "object",
+                        "properties": {"a": {"type": "number"}},
+                        "required": ["a"],
+                        "additionalProperties": true,
+                        "default": {"a": 1},
+                    },
+                    "foo": {"type": "number", "description": "The foo", "default": 1},
+                },
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn unevaluable_defaults() {
+        assert_eq!(
+            schema_of("{host | Str, url | Str | default = \"http://%{host}\"}"),
+            json!({
+                "$schema": SCHEMA_VERSION,
+                "type": "object",
+                "properties": {
+                    "host": {"type": "string"},
+                    "url": {"type": "string"},
+                },
+                "required": ["host"],
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn record_contracts() {
+        assert_eq!(
+            schema_of(
+                "let Server = {host | Str, port | Num | default = 80} in \
+                 {server | Server, nested = {name | Str, ..}}"
+            ),
+            json!({
+                "$schema": SCHEMA_VERSION,
+                "type": "object",
+                "properties": {
+                    "nested": {
+                        "type": "object",
+                        "properties": {"name": {"type": "string"}},
+                        "required": ["name"],
+                        "additionalProperties": true,
+                    },
+                    "server": {
+                        "type": "object",
+                        "properties": {
+                            "host": {"type": "string"},
+                            "port": {"type": "number", "default": 80},
+                        },
+                        "required": ["host"],
+                        "additionalProperties": false,
+                    },
+                },
+                "required": ["server"],
+                "additionalProperties": false,
+            })
+        );
+    }
+
+    #[test]
+    fn flat_contracts() {
+        assert_eq!(
+            schema_of("let Port = fun label value => value in {port | Port | Num}"),
+            json!({
+                "$schema": SCHEMA_VERSION,
+                "type": "object",
+                "properties": {
+                    "port": {
+                        "allOf": [
+                            {"description": "contract `Port` can't be translated"},
+                            {"type": "number"},
+                        ]
+                    },
+                },
+                "required": ["port"],
+                "additionalProperties": false,
+            })
+        );
+    }
+}
@@ -6,19 +6,29 @@ use crate::{
         cache::{CBNCache, Cache},
         is_empty_optional,
     },
+    identifier::Ident,
     term::{
         array::{Array, ArrayAttrs},
+        number::Number,
         record::RecordData,
-        MetaValue, RichTerm, Term,
+        Contract, MetaValue, RichTerm, Term, UnaryOp,
     },
+    types::TypeF,
 };
 
+use indexmap::IndexMap;
+
 use serde::{
     de::{Deserialize, Deserializer},
     ser::{Error, Serialize, SerializeMap, SerializeSeq, Serializer},
 };
 
-use std::{collections::HashMap, fmt, io, rc::Rc, str::FromStr};
+use std::{
+    fmt, io,
+    path::{Component, Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
 
 /// Available export formats.
 // If you add or remove variants, remember to update the CLI docs in `src/bin/nickel.rs'
@@ -27,7 +37,18 @@ pub enum ExportFormat {
     Raw,
     Json,
     Yaml,
+    /// A stream of YAML documents separated by `---`, one for each element of a top-level array.
+    YamlStream,
     Toml,
+    /// A Nix expression made of attribute sets, lists and literals.
+    Nix,
+    /// A `.env` file, with one `NAME=value` line for each field of a record of scalars.
+    Dotenv,
+    /// A Java `.properties` file. Nested records are flattened using dotted keys.
+    Properties,
+    /// An XML document, whose root element is the top-level record. See [`to_xml`] for the
+    /// representation of values.
+    Xml,
 }
 
 impl std::default::Default for ExportFormat {
@@ -42,7 +63,12 @@ impl fmt::Display for ExportFormat {
             Self::Raw => write!(f, "raw"),
             Self::Json => write!(f, "json"),
             Self::Yaml => write!(f, "yaml"),
+            Self::YamlStream => write!(f, "yaml-stream"),
             Self::Toml => write!(f, "toml"),
+            Self::Nix => write!(f, "nix"),
+            Self::Dotenv => write!(f, "dotenv"),
+            Self::Properties => write!(f, "properties"),
+            Self::Xml => write!(f, "xml"),
         }
     }
 }
@@ -64,29 +90,89 @@ impl FromStr for ExportFormat {
             "raw" => Ok(ExportFormat::Raw),
             "json" => Ok(ExportFormat::Json),
             "yaml" => Ok(ExportFormat::Yaml),
+            // `yamlstream` is the lowercased form of the enum tag `YamlStream`, as used by
+            // multi-file exports
+            "yaml-stream" | "yamlstream" => Ok(ExportFormat::YamlStream),
             "toml" => Ok(ExportFormat::Toml),
+            "nix" => Ok(ExportFormat::Nix),
+            "dotenv" | "env" => Ok(ExportFormat::Dotenv),
+            "properties" => Ok(ExportFormat::Properties),
+            "xml" => Ok(ExportFormat::Xml),
             _ => Err(ParseFormatError(String::from(s))),
         }
     }
 }
 
-/// Implicitly convert float to integers when possible to avoid trailing zeros. Note this this
-/// only work if the float is in range of either `i64` or `f64`. It seems there's no easy general
-/// solution (working for both YAML, TOML, and JSON) to choose the way floating point values are
-/// formatted.
-pub fn serialize_num<S>(n: &f64, serializer: S) -> Result<S::Ok, S::Error>
+/// Order of the fields of records in the output.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum FieldOrder {
+    /// The order in which fields are defined. The fields of a merge come in the order of the left
+    /// operand, followed by the fields which are only defined in the right operand.
+    Definition,
+    /// Alphabetical order of field names.
+    Alphabetical,
+}
+
+impl std::default::Default for FieldOrder {
+    fn default() -> Self {
+        FieldOrder::Alphabetical
+    }
+}
+
+impl fmt::Display for FieldOrder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Definition => write!(f, "definition"),
+            Self::Alphabetical => write!(f, "alphabetical"),
+        }
+    }
+}
+
+impl FromStr for FieldOrder {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_ref() {
+            "definition" => Ok(FieldOrder::Definition),
+            "alphabetical" => Ok(FieldOrder::Alphabetical),
+            _ => Err(format!("unsupported field order {}", s)),
+        }
+    }
+}
+
+/// Serialize a number. Integers are serialized exactly, using the smallest integer type they fit
+/// in, as some formats don't support 128 bits integers.
+///
+/// Floating-point values are implicitly converted to integers when possible to avoid trailing
+/// zeros. Note this this only work if the float is in range of either `i64` or `f64`. It seems
+/// there's no easy general solution (working for both YAML, TOML, and JSON) to choose the way
+/// floating point values are formatted.
+pub fn serialize_num<S>(n: &Number, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    if n.fract() == 0.0 {
-        if *n < 0.0 && *n >= (i64::MIN as f64) && *n <= (i64::MAX as f64) {
-            return (*n as i64).serialize(serializer);
-        } else if *n >= 0.0 && *n <= (u64::MAX as f64) {
-            return (*n as u64).serialize(serializer);
+    match *n {
+        Number::Int(n) => {
+            if let Ok(n) = i64::try_from(n) {
+                n.serialize(serializer)
+            } else if let Ok(n) = u64::try_from(n) {
+                n.serialize(serializer)
+            } else {
+                n.serialize(serializer)
+            }
         }
-    }
+        Number::Float(f) => {
+            if f.fract() == 0.0 {
+                if f < 0.0 && f >= (i64::MIN as f64) && f <= (i64::MAX as f64) {
+                    return (f as i64).serialize(serializer);
+                } else if f >= 0.0 && f <= (u64::MAX as f64) {
+                    return (f as u64).serialize(serializer);
+                }
+            }
 
-    n.serialize(serializer)
+            f.serialize(serializer)
+        }
+    }
 }
 
 /// Serializer for metavalues.
@@ -102,20 +188,32 @@ where
     }
 }
 
-/// Serializer for a record. Serialize fields in alphabetical order to get a deterministic output
-/// (by default, `HashMap`'s randomness implies a randomized order of fields in the output).
+/// Iterate over the fields of a record which are exported, in the order of the record. Optional
+/// fields without a definition are filtered out. All variables should have been substituted at
+/// this point, so we pass an empty environment.
+fn exported_fields(record: &RecordData) -> impl Iterator<Item = (&Ident, &RichTerm)> {
+    record
+        .fields
+        .iter()
+        .filter(|(_, t)| !is_empty_optional(&CBNCache::new(), t, &eval::Environment::new()))
+}
+
+/// Same as [`exported_fields`], but in the given order.
+fn fields_in_order(record: &RecordData, order: FieldOrder) -> Vec<(&Ident, &RichTerm)> {
+    let mut fields: Vec<_> = exported_fields(record).collect();
+    if order == FieldOrder::Alphabetical {
+        fields.sort_by_key(|(id, _)| *id);
+    }
+    fields
+}
+
+/// Serializer for a record. Serialize fields in alphabetical order to get a deterministic output,
+/// which doesn't depend on the order of definition of the fields.
 pub fn serialize_record<S>(record: &RecordData, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let mut entries: Vec<(_, _)> = record
-        .fields
-        .iter()
-        // Filtering out optional fields without a definition. All variable should have been
-        // substituted at this point, so we pass an empty environment.
-        .filter(|(_, t)| !is_empty_optional(&CBNCache::new(), t, &eval::Environment::new()))
-        .collect();
-    entries.sort_by_key(|(k, _)| *k);
+    let entries = fields_in_order(record, FieldOrder::Alphabetical);
 
     let mut map_ser = serializer.serialize_map(Some(entries.len()))?;
     for (id, t) in entries.iter() {
@@ -125,12 +223,27 @@ where
     map_ser.end()
 }
 
+/// Serializer for an enum variant. A variant is serialized as a map with a single entry, whose key
+/// is the tag and whose value is the payload, that is `` `Some 5`` is serialized as `{"Some": 5}`.
+pub fn serialize_enum_variant<S>(
+    tag: &Ident,
+    arg: &RichTerm,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    let mut map_ser = serializer.serialize_map(Some(1))?;
+    map_ser.serialize_entry(&tag.to_string(), arg)?;
+    map_ser.end()
+}
+
 /// Deserialize for a record. Required to set the record attributes to default.
 pub fn deserialize_record<'de, D>(deserializer: D) -> Result<RecordData, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let fields = HashMap::deserialize(deserializer)?;
+    let fields = IndexMap::deserialize(deserializer)?;
     Ok(RecordData::with_fields(fields))
 }
 
@@ -180,8 +293,53 @@ impl<'de> Deserialize<'de> for RichTerm {
     }
 }
 
+/// Serialize a term with the fields of records in the given order. The [`Serialize`]
+/// implementation of [`RichTerm`] always uses the alphabetical order.
+struct InOrder<'a>(&'a RichTerm, FieldOrder);
+
+impl Serialize for InOrder<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let InOrder(rt, order) = *self;
+
+        match rt.as_ref() {
+            Term::Record(record) => {
+                let entries = fields_in_order(record, order);
+
+                let mut map_ser = serializer.serialize_map(Some(entries.len()))?;
+                for (id, t) in entries {
+                    map_ser.serialize_entry(&id.to_string(), &InOrder(t, order))?
+                }
+                map_ser.end()
+            }
+            Term::Array(terms, _) => {
+                let mut seq_ser = serializer.serialize_seq(Some(terms.len()))?;
+                for t in terms.iter() {
+                    seq_ser.serialize_element(&InOrder(t, order))?
+                }
+                seq_ser.end()
+            }
+            Term::MetaValue(MetaValue { value: Some(t), .. }) => {
+                InOrder(t, order).serialize(serializer)
+            }
+            Term::EnumVariant(tag, arg) => {
+                let mut map_ser = serializer.serialize_map(Some(1))?;
+                map_ser.serialize_entry(&tag.to_string(), &InOrder(arg, order))?;
+                map_ser.end()
+            }
+            _ => rt.serialize(serializer),
+        }
+    }
+}
+
 /// Check that a term is serializable. Serializable terms are booleans, numbers, strings, enum,
-/// arrays of serializable terms or records of serializable terms.
+/// enum variants carrying a serializable term, arrays of serializable terms or records of
+/// serializable terms. A YAML stream must be an array,
+/// each element of which is checked as a YAML document. Key-value formats (dotenv and properties)
+/// require a record of scalars, or of nested records of scalars for properties. XML requires a
+/// record which can be represented as described in [`to_xml`].
 /// TODO: We should have a NoCache impl of Cache or adapt the signature of [is_empty_optional()]
 pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), SerializationError> {
     use crate::term;
@@ -193,12 +351,29 @@ pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), SerializationE
         } else {
             Err(SerializationError::NotAString(t.clone()))
         }
+    } else if format == ExportFormat::YamlStream {
+        if let Term::Array(array, _) = t.term.as_ref() {
+            array
+                .iter()
+                .try_for_each(|t| validate(ExportFormat::Yaml, t))
+        } else {
+            Err(SerializationError::NotAnArray(t.clone()))
+        }
+    } else if format == ExportFormat::Dotenv || format == ExportFormat::Properties {
+        key_values(format, t, FieldOrder::Definition).map(|_| ())
+    } else if format == ExportFormat::Xml {
+        to_xml(t, XML_ROOT, FieldOrder::Definition).map(|_| ())
     } else {
         match t.term.as_ref() {
             // TOML doesn't support null values
-            Null if format == ExportFormat::Json || format == ExportFormat::Yaml => Ok(()),
+            Null if format != ExportFormat::Toml => Ok(()),
             Null => Err(SerializationError::UnsupportedNull(format, t.clone())),
+            // TOML integers are 64 bits signed integers
+            Num(Number::Int(n)) if format == ExportFormat::Toml && i64::try_from(*n).is_err() => {
+                Err(SerializationError::UnsupportedNumber(format, t.clone()))
+            }
             Bool(_) | Num(_) | Str(_) | Enum(_) => Ok(()),
+            EnumVariant(_, arg) => validate(format, arg),
             Record(record) => {
                 record
                     .fields
@@ -222,24 +397,634 @@ pub fn validate(format: ExportFormat, t: &RichTerm) -> Result<(), SerializationE
     }
 }
 
+/// Check if a contract is the `string.Datetime` contract of the standard library. Contracts may be
+/// closurized during evaluation, so we look at the original contract stored in the label.
+fn is_datetime_contract(ctr: &Contract) -> bool {
+    match &ctr.label.types.0 {
+        TypeF::Flat(rt) => matches!(
+            rt.as_ref(),
+            Term::Op1(UnaryOp::StaticAccess(id), t)
+                if id.label() == "Datetime"
+                    && matches!(t.as_ref(), Term::Var(module) if module.label() == "string")
+        ),
+        _ => false,
+    }
+}
+
+/// Convert a term to a TOML value. Contrary to the other formats, which go through the
+/// [`Serialize`] implementation of [`RichTerm`], the TOML value is built explicitly, so that
+/// strings annotated with the `string.Datetime` contract are exported as native TOML datetimes.
+fn to_toml(
+    rt: &RichTerm,
+    is_datetime: bool,
+    order: FieldOrder,
+) -> Result<toml::Value, SerializationError> {
+    match rt.as_ref() {
+        Term::Str(s) if is_datetime => s
+            .parse::<toml::value::Datetime>()
+            .map(toml::Value::Datetime)
+            .map_err(|err| {
+                SerializationError::Other(format!("invalid datetime \"{}\": {}", s, err))
+            }),
+        Term::MetaValue(meta) => match meta.value {
+            Some(ref t) => to_toml(
+                t,
+                is_datetime || meta.contracts.iter().any(is_datetime_contract),
+                order,
+            ),
+            None => Err(SerializationError::Other(String::from("empty metavalue"))),
+        },
+        Term::Num(Number::Int(n)) => i64::try_from(*n)
+            .map(toml::Value::Integer)
+            .map_err(|_| SerializationError::UnsupportedNumber(ExportFormat::Toml, rt.clone())),
+        Term::Record(record) => fields_in_order(record, order)
+            .into_iter()
+            .map(|(id, t)| Ok((id.to_string(), to_toml(t, false, order)?)))
+            .collect::<Result<_, _>>()
+            .map(toml::Value::Table),
+        Term::EnumVariant(tag, arg) => Ok(toml::Value::Table(
+            [(tag.to_string(), to_toml(arg, false, order)?)]
+                .into_iter()
+                .collect(),
+        )),
+        Term::Array(array, _) => array
+            .iter()
+            .map(|t| to_toml(t, false, order))
+            .collect::<Result<_, _>>()
+            .map(toml::Value::Array),
+        _ => toml::Value::try_from(rt).map_err(|err| SerializationError::Other(err.to_string())),
+    }
+}
+
+/// Keywords of the Nix language, which can't be used as unquoted attribute names.
+const NIX_KEYWORDS: &[&str] = &[
+    "assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with",
+];
+
+/// Write a string literal in Nix syntax. Besides the usual escape sequences, `${` must be escaped
+/// as `\${`, as it would otherwise start an interpolation.
+fn write_nix_str(out: &mut String, s: &str) {
+    out.push('"');
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '$' if chars.peek() == Some(&'{') => out.push_str("\\$"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+/// Write an attribute name in Nix syntax, quoting it if it's not a valid identifier.
+fn write_nix_attr_name(out: &mut String, name: &str) {
+    let is_ident = name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-'))
+        && !NIX_KEYWORDS.contains(&name);
+
+    if is_ident {
+        out.push_str(name);
+    } else {
+        write_nix_str(out, name);
+    }
+}
+
+/// Write a term as a Nix expression. Records are written as attribute sets, arrays as lists, enum
+/// tags as strings and enum variants as attribute sets with a single attribute. `indent` is the
+/// indentation of the line where the term starts.
+fn write_nix(
+    out: &mut String,
+    rt: &RichTerm,
+    indent: usize,
+    order: FieldOrder,
+) -> Result<(), SerializationError> {
+    const INDENT: &str = "  ";
+
+    match rt.as_ref() {
+        Term::Null => out.push_str("null"),
+        Term::Bool(b) => out.push_str(&b.to_string()),
+        // Nix integers are signed 64 bits integers. Negative numbers are parenthesized, as
+        // negation isn't allowed for a list element.
+        Term::Num(n) => {
+            let literal = match *n {
+                Number::Int(n) => i64::try_from(n).map(|n| n.to_string()).map_err(|_| {
+                    SerializationError::UnsupportedNumber(ExportFormat::Nix, rt.clone())
+                })?,
+                Number::Float(f) if !f.is_finite() => {
+                    return Err(SerializationError::Other(format!(
+                        "Nix doesn't support the number {}",
+                        f
+                    )))
+                }
+                Number::Float(f)
+                    if f.fract() == 0.0 && f >= (i64::MIN as f64) && f <= (i64::MAX as f64) =>
+                {
+                    (f as i64).to_string()
+                }
+                // A float literal must have a decimal point, which `Display` omits for integral
+                // values.
+                Number::Float(f) if f.fract() == 0.0 => format!("{}.0", f),
+                Number::Float(f) => f.to_string(),
+            };
+
+            if literal.starts_with('-') {
+                out.push_str(&format!("({})", literal));
+            } else {
+                out.push_str(&literal);
+            }
+        }
+        Term::Str(s) => write_nix_str(out, s),
+        Term::Enum(id) => write_nix_str(out, id.label()),
+        Term::EnumVariant(tag, arg) => {
+            out.push_str("{\n");
+            out.push_str(&INDENT.repeat(indent + 1));
+            write_nix_attr_name(out, tag.label());
+            out.push_str(" = ");
+            write_nix(out, arg, indent + 1, order)?;
+            out.push_str(";\n");
+            out.push_str(&INDENT.repeat(indent));
+            out.push('}');
+        }
+        Term::MetaValue(MetaValue {
+            value: Some(ref t), ..
+        }) => write_nix(out, t, indent, order)?,
+        Term::Record(record) => {
+            let fields = fields_in_order(record, order);
+
+            if fields.is_empty() {
+                out.push_str("{ }");
+            } else {
+                out.push_str("{\n");
+                for (id, t) in fields {
+                    out.push_str(&INDENT.repeat(indent + 1));
+                    write_nix_attr_name(out, id.label());
+                    out.push_str(" = ");
+                    write_nix(out, t, indent + 1, order)?;
+                    out.push_str(";\n");
+                }
+                out.push_str(&INDENT.repeat(indent));
+                out.push('}');
+            }
+        }
+        Term::Array(array, _) if array.is_empty() => out.push_str("[ ]"),
+        Term::Array(array, _) => {
+            out.push_str("[\n");
+            for t in array.iter() {
+                out.push_str(&INDENT.repeat(indent + 1));
+                write_nix(out, t, indent + 1, order)?;
+                out.push('\n');
+            }
+            out.push_str(&INDENT.repeat(indent));
+            out.push(']');
+        }
+        _ => return Err(SerializationError::NonSerializable(rt.clone())),
+    }
+
+    Ok(())
+}
+
+/// Convert a term to a Nix expression.
+fn to_nix(rt: &RichTerm, order: FieldOrder) -> Result<String, SerializationError> {
+    let mut out = String::new();
+    write_nix(&mut out, rt, 0, order)?;
+    Ok(out)
+}
+
+/// Flatten a record of scalars to the key-value pairs of a dotenv or properties file. The fields
+/// of nested records are flattened using dotted keys for properties, while dotenv doesn't support
+/// nested records. Values are booleans, numbers, strings or enum tags. Flattening fails if two
+/// fields give the same key, as in `{"a.b" = 1, a.b = 2}`.
+fn key_values(
+    format: ExportFormat,
+    rt: &RichTerm,
+    order: FieldOrder,
+) -> Result<IndexMap<String, RichTerm>, SerializationError> {
+    fn flatten(
+        format: ExportFormat,
+        prefix: &str,
+        record: &RecordData,
+        order: FieldOrder,
+        acc: &mut IndexMap<String, RichTerm>,
+    ) -> Result<(), SerializationError> {
+        for (id, t) in fields_in_order(record, order) {
+            let key = format!("{}{}", prefix, id);
+            let t = unwrap_meta(t);
+
+            match t.as_ref() {
+                Term::Bool(_) | Term::Num(_) | Term::Str(_) | Term::Enum(_) => {
+                    if (format == ExportFormat::Dotenv && !is_dotenv_name(&key))
+                        || acc.contains_key(&key)
+                    {
+                        return Err(SerializationError::InvalidKey(format, key, t.clone()));
+                    }
+
+                    acc.insert(key, t.clone());
+                }
+                Term::Null => return Err(SerializationError::UnsupportedNull(format, t.clone())),
+                Term::Record(record) if format == ExportFormat::Properties => {
+                    flatten(format, &format!("{}.", key), record, order, acc)?
+                }
+                _ => return Err(SerializationError::NotAScalar(format, t.clone())),
+            }
+        }
+
+        Ok(())
+    }
+
+    let rt = unwrap_meta(rt);
+    match rt.as_ref() {
+        Term::Record(record) => {
+            let mut acc = IndexMap::new();
+            flatten(format, "", record, order, &mut acc)?;
+            Ok(acc)
+        }
+        _ => Err(SerializationError::NotARecord(format, rt.clone())),
+    }
+}
+
+/// Return the inner value of a term wrapped in metavalues.
+fn unwrap_meta(rt: &RichTerm) -> &RichTerm {
+    match rt.as_ref() {
+        Term::MetaValue(MetaValue {
+            value: Some(ref t), ..
+        }) => unwrap_meta(t),
+        _ => rt,
+    }
+}
+
+/// Return the text of a scalar, without any quoting or escaping.
+fn scalar_text(rt: &RichTerm) -> String {
+    match rt.as_ref() {
+        Term::Bool(b) => b.to_string(),
+        Term::Num(n) => n.to_string(),
+        Term::Str(s) => s.clone(),
+        Term::Enum(id) => id.to_string(),
+        _ => unreachable!("key_values only returns scalars"),
+    }
+}
+
+/// Check that a name is a valid environment variable name, as accepted by POSIX shells.
+fn is_dotenv_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Convert a record of scalars to a dotenv file. Values made only of safe characters are written
+/// as is, and other values are double-quoted. Inside double quotes, `$` and backquotes are escaped
+/// so that they are not subject to expansion.
+fn to_dotenv(rt: &RichTerm, order: FieldOrder) -> Result<String, SerializationError> {
+    let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-.,/:@+%".contains(c);
+    let mut out = String::new();
+
+    for (key, value) in key_values(ExportFormat::Dotenv, rt, order)? {
+        let text = scalar_text(&value);
+        out.push_str(&key);
+        out.push('=');
+
+        if !text.is_empty() && text.chars().all(is_safe) {
+            out.push_str(&text);
+        } else {
+            out.push('"');
+            for c in text.chars() {
+                match c {
+                    '"' | '\\' | '$' | '`' => {
+                        out.push('\\');
+                        out.push(c);
+                    }
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+/// Write a key or a value of a properties file, following the escaping rules of
+/// `java.util.Properties::store`. All the spaces of a key are escaped, but only the leading space
+/// of a value. Non-ASCII characters are written as `\uXXXX` escapes, so that the output can be
+/// read using the default ISO-8859-1 encoding.
+fn write_properties_escaped(out: &mut String, s: &str, is_key: bool) {
+    for (i, c) in s.chars().enumerate() {
+        match c {
+            ' ' if i == 0 || is_key => out.push_str("\\ "),
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\x0c' => out.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            c if (' '..='~').contains(&c) => out.push(c),
+            c => {
+                let mut buf = [0u16; 2];
+                for unit in c.encode_utf16(&mut buf) {
+                    out.push_str(&format!("\\u{:04X}", unit));
+                }
+            }
+        }
+    }
+}
+
+/// Convert a record of scalars to a properties file.
+fn to_properties(rt: &RichTerm, order: FieldOrder) -> Result<String, SerializationError> {
+    let mut out = String::new();
+
+    for (key, value) in key_values(ExportFormat::Properties, rt, order)? {
+        write_properties_escaped(&mut out, &key, true);
+        out.push('=');
+        write_properties_escaped(&mut out, &scalar_text(&value), false);
+        out.push('\n');
+    }
+
+    Ok(out)
+}
+
+/// Default name of the root element of an XML export.
+pub const XML_ROOT: &str = "root";
+
+/// Check that a name is a valid XML element or attribute name. Colons are accepted, so that
+/// namespaced names such as `xsi:schemaLocation` can be used.
+pub fn is_xml_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == ':')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '-' | '.'))
+}
+
+/// Write the text of a scalar as XML character data, or as the value of an attribute if
+/// `in_attribute` is true.
+fn write_xml_text(
+    out: &mut String,
+    rt: &RichTerm,
+    in_attribute: bool,
+) -> Result<(), SerializationError> {
+    let text = match rt.as_ref() {
+        Term::Bool(b) => b.to_string(),
+        Term::Num(n) => n.to_string(),
+        Term::Str(s) => s.clone(),
+        Term::Enum(id) => id.to_string(),
+        _ => {
+            return Err(SerializationError::InvalidXml(
+                String::from("attributes and text must be booleans, numbers, strings or enum tags"),
+                rt.clone(),
+            ))
+        }
+    };
+
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' if in_attribute => out.push_str("&quot;"),
+            '\n' if in_attribute => out.push_str("&#10;"),
+            '\t' if in_attribute => out.push_str("&#9;"),
+            '\r' => out.push_str("&#13;"),
+            '\t' | '\n' => out.push(c),
+            c if c.is_control() => {
+                return Err(SerializationError::InvalidXml(
+                    format!("the control character {:?} can't be written in XML", c),
+                    rt.clone(),
+                ))
+            }
+            c => out.push(c),
+        }
+    }
+
+    Ok(())
+}
+
+/// Write an element called `name` whose content is given by a term, which has already been
+/// unwrapped from its metavalues.
+fn write_xml_element(
+    out: &mut String,
+    name: &str,
+    rt: &RichTerm,
+    indent: usize,
+    order: FieldOrder,
+) -> Result<(), SerializationError> {
+    const INDENT: &str = "  ";
+
+    out.push_str(&INDENT.repeat(indent));
+    out.push('<');
+    out.push_str(name);
+
+    match rt.as_ref() {
+        Term::Null => out.push_str("/>"),
+        Term::Bool(_) | Term::Num(_) | Term::Str(_) | Term::Enum(_) => {
+            out.push('>');
+            write_xml_text(out, rt, false)?;
+            out.push_str(&format!("</{}>", name));
+        }
+        Term::Record(record) => {
+            let fields = fields_in_order(record, order)
+                .into_iter()
+                .map(|(id, t)| (id.label(), unwrap_meta(t)));
+
+            let mut text = None;
+            let mut children = Vec::new();
+
+            for (field, t) in fields {
+                if let Some(attr) = field.strip_prefix('@') {
+                    if !is_xml_name(attr) {
+                        return Err(SerializationError::InvalidXml(
+                            format!("`{}` is not a valid XML attribute name", attr),
+                            t.clone(),
+                        ));
+                    }
+
+                    out.push_str(&format!(" {}=\"", attr));
+                    write_xml_text(out, t, true)?;
+                    out.push('"');
+                } else if field == "$text" {
+                    text = Some(t);
+                } else if is_xml_name(field) {
+                    children.push((field, t));
+                } else {
+                    return Err(SerializationError::InvalidXml(
+                        format!("`{}` is not a valid XML element name", field),
+                        t.clone(),
+                    ));
+                }
+            }
+
+            match (text, children.is_empty()) {
+                (Some(t), false) => {
+                    return Err(SerializationError::InvalidXml(
+                        String::from("the text of an element can't be mixed with child elements"),
+                        t.clone(),
+                    ))
+                }
+                (Some(t), true) => {
+                    out.push('>');
+                    write_xml_text(out, t, false)?;
+                    out.push_str(&format!("</{}>", name));
+                }
+                (None, true) => out.push_str("/>"),
+                (None, false) => {
+                    out.push_str(">\n");
+                    for (child, t) in children {
+                        write_xml_children(out, child, t, indent + 1, order)?;
+                    }
+                    out.push_str(&INDENT.repeat(indent));
+                    out.push_str(&format!("</{}>", name));
+                }
+            }
+        }
+        Term::EnumVariant(tag, arg) => {
+            if !is_xml_name(tag.label()) {
+                return Err(SerializationError::InvalidXml(
+                    format!("`{}` is not a valid XML element name", tag),
+                    rt.clone(),
+                ));
+            }
+
+            out.push_str(">\n");
+            write_xml_children(out, tag.label(), unwrap_meta(arg), indent + 1, order)?;
+            out.push_str(&INDENT.repeat(indent));
+            out.push_str(&format!("</{}>", name));
+        }
+        _ => return Err(SerializationError::NonSerializable(rt.clone())),
+    }
+
+    out.push('\n');
+    Ok(())
+}
+
+/// Write the child elements called `name` given by a term: one element for each value of an
+/// array, or a single element otherwise.
+fn write_xml_children(
+    out: &mut String,
+    name: &str,
+    rt: &RichTerm,
+    indent: usize,
+    order: FieldOrder,
+) -> Result<(), SerializationError> {
+    match rt.as_ref() {
+        Term::Array(array, _) => {
+            for elt in array.iter().map(unwrap_meta) {
+                if let Term::Array(..) = elt.as_ref() {
+                    return Err(SerializationError::InvalidXml(
+                        String::from("nested arrays can't be represented in XML"),
+                        elt.clone(),
+                    ));
+                }
+
+                write_xml_element(out, name, elt, indent, order)?;
+            }
+
+            Ok(())
+        }
+        _ => write_xml_element(out, name, rt, indent, order),
+    }
+}
+
+/// Convert a record to an XML document whose root element is called `root`. The content of an
+/// element is given by a Nickel value as follows:
+///
+/// - a record gives the attributes and the children of the element. A field whose name starts
+///   with `@`, as in `"@version"`, is written as an attribute, and the field `"$text"` as the text
+///   of the element. Other fields are written as child elements, in the given order.
+/// - an array of values gives an element for each value, all of them with the name of the field.
+///   Arrays can't be nested.
+/// - a boolean, a number, a string or an enum tag gives the text of the element.
+/// - an enum variant, as in `` `Http {port = 80}``, gives a child element named after its tag,
+///   whose content is given by its argument.
+/// - `null` gives an empty element.
+pub fn to_xml(rt: &RichTerm, root: &str, order: FieldOrder) -> Result<String, SerializationError> {
+    let rt = unwrap_meta(rt);
+
+    if !matches!(rt.as_ref(), Term::Record(_)) {
+        return Err(SerializationError::NotARecord(
+            ExportFormat::Xml,
+            rt.clone(),
+        ));
+    }
+
+    if !is_xml_name(root) {
+        return Err(SerializationError::InvalidXml(
+            format!("`{}` is not a valid XML element name", root),
+            rt.clone(),
+        ));
+    }
+
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    write_xml_element(&mut out, root, rt, 0, order)?;
+    Ok(out)
+}
+
+/// Serialize a term to a writer. The fields of records are written in alphabetical order.
 pub fn to_writer<W>(
+    writer: W,
+    format: ExportFormat,
+    rt: &RichTerm,
+) -> Result<(), SerializationError>
+where
+    W: io::Write,
+{
+    to_writer_with_order(writer, format, rt, FieldOrder::Alphabetical)
+}
+
+/// Serialize a term to a writer, with the fields of records in the given order.
+pub fn to_writer_with_order<W>(
     mut writer: W,
     format: ExportFormat,
     rt: &RichTerm,
+    order: FieldOrder,
 ) -> Result<(), SerializationError>
 where
     W: io::Write,
 {
     match format {
-        ExportFormat::Json => serde_json::to_writer_pretty(writer, &rt)
+        ExportFormat::Json => serde_json::to_writer_pretty(writer, &InOrder(rt, order))
             .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Yaml => serde_yaml::to_writer(writer, &rt)
+        ExportFormat::Yaml => serde_yaml::to_writer(writer, &InOrder(rt, order))
             .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Toml => toml::Value::try_from(rt)
-            .map_err(|err| SerializationError::Other(err.to_string()))
-            .and_then(|v| {
-                write!(writer, "{}", v).map_err(|err| SerializationError::Other(err.to_string()))
+        // Each document is started by `---`, so they can simply be written one after the other
+        ExportFormat::YamlStream => match rt.as_ref() {
+            Term::Array(array, _) => array.iter().try_for_each(|t| {
+                serde_yaml::to_writer(&mut writer, &InOrder(t, order))
+                    .map_err(|err| SerializationError::Other(err.to_string()))
             }),
+            _ => Err(SerializationError::NotAnArray(rt.clone())),
+        },
+        ExportFormat::Toml => to_toml(rt, false, order).and_then(|v| {
+            write!(writer, "{}", v).map_err(|err| SerializationError::Other(err.to_string()))
+        }),
+        ExportFormat::Nix => to_nix(rt, order).and_then(|s| {
+            writer
+                .write_all(s.as_bytes())
+                .map_err(|err| SerializationError::Other(err.to_string()))
+        }),
+        ExportFormat::Dotenv => to_dotenv(rt, order).and_then(|s| {
+            writer
+                .write_all(s.as_bytes())
+                .map_err(|err| SerializationError::Other(err.to_string()))
+        }),
+        ExportFormat::Properties => to_properties(rt, order).and_then(|s| {
+            writer
+                .write_all(s.as_bytes())
+                .map_err(|err| SerializationError::Other(err.to_string()))
+        }),
+        ExportFormat::Xml => to_xml(rt, XML_ROOT, order).and_then(|s| {
+            writer
+                .write_all(s.as_bytes())
+                .map_err(|err| SerializationError::Other(err.to_string()))
+        }),
         ExportFormat::Raw => match rt.as_ref() {
             Term::Str(s) => writer
                 .write_all(s.as_bytes())
@@ -252,24 +1037,130 @@ where
     }
 }
 
-pub fn to_string(format: ExportFormat, rt: &RichTerm) -> Result<String, SerializationError> {
-    match format {
-        ExportFormat::Json => serde_json::to_string_pretty(&rt)
-            .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Yaml => {
-            serde_yaml::to_string(&rt).map_err(|err| SerializationError::Other(err.to_string()))
+/// An output file of a multi-file export.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExportFile {
+    /// The path of the file, relative to the output directory.
+    pub path: PathBuf,
+    pub format: ExportFormat,
+    pub content: RichTerm,
+}
+
+/// Extract the output files of a multi-file export from an evaluated term. The term must be a
+/// record mapping relative paths to records `{format = `Yaml, content = ...}`. Paths can't go up
+/// the directory tree. The content of each file is validated against its format, so that nothing
+/// needs to be written if one of them is invalid. Files are returned in the order of their path.
+pub fn multi_export_files(rt: &RichTerm) -> Result<Vec<ExportFile>, SerializationError> {
+    fn invalid(msg: impl Into<String>, rt: &RichTerm) -> SerializationError {
+        SerializationError::InvalidMultiExport(msg.into(), rt.clone())
+    }
+
+    let files = match unwrap_meta(rt).as_ref() {
+        Term::Record(record) => &record.fields,
+        _ => return Err(invalid("expected a record", rt)),
+    };
+
+    let mut result = files
+        .iter()
+        .map(|(id, file)| {
+            let path = PathBuf::from(id.label());
+            if !path
+                .components()
+                .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+            {
+                return Err(invalid(
+                    format!(
+                        "`{}` is not a relative path inside the output directory",
+                        id
+                    ),
+                    file,
+                ));
+            }
+
+            let file = unwrap_meta(file);
+            let fields = match file.as_ref() {
+                Term::Record(record) => &record.fields,
+                _ => return Err(invalid(format!("expected a record for `{}`", id), file)),
+            };
+
+            let format = match fields
+                .get(&Ident::from("format"))
+                .map(|t| unwrap_meta(t).as_ref())
+            {
+                Some(Term::Enum(format)) => format
+                    .label()
+                    .parse()
+                    .map_err(|err| invalid(format!("{} for `{}`", err, id), file))?,
+                _ => {
+                    return Err(invalid(
+                        format!("missing or invalid field `format` for `{}`", id),
+                        file,
+                    ))
+                }
+            };
+
+            let content = fields
+                .get(&Ident::from("content"))
+                .ok_or_else(|| invalid(format!("missing field `content` for `{}`", id), file))?;
+            validate(format, content)?;
+
+            Ok(ExportFile {
+                path,
+                format,
+                content: content.clone(),
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    result.sort_by(|f1, f2| f1.path.cmp(&f2.path));
+    Ok(result)
+}
+
+/// Write the output files of a multi-file export in the given directory, creating intermediate
+/// directories as needed. The files should have been validated by [`multi_export_files`]. The
+/// fields of records are written in the given order.
+pub fn write_multi_export(
+    dir: &Path,
+    files: &[ExportFile],
+    order: FieldOrder,
+) -> Result<(), crate::error::Error> {
+    use crate::error::IOError;
+
+    for file in files {
+        let path = dir.join(&file.path);
+        let io_err = |err: io::Error| {
+            IOError(format!(
+                "when writing output file `{}`: {}",
+                path.display(),
+                err
+            ))
+        };
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(io_err)?;
         }
-        ExportFormat::Toml => toml::Value::try_from(rt)
-            .map(|v| format!("{}", v))
-            .map_err(|err| SerializationError::Other(err.to_string())),
-        ExportFormat::Raw => match rt.as_ref() {
-            Term::Str(s) => Ok(s.clone()),
-            t => Err(SerializationError::Other(format!(
-                "raw export requires a `Str`, got {}",
-                t.type_of().unwrap()
-            ))),
-        },
+        let writer = std::fs::File::create(&path).map_err(io_err)?;
+        to_writer_with_order(writer, file.format, &file.content, order)?;
     }
+
+    Ok(())
+}
+
+/// Serialize a term to a string. The fields of records are written in alphabetical order.
+pub fn to_string(format: ExportFormat, rt: &RichTerm) -> Result<String, SerializationError> {
+    to_string_with_order(format, rt, FieldOrder::Alphabetical)
+}
+
+/// Serialize a term to a string, with the fields of records in the given order.
+pub fn to_string_with_order(
+    format: ExportFormat,
+    rt: &RichTerm,
+    order: FieldOrder,
+) -> Result<String, SerializationError> {
+    let mut buffer = Vec::new();
+    to_writer_with_order(&mut buffer, format, rt, order)?;
+    // unwrap(): all the serializers produce valid UTF-8
+    Ok(String::from_utf8(buffer).unwrap())
 }
 
 #[cfg(test)]
@@ -300,9 +1191,9 @@ mod tests {
     macro_rules! assert_json_eq {
         ( $term:expr, $result:expr ) => {
             assert_eq!(
-                serde_json::to_string(&mk_program($term).and_then(|mut p| p.eval_full()).unwrap())
+                serde_json::to_value(&mk_program($term).and_then(|mut p| p.eval_full()).unwrap())
                     .unwrap(),
-                serde_json::to_string(&$result).unwrap()
+                serde_json::to_value(&$result).unwrap()
             )
         };
     }
@@ -434,6 +1325,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn enum_variants() {
+        assert_json_eq!("`Some (2 + 3)", json!({"Some": 5}));
+        assert_json_eq!(
+            "{backend = `Http {port = 80, host = \"a\"}, fallback = `None}",
+            json!({"backend": {"Http": {"port": 80, "host": "a"}}, "fallback": "None"})
+        );
+        assert_json_eq!("[`Ok (`Some [1])]", json!([{"Ok": {"Some": [1]}}]));
+
+        let export = |format, src| {
+            let rt = mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+            to_string(format, &rt)
+        };
+
+        assert_eq!(
+            export(ExportFormat::Yaml, "{backend = `Http {port = 80}}"),
+            Ok(String::from("---\nbackend:\n  Http:\n    port: 80\n"))
+        );
+        assert!(export(ExportFormat::Json, "{backend = `Http (fun x => x)}").is_err());
+    }
+
     #[test]
     fn prevalidation() {
         assert_pass_validation!(ExportFormat::Json, "{a = 1, b = {c = fun x => x}}", false);
@@ -446,6 +1358,374 @@ mod tests {
         assert_pass_validation!(ExportFormat::Toml, "{foo = null}", false);
     }
 
+    #[test]
+    fn multi_export() {
+        let multi_files =
+            |s: &str| multi_export_files(&mk_program(s).and_then(|mut p| p.eval_full()).unwrap());
+
+        let files = multi_files(
+            "{\"b/c.yaml\" = {format = `Yaml, content.foo = 1}, a = {format = `Raw, content = \"a\"}}",
+        )
+        .unwrap();
+        let summary: Vec<_> = files
+            .iter()
+            .map(|file| (file.path.to_str().unwrap(), file.format))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("a", ExportFormat::Raw), ("b/c.yaml", ExportFormat::Yaml)]
+        );
+
+        assert_matches::assert_matches!(
+            multi_files("{a = {format = `Toml, content.foo = null}}"),
+            Err(SerializationError::UnsupportedNull(..))
+        );
+        assert_matches::assert_matches!(
+            multi_files("{a = {format = `Raw}}"),
+            Err(SerializationError::InvalidMultiExport(..))
+        );
+        assert_matches::assert_matches!(
+            multi_files("{a = {format = \"json\", content = 1}}"),
+            Err(SerializationError::InvalidMultiExport(..))
+        );
+        assert_matches::assert_matches!(
+            multi_files("{\"../a\" = {format = `Json, content = 1}}"),
+            Err(SerializationError::InvalidMultiExport(..))
+        );
+        assert_matches::assert_matches!(
+            multi_files("{\"/a\" = {format = `Json, content = 1}}"),
+            Err(SerializationError::InvalidMultiExport(..))
+        );
+    }
+
+    #[test]
+    fn field_order() {
+        let export = |format, order| {
+            let rt = mk_program("{z = 1, a = {d = 2, c = 3}} & {a.b = 4, m = 5}")
+                .and_then(|mut p| p.eval_full())
+                .unwrap();
+            to_string_with_order(format, &rt, order).unwrap()
+        };
+
+        assert_eq!(
+            export(ExportFormat::Yaml, FieldOrder::Definition),
+            "---\nz: 1\na:\n  d: 2\n  c: 3\n  b: 4\nm: 5\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Yaml, FieldOrder::Alphabetical),
+            "---\na:\n  b: 4\n  c: 3\n  d: 2\nm: 5\nz: 1\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Toml, FieldOrder::Definition),
+            "z = 1\nm = 5\n\n[a]\nd = 2\nc = 3\nb = 4\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Toml, FieldOrder::Alphabetical),
+            "m = 5\nz = 1\n\n[a]\nb = 4\nc = 3\nd = 2\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Properties, FieldOrder::Alphabetical),
+            "a.b=4\na.c=3\na.d=2\nm=5\nz=1\n"
+        );
+    }
+
+    #[test]
+    fn yaml_stream() {
+        let export = |src| {
+            let rt = mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+            validate(ExportFormat::YamlStream, &rt)?;
+            to_string(ExportFormat::YamlStream, &rt)
+        };
+
+        assert_eq!(
+            export("[{kind = \"Service\"}, {kind = \"Deployment\", replicas = 1 + 1}]"),
+            Ok(String::from(
+                "---\nkind: Service\n---\nkind: Deployment\nreplicas: 2\n"
+            ))
+        );
+        assert_eq!(export("[]"), Ok(String::new()));
+        assert_matches::assert_matches!(
+            export("{kind = \"Service\"}"),
+            Err(SerializationError::NotAnArray(..))
+        );
+        assert_matches::assert_matches!(
+            export("[{kind = fun x => x}]"),
+            Err(SerializationError::NonSerializable(..))
+        );
+        assert_matches::assert_matches!(
+            to_string(
+                ExportFormat::YamlStream,
+                &Term::Var(Ident::from("x")).into()
+            ),
+            Err(SerializationError::NotAnArray(..))
+        );
+    }
+
+    #[test]
+    fn big_integers() {
+        let export = |format, src| {
+            let rt = mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+            to_string(format, &rt).unwrap()
+        };
+
+        assert_eq!(
+            export(ExportFormat::Json, "{n = 9007199254740992 + 1}"),
+            "{\n  \"n\": 9007199254740993\n}"
+        );
+        assert_eq!(
+            export(ExportFormat::Yaml, "{n = -9007199254740993}"),
+            "---\nn: -9007199254740993\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Toml, "{n = 9223372036854775807}"),
+            "n = 9223372036854775807\n"
+        );
+        assert_eq!(
+            export(ExportFormat::Json, "{n = 18446744073709551615}"),
+            "{\n  \"n\": 18446744073709551615\n}"
+        );
+        assert_eq!(
+            export(ExportFormat::Json, "{n = 2.5}"),
+            "{\n  \"n\": 2.5\n}"
+        );
+
+        let json = "{\"m\":-9007199254740993,\"n\":9007199254740993,\"u\":18446744073709551615}";
+        let rt: RichTerm = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&rt).unwrap(), json);
+
+        let yaml = "---\nn: 9007199254740993\n";
+        let rt: RichTerm = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(serde_yaml::to_string(&rt).unwrap(), yaml);
+
+        let toml = "n = 9007199254740993\n";
+        let rt: RichTerm = toml::from_str(toml).unwrap();
+        assert_eq!(toml::to_string(&rt).unwrap(), toml);
+
+        let rt = mk_program("{n = 18446744073709551615}")
+            .and_then(|mut p| p.eval_full())
+            .unwrap();
+        assert_matches::assert_matches!(
+            to_string(ExportFormat::Toml, &rt),
+            Err(SerializationError::UnsupportedNumber(ExportFormat::Toml, _))
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Toml, &rt),
+            Err(SerializationError::UnsupportedNumber(ExportFormat::Toml, _))
+        );
+    }
+
+    #[test]
+    fn datetimes() {
+        let export = |format, src| {
+            let rt = mk_program(src)
+                .and_then(|mut p| p.eval_full_annotated(None))
+                .unwrap();
+            to_string_with_order(format, &rt, FieldOrder::Definition)
+        };
+
+        assert_eq!(
+            export(
+                ExportFormat::Toml,
+                "{released | string.Datetime = \"1979-05-27T07:32:00Z\", name = \"1979-05-27\"}"
+            ),
+            Ok(String::from(
+                "released = 1979-05-27T07:32:00Z\nname = \"1979-05-27\"\n"
+            ))
+        );
+        assert_eq!(
+            export(
+                ExportFormat::Toml,
+                "{windows = [\"02:00:00\" | string.Datetime, \"03:30:00\"]}"
+            ),
+            Ok(String::from("windows = [02:00:00, \"03:30:00\"]\n"))
+        );
+        assert_eq!(
+            export(
+                ExportFormat::Json,
+                "{released | string.Datetime = \"1979-05-27\"}"
+            ),
+            Ok(String::from("{\n  \"released\": \"1979-05-27\"\n}"))
+        );
+        assert!(mk_program("{released | string.Datetime = \"yesterday\"}")
+            .and_then(|mut p| p.eval_full())
+            .is_err());
+    }
+
+    #[test]
+    fn nix() {
+        let export = |src| {
+            let rt = mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+            to_string_with_order(ExportFormat::Nix, &rt, FieldOrder::Definition)
+        };
+
+        assert_eq!(
+            export(
+                "{name = \"svc\", port = 80, ratio = 0.5, offset = -1, enabled = true, \
+                 tag = `prod, extra = null, hosts = [\"a\", -2, [], {}]}"
+            ),
+            Ok(String::from(
+                "{\n  name = \"svc\";\n  port = 80;\n  ratio = 0.5;\n  offset = (-1);\n  \
+                 enabled = true;\n  tag = \"prod\";\n  extra = null;\n  hosts = [\n    \
+                 \"a\"\n    (-2)\n    [ ]\n    { }\n  ];\n}"
+            ))
+        );
+        assert_eq!(
+            export(
+                "{script = m%\"echo \"${HOME}\" $PATH\\n\"%, \"a b\" = 1, \"with\" = 2, \
+                 \"x'-_1\" = 3, \"1st\" = 4}"
+            ),
+            Ok(String::from(
+                "{\n  script = \"echo \\\"\\${HOME}\\\" $PATH\\\\n\";\n  \"a b\" = 1;\n  \
+                 \"with\" = 2;\n  x'-_1 = 3;\n  \"1st\" = 4;\n}"
+            ))
+        );
+        assert_eq!(
+            export("{backend = `Http {port = 80}}"),
+            Ok(String::from(
+                "{\n  backend = {\n    Http = {\n      port = 80;\n    };\n  };\n}"
+            ))
+        );
+        assert!(export("{n = 9223372036854775808}").is_err());
+    }
+
+    #[test]
+    fn key_value_formats() {
+        let eval = |src| mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+
+        let rt = eval(
+            "{PORT = 8080, DEBUG = false, MODE = `prod, URL = \"http://host:80/a\", \
+             MOTD = \"Hi \\\"$USER\\\"\\nbye\", EMPTY = \"\"}",
+        );
+        assert_eq!(
+            to_string_with_order(ExportFormat::Dotenv, &rt, FieldOrder::Definition),
+            Ok(String::from(
+                "PORT=8080\nDEBUG=false\nMODE=prod\nURL=http://host:80/a\n\
+                 MOTD=\"Hi \\\"\\$USER\\\"\\nbye\"\nEMPTY=\"\"\n"
+            ))
+        );
+
+        let rt = eval(
+            "{server = {port = 8080, host = \"localhost\"}, \"key with spaces\" = \" a=b\", \
+             greeting = \"héllo #1\"}",
+        );
+        assert_eq!(
+            to_string_with_order(ExportFormat::Properties, &rt, FieldOrder::Definition),
+            Ok(String::from(
+                "server.port=8080\nserver.host=localhost\nkey\\ with\\ spaces=\\ a\\=b\n\
+                 greeting=h\\u00E9llo \\#1\n"
+            ))
+        );
+
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Dotenv, &eval("{server = {port = 8080}}")),
+            Err(SerializationError::NotAScalar(ExportFormat::Dotenv, _))
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Properties, &eval("{hosts = [\"a\"]}")),
+            Err(SerializationError::NotAScalar(ExportFormat::Properties, _))
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Properties, &eval("{f = fun x => x}")),
+            Err(SerializationError::NotAScalar(ExportFormat::Properties, _))
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Dotenv, &eval("[1]")),
+            Err(SerializationError::NotARecord(ExportFormat::Dotenv, _))
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Dotenv, &eval("{\"not-a-name\" = 1}")),
+            Err(SerializationError::InvalidKey(ExportFormat::Dotenv, key, _)) if key == "not-a-name"
+        );
+        assert_matches::assert_matches!(
+            validate(ExportFormat::Properties, &eval("{\"a.b\" = 1, a = {b = 2}}")),
+            Err(SerializationError::InvalidKey(ExportFormat::Properties, key, _)) if key == "a.b"
+        );
+    }
+
+    #[test]
+    fn xml() {
+        let eval = |src| mk_program(src).and_then(|mut p| p.eval_full()).unwrap();
+
+        let rt = eval(
+            "{\"@xmlns\" = \"http://maven.apache.org/POM/4.0.0\", modelVersion = \"4.0.0\", \
+             name = \"a <b> & c\", \
+             dependencies.dependency = [ \
+               {groupId = \"junit\", scope = `test, classifier = null}, \
+               {groupId = \"log4j\", version = {\"@pinned\" = true, \"$text\" = 2}}, \
+             ], \
+             modules = [], \
+             properties = {}}",
+        );
+        assert_eq!(
+            to_xml(&rt, "project", FieldOrder::Definition),
+            Ok(String::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <project xmlns=\"http://maven.apache.org/POM/4.0.0\">\n  \
+                   <modelVersion>4.0.0</modelVersion>\n  \
+                   <name>a &lt;b&gt; &amp; c</name>\n  \
+                   <dependencies>\n    \
+                     <dependency>\n      \
+                       <groupId>junit</groupId>\n      \
+                       <scope>test</scope>\n      \
+                       <classifier/>\n    \
+                     </dependency>\n    \
+                     <dependency>\n      \
+                       <groupId>log4j</groupId>\n      \
+                       <version pinned=\"true\">2</version>\n    \
+                     </dependency>\n  \
+                   </dependencies>\n  \
+                   <properties/>\n\
+                 </project>\n"
+            ))
+        );
+        assert_eq!(
+            to_string(ExportFormat::Xml, &eval("{\"@a\" = \"say \\\"hi\\\"\"}")),
+            Ok(String::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<root a=\"say &quot;hi&quot;\"/>\n"
+            ))
+        );
+
+        assert_eq!(
+            to_string(ExportFormat::Xml, &eval("{x = `Http {port = 80}}")),
+            Ok(String::from(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+                 <root>\n  \
+                   <x>\n    \
+                     <Http>\n      \
+                       <port>80</port>\n    \
+                     </Http>\n  \
+                   </x>\n\
+                 </root>\n"
+            ))
+        );
+
+        let invalid = |src| validate(ExportFormat::Xml, &eval(src));
+        assert_matches::assert_matches!(
+            invalid("{a = [[1]]}"),
+            Err(SerializationError::InvalidXml(..))
+        );
+        assert_matches::assert_matches!(
+            invalid("{\"@a\" = {b = 1}}"),
+            Err(SerializationError::InvalidXml(..))
+        );
+        assert_matches::assert_matches!(
+            invalid("{\"1a\" = 1}"),
+            Err(SerializationError::InvalidXml(..))
+        );
+        assert_matches::assert_matches!(
+            invalid("{a = {\"$text\" = 1, b = 2}}"),
+            Err(SerializationError::InvalidXml(..))
+        );
+        assert_matches::assert_matches!(
+            invalid("[1]"),
+            Err(SerializationError::NotARecord(ExportFormat::Xml, _))
+        );
+        assert_matches::assert_matches!(
+            to_xml(&eval("{a = 1}"), "1bad", FieldOrder::Definition),
+            Err(SerializationError::InvalidXml(..))
+        );
+    }
+
     #[test]
     fn involution() {
         assert_involutory!("{val = 1 + 1}");
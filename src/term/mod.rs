@@ -18,12 +18,15 @@
 //! definitions of contracts, record and metadata all together.
 
 pub mod array;
+pub mod number;
 pub mod record;
 
 use array::{Array, ArrayAttrs};
+use number::Number;
 
 use crate::{
-    destruct::Destruct,
+    cache::InputFormat,
+    destruct::{Destruct, MatchBranch},
     error::ParseError,
     eval::EvalMode,
     identifier::Ident,
@@ -34,6 +37,7 @@ use crate::{
 };
 
 use codespan::FileId;
+use indexmap::IndexMap;
 
 use serde::{Deserialize, Serialize};
 
@@ -60,9 +64,9 @@ pub enum Term {
     Null,
     /// A boolean value.
     Bool(bool),
-    /// A floating-point value.
+    /// A number, either an exact integer or a floating-point value.
     #[serde(serialize_with = "crate::serialize::serialize_num")]
-    Num(f64),
+    Num(Number),
     /// A literal string.
     Str(String),
     /// A string containing interpolated expressions, represented as a list of either literals or
@@ -100,6 +104,10 @@ pub enum Term {
 
     /// An enum variant.
     Enum(Ident),
+    /// An enum variant carrying a value, as in `` `Some 5``.
+    #[serde(serialize_with = "crate::serialize::serialize_enum_variant")]
+    #[serde(skip_deserializing)]
+    EnumVariant(Ident, RichTerm),
 
     /// A record, mapping identifiers to terms.
     #[serde(serialize_with = "crate::serialize::serialize_record")]
@@ -121,6 +129,10 @@ pub enum Term {
         cases: HashMap<Ident, RichTerm>,
         default: Option<RichTerm>,
     },
+    /// A match construct whose branches are arbitrary patterns, possibly with guards, which are
+    /// tried in order. Like destructuring let-bindings, it is desugared before evaluation.
+    #[serde(skip)]
+    MatchPattern(Vec<MatchBranch>),
 
     /// An array.
     #[serde(serialize_with = "crate::serialize::serialize_array")]
@@ -168,9 +180,10 @@ pub enum Term {
     #[serde(skip_deserializing)]
     MetaValue(MetaValue),
 
-    /// An unresolved import.
+    /// An unresolved import, with the format of the imported file if it was given explicitly
+    /// (`import "file" as `Text`). Otherwise, the format is guessed from the extension.
     #[serde(skip)]
-    Import(OsString),
+    Import(OsString, Option<InputFormat>),
     /// A resolved import (which has already been loaded and parsed).
     #[serde(skip)]
     ResolvedImport(FileId),
@@ -359,6 +372,25 @@ impl fmt::Display for MergePriority {
     }
 }
 
+impl std::str::FromStr for MergePriority {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ERR_MSG: &str = "possible values are 'default', 'force' or a number.";
+
+        match s {
+            "default" => Ok(MergePriority::Bottom),
+            "force" => Ok(MergePriority::Top),
+            _ => s
+                .parse::<f64>()
+                .ok()
+                .and_then(|f| NumeralPriority::try_from(f).ok())
+                .map(MergePriority::Numeral)
+                .ok_or(ERR_MSG),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Contract {
     pub types: Types,
@@ -495,6 +527,14 @@ impl Term {
                     func(default)
                 }
             }
+            MatchPattern(ref mut branches) => {
+                branches.iter_mut().for_each(|branch| {
+                    if let Some(ref mut guard) = branch.guard {
+                        func(guard);
+                    }
+                    func(&mut branch.body);
+                });
+            }
             Record(ref mut r) => {
                 r.fields.iter_mut().for_each(|(_, t)| func(t));
             }
@@ -505,10 +545,11 @@ impl Term {
                     func(t2);
                 });
             }
-            Bool(_) | Num(_) | Str(_) | Lbl(_) | Var(_) | SealingKey(_) | Enum(_) | Import(_)
+            Bool(_) | Num(_) | Str(_) | Lbl(_) | Var(_) | SealingKey(_) | Enum(_) | Import(..)
             | ResolvedImport(_) => {}
             Fun(_, ref mut t)
             | FunPattern(_, _, ref mut t)
+            | EnumVariant(_, ref mut t)
             | Op1(_, ref mut t)
             | Sealed(_, ref mut t, _) => {
                 func(t);
@@ -554,9 +595,9 @@ impl Term {
             Term::Num(_) => Some("Num"),
             Term::Str(_) => Some("Str"),
             Term::Fun(_, _) | Term::FunPattern(_, _, _) => Some("Fun"),
-            Term::Match { .. } => Some("MatchExpression"),
+            Term::Match { .. } | Term::MatchPattern(_) => Some("MatchExpression"),
             Term::Lbl(_) => Some("Label"),
-            Term::Enum(_) => Some("Enum"),
+            Term::Enum(_) | Term::EnumVariant(..) => Some("Enum"),
             Term::Record(..) | Term::RecRecord(..) => Some("Record"),
             Term::Array(..) => Some("Array"),
             Term::SealingKey(_) => Some("SealingKey"),
@@ -569,7 +610,7 @@ impl Term {
             | Term::Op1(_, _)
             | Term::Op2(_, _, _)
             | Term::OpN(..)
-            | Term::Import(_)
+            | Term::Import(..)
             | Term::ResolvedImport(_)
             | Term::StrChunks(_)
             | Term::ParseError(_) => None,
@@ -598,7 +639,7 @@ impl Term {
                 format!("\"{}\"", chunks_str.join(""))
             }
             Term::Fun(_, _) | Term::FunPattern(_, _, _) => String::from("<func>"),
-            Term::Match { .. } => String::from("<func (match expr)>"),
+            Term::Match { .. } | Term::MatchPattern(_) => String::from("<func (match expr)>"),
             Term::Lbl(_) => String::from("<label>"),
             Term::Enum(id) => {
                 let re = regex::Regex::new("_?[a-zA-Z][_a-zA-Z0-9]*").unwrap();
@@ -609,6 +650,11 @@ impl Term {
                     format!("`\"{}\"", s)
                 }
             }
+            Term::EnumVariant(id, arg) => format!(
+                "{} {}",
+                Term::Enum(*id).shallow_repr(),
+                arg.as_ref().shallow_repr()
+            ),
             Term::Record(..) | Term::RecRecord(..) => String::from("{ ... }"),
             Term::Array(..) => String::from("[ ... ]"),
             Term::SealingKey(_) => String::from("<sealing key>"),
@@ -644,7 +690,7 @@ impl Term {
             | Term::Op1(_, _)
             | Term::Op2(_, _, _)
             | Term::OpN(..)
-            | Term::Import(_)
+            | Term::Import(..)
             | Term::ResolvedImport(_) => String::from("<unevaluated>"),
         }
     }
@@ -689,12 +735,14 @@ impl Term {
             | Term::Match {..}
             | Term::Lbl(_)
             | Term::Enum(_)
+            | Term::EnumVariant(..)
             | Term::Record(..)
             | Term::Array(..)
             | Term::SealingKey(_) => true,
             Term::Let(..)
             | Term::LetPattern(..)
             | Term::FunPattern(..)
+            | Term::MatchPattern(_)
             | Term::App(_, _)
             | Term::Var(_)
             | Term::Op1(_, _)
@@ -702,7 +750,7 @@ impl Term {
             | Term::OpN(..)
             | Term::Sealed(..)
             | Term::MetaValue(_)
-            | Term::Import(_)
+            | Term::Import(..)
             | Term::ResolvedImport(_)
             | Term::StrChunks(_)
             | Term::RecRecord(..)
@@ -730,19 +778,21 @@ impl Term {
             | Term::SealingKey(_) => true,
             Term::Let(..)
             | Term::LetPattern(..)
+            | Term::EnumVariant(..)
             | Term::Record(..)
             | Term::Array(..)
             | Term::Fun(_, _)
             | Term::FunPattern(_, _, _)
             | Term::App(_, _)
             | Term::Match { .. }
+            | Term::MatchPattern(_)
             | Term::Var(_)
             | Term::Op1(_, _)
             | Term::Op2(_, _, _)
             | Term::OpN(..)
             | Term::Sealed(..)
             | Term::MetaValue(_)
-            | Term::Import(_)
+            | Term::Import(..)
             | Term::ResolvedImport(_)
             | Term::StrChunks(_)
             | Term::RecRecord(..)
@@ -776,8 +826,12 @@ impl Term {
             | Term::Op1(UnaryOp::BoolAnd(), _)
             | Term::Op1(UnaryOp::BoolOr(), _)
             => true,
+            // A field access binds tighter than an application, as in `array.map f xs`.
+            Term::Op1(UnaryOp::StaticAccess(_), t) => t.as_ref().is_atom(),
             Term::Let(..)
             | Term::Match { .. }
+            | Term::MatchPattern(_)
+            | Term::EnumVariant(..)
             | Term::LetPattern(..)
             | Term::Fun(..)
             | Term::FunPattern(..)
@@ -994,6 +1048,12 @@ pub enum UnaryOp {
     NumFromStr(),
     /// Transform a string to an enum.
     EnumFromStr(),
+    /// Test if an enum is a variant carrying a value, as opposed to a bare tag.
+    EnumIsVariant(),
+    /// Return the tag of an enum variant, as a bare tag. A bare tag is returned as it is.
+    EnumGetTag(),
+    /// Return the value carried by an enum variant.
+    EnumUnwrapVariant(),
     /// Test if a regex matches a string.
     /// Like [`UnaryOp::StrFind`], this is a unary operator because we would like a way to share the
     /// same "compiled regex" for many matching calls. This is done by returning functions
@@ -1365,6 +1425,13 @@ impl RichTerm {
                     pos,
                 )
             },
+            Term::EnumVariant(tag, t) => {
+                let t = t.traverse(f, state, order)?;
+                RichTerm::new(
+                    Term::EnumVariant(tag, t),
+                    pos,
+                )
+            },
             Term::Let(id, t1, t2, attrs) => {
                 let t1 = t1.traverse(f, state, order)?;
                 let t2 = t2.traverse(f, state, order)?;
@@ -1405,6 +1472,23 @@ impl RichTerm {
                     pos,
                 )
             },
+            Term::MatchPattern(branches) => {
+                let branches: Result<Vec<MatchBranch>, E> = branches
+                    .into_iter()
+                    .map(|MatchBranch { pattern, guard, body }| {
+                        Ok(MatchBranch {
+                            pattern,
+                            guard: guard.map(|t| t.traverse(f, state, order)).transpose()?,
+                            body: body.traverse(f, state, order)?,
+                        })
+                    })
+                    .collect();
+
+                RichTerm::new(
+                    Term::MatchPattern(branches?),
+                    pos,
+                )
+            },
             Term::Op1(op, t) => {
                 let t = t.traverse(f, state, order)?;
                 RichTerm::new(
@@ -1439,7 +1523,7 @@ impl RichTerm {
             Term::Record(record) => {
                 // The annotation on `fields_res` uses Result's corresponding trait to convert from
                 // Iterator<Result> to a Result<Iterator>
-                let fields_res: Result<HashMap<Ident, RichTerm>, E> = record.fields
+                let fields_res: Result<IndexMap<Ident, RichTerm>, E> = record.fields
                     .into_iter()
                     // For the conversion to work, note that we need a Result<(Ident,RichTerm), E>
                     .map(|(id, t)| t.traverse(f, state, order).map(|t_ok| (id, t_ok)))
@@ -1449,7 +1533,7 @@ impl RichTerm {
             Term::RecRecord(record, dyn_fields, deps) => {
                 // The annotation on `map_res` uses Result's corresponding trait to convert from
                 // Iterator<Result> to a Result<Iterator>
-                let static_fields_res: Result<HashMap<Ident, RichTerm>, E> = record.fields
+                let static_fields_res: Result<IndexMap<Ident, RichTerm>, E> = record.fields
                     .into_iter()
                     // For the conversion to work, note that we need a Result<(Ident,RichTerm), E>
                     .map(|(id, t)| Ok((id, t.traverse(f, state, order)?)))
@@ -1608,23 +1692,23 @@ impl std::fmt::Display for RichTerm {
 ///
 /// It is used somehow as a match statement, going from
 /// ```
-/// # use nickel_lang::term::{RichTerm, Term};
-/// let rt = RichTerm::from(Term::Num(5.0));
+/// # use nickel_lang::term::{number::Number, RichTerm, Term};
+/// let rt = RichTerm::from(Term::Num(Number::Int(5)));
 ///
 /// match rt.term.into_owned() {
-///     Term::Num(x) => x as usize,
+///     Term::Num(x) => x.to_f64() as usize,
 ///     Term::Str(s) => s.len(),
 ///     _ => 42,
 /// };
 /// ```
 /// to
 /// ```
-/// # use nickel_lang::term::{RichTerm, Term};
+/// # use nickel_lang::term::{number::Number, RichTerm, Term};
 /// # use nickel_lang::match_sharedterm;
-/// let rt = RichTerm::from(Term::Num(5.0));
+/// let rt = RichTerm::from(Term::Num(Number::Int(5)));
 ///
 /// match_sharedterm!{rt.term, with {
-///         Term::Num(x) => x as usize,
+///         Term::Num(x) => x.to_f64() as usize,
 ///         Term::Str(s) => s.len(),
 ///     } else 42
 /// };
@@ -1698,13 +1782,13 @@ pub mod make {
     #[macro_export]
     macro_rules! mk_record {
         ( $( ($id:expr, $body:expr) ),* ) => {
-            {
-                let mut fields = std::collections::HashMap::new();
-                $(
-                    fields.insert($id.into(), $body.into());
-                )*
-                $crate::term::RichTerm::from($crate::term::Term::Record($crate::term::record::RecordData::with_fields(fields)))
-            }
+            $crate::term::RichTerm::from(
+                $crate::term::Term::Record(
+                    $crate::term::record::RecordData::with_fields(
+                        [$(($id.into(), $body.into())),*].into_iter().collect()
+                    )
+                )
+            )
         };
     }
 
@@ -1799,7 +1883,6 @@ pub mod make {
         Term::LetPattern(id.map(|i| i.into()), pat.into(), t1.into(), t2.into()).into()
     }
 
-    #[cfg(test)]
     pub fn if_then_else<T1, T2, T3>(cond: T1, t1: T2, t2: T3) -> RichTerm
     where
         T1: Into<RichTerm>,
@@ -1856,7 +1939,7 @@ pub mod make {
     where
         S: Into<OsString>,
     {
-        Term::Import(path.into()).into()
+        Term::Import(path.into(), None).into()
     }
 }
 
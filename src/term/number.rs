@@ -0,0 +1,281 @@
+//! Representation of Nickel numbers.
+//!
+//! A number is either an integer, represented exactly, or a floating-point value. Integer literals
+//! and integers coming from JSON, YAML or TOML are represented exactly, and arithmetic stays exact
+//! as long as both operands are integers and the result is an integer which doesn't overflow.
+//! Otherwise, the computation falls back to floating-point.
+use serde::de::{Deserialize, Deserializer, Visitor};
+use std::{cmp::Ordering, fmt, ops, str::FromStr};
+
+/// A Nickel number.
+#[derive(Debug, Clone, Copy)]
+pub enum Number {
+    /// An exact integer.
+    Int(i128),
+    /// A floating-point value.
+    Float(f64),
+}
+
+impl Number {
+    /// Convert a number to a floating-point value, possibly losing precision.
+    pub fn to_f64(self) -> f64 {
+        match self {
+            Number::Int(n) => n as f64,
+            Number::Float(f) => f,
+        }
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.to_f64() == 0.0
+    }
+
+    pub fn pow(self, other: Number) -> Number {
+        int_op(self, other, |n1, n2| {
+            u32::try_from(n2).ok().and_then(|n2| n1.checked_pow(n2))
+        })
+        .unwrap_or_else(|| Number::Float(self.to_f64().powf(other.to_f64())))
+    }
+}
+
+impl ops::Add for Number {
+    type Output = Number;
+
+    fn add(self, other: Number) -> Number {
+        int_op(self, other, i128::checked_add)
+            .unwrap_or_else(|| Number::Float(self.to_f64() + other.to_f64()))
+    }
+}
+
+impl ops::Sub for Number {
+    type Output = Number;
+
+    fn sub(self, other: Number) -> Number {
+        int_op(self, other, i128::checked_sub)
+            .unwrap_or_else(|| Number::Float(self.to_f64() - other.to_f64()))
+    }
+}
+
+impl ops::Mul for Number {
+    type Output = Number;
+
+    fn mul(self, other: Number) -> Number {
+        int_op(self, other, i128::checked_mul)
+            .unwrap_or_else(|| Number::Float(self.to_f64() * other.to_f64()))
+    }
+}
+
+/// The result of the division of two integers is an integer only if the division is exact.
+impl ops::Div for Number {
+    type Output = Number;
+
+    fn div(self, other: Number) -> Number {
+        int_op(self, other, |n1, n2| {
+            n1.checked_rem(n2)
+                .filter(|rem| *rem == 0)
+                .and_then(|_| n1.checked_div(n2))
+        })
+        .unwrap_or_else(|| Number::Float(self.to_f64() / other.to_f64()))
+    }
+}
+
+/// Remainder of the truncated division, which has the sign of the dividend.
+impl ops::Rem for Number {
+    type Output = Number;
+
+    fn rem(self, other: Number) -> Number {
+        int_op(self, other, i128::checked_rem)
+            .unwrap_or_else(|| Number::Float(self.to_f64() % other.to_f64()))
+    }
+}
+
+impl ops::Neg for Number {
+    type Output = Number;
+
+    fn neg(self) -> Number {
+        match self {
+            Number::Int(n) => n
+                .checked_neg()
+                .map(Number::Int)
+                .unwrap_or_else(|| Number::Float(-(n as f64))),
+            Number::Float(f) => Number::Float(-f),
+        }
+    }
+}
+
+/// Apply an integer operation if both operands are integers. Return `None` if one of the operand
+/// is a floating-point value, or if the operation fails.
+fn int_op<F>(n1: Number, n2: Number, op: F) -> Option<Number>
+where
+    F: FnOnce(i128, i128) -> Option<i128>,
+{
+    match (n1, n2) {
+        (Number::Int(n1), Number::Int(n2)) => op(n1, n2).map(Number::Int),
+        _ => None,
+    }
+}
+
+/// Compare an integer with a floating-point value, exactly if the floating-point value is integral.
+fn cmp_int_float(n: i128, f: f64) -> Option<Ordering> {
+    if f.fract() == 0.0 && f.abs() < 2f64.powi(127) {
+        Some(n.cmp(&(f as i128)))
+    } else {
+        (n as f64).partial_cmp(&f)
+    }
+}
+
+impl PartialEq for Number {
+    fn eq(&self, other: &Number) -> bool {
+        self.partial_cmp(other) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for Number {
+    fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+        match (*self, *other) {
+            (Number::Int(n1), Number::Int(n2)) => Some(n1.cmp(&n2)),
+            (Number::Float(f1), Number::Float(f2)) => f1.partial_cmp(&f2),
+            (Number::Int(n), Number::Float(f)) => cmp_int_float(n, f),
+            (Number::Float(f), Number::Int(n)) => cmp_int_float(n, f).map(Ordering::reverse),
+        }
+    }
+}
+
+impl fmt::Display for Number {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Number::Int(n) => write!(f, "{}", n),
+            Number::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+/// Parse an integer exactly, or a floating-point value otherwise.
+impl FromStr for Number {
+    type Err = std::num::ParseFloatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<i128>() {
+            Ok(n) => Ok(Number::Int(n)),
+            Err(_) => s.parse::<f64>().map(Number::Float),
+        }
+    }
+}
+
+impl From<f64> for Number {
+    fn from(f: f64) -> Self {
+        Number::Float(f)
+    }
+}
+
+impl From<i64> for Number {
+    fn from(n: i64) -> Self {
+        Number::Int(n.into())
+    }
+}
+
+impl From<u64> for Number {
+    fn from(n: u64) -> Self {
+        Number::Int(n.into())
+    }
+}
+
+impl From<u32> for Number {
+    fn from(n: u32) -> Self {
+        Number::Int(n.into())
+    }
+}
+
+impl From<usize> for Number {
+    fn from(n: usize) -> Self {
+        // unwrap(): usize is at most 128 bits wide, and in practice at most 64 bits
+        Number::Int(i128::try_from(n).unwrap())
+    }
+}
+
+impl<'de> Deserialize<'de> for Number {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct NumberVisitor;
+
+        impl<'de> Visitor<'de> for NumberVisitor {
+            type Value = Number;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a number")
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<Number, E> {
+                Ok(Number::from(n))
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<Number, E> {
+                Ok(Number::from(n))
+            }
+
+            fn visit_i128<E>(self, n: i128) -> Result<Number, E> {
+                Ok(Number::Int(n))
+            }
+
+            fn visit_u128<E>(self, n: u128) -> Result<Number, E> {
+                Ok(i128::try_from(n)
+                    .map(Number::Int)
+                    .unwrap_or(Number::Float(n as f64)))
+            }
+
+            fn visit_f64<E>(self, f: f64) -> Result<Number, E> {
+                Ok(Number::Float(f))
+            }
+        }
+
+        deserializer.deserialize_any(NumberVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exact_arithmetic() {
+        let big = Number::Int(1 << 60);
+        assert_eq!((big + Number::Int(1)).to_string(), "1152921504606846977");
+        assert_eq!((big * Number::Int(3)).to_string(), "3458764513820540928");
+        assert_eq!((Number::Int(7) / Number::Int(2)).to_string(), "3.5");
+        assert_eq!((Number::Int(8) / Number::Int(2)).to_string(), "4");
+        assert_eq!((Number::Int(-7) % Number::Int(2)).to_string(), "-1");
+        assert_eq!(
+            Number::Int(2).pow(Number::Int(100)).to_string(),
+            "1267650600228229401496703205376"
+        );
+        assert_eq!(Number::Int(2).pow(Number::Int(-1)).to_string(), "0.5");
+        assert_eq!(
+            (Number::Int(i128::MAX) + Number::Int(1)).to_f64(),
+            i128::MAX as f64
+        );
+        assert_eq!((Number::Int(1) + Number::Float(0.5)).to_string(), "1.5");
+    }
+
+    #[test]
+    fn comparison() {
+        assert_eq!(Number::Int(1), Number::Float(1.0));
+        assert_ne!(
+            Number::Int((1 << 53) + 1),
+            Number::Float((1u64 << 53) as f64)
+        );
+        assert!(Number::Int((1 << 53) + 1) > Number::Float((1u64 << 53) as f64));
+        assert!(Number::Float(0.5) < Number::Int(1));
+        assert_eq!(Number::Int(1).partial_cmp(&Number::Float(f64::NAN)), None);
+    }
+
+    #[test]
+    fn parsing() {
+        assert_eq!(
+            "9007199254740993".parse::<Number>().unwrap().to_string(),
+            "9007199254740993"
+        );
+        assert!(matches!("1.0".parse::<Number>(), Ok(Number::Float(_))));
+        assert!(matches!("12".parse::<Number>(), Ok(Number::Int(12))));
+    }
+}
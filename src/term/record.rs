@@ -1,5 +1,6 @@
 use super::{RichTerm, SealingKey};
 use crate::{identifier::Ident, label::Label};
+use indexmap::IndexMap;
 use std::{
     collections::{HashMap, HashSet},
     rc::Rc,
@@ -79,8 +80,8 @@ pub struct RecordDeps {
 /// [super::Term::RecRecord] terms.
 #[derive(Clone, Debug, Default, PartialEq)]
 pub struct RecordData {
-    /// Fields whose names are known statically.
-    pub fields: HashMap<Ident, RichTerm>,
+    /// Fields whose names are known statically, in definition order.
+    pub fields: IndexMap<Ident, RichTerm>,
     /// Attributes which may be applied to a record.
     pub attrs: RecordAttrs,
     /// The hidden part of a record under a polymorphic contract.
@@ -89,7 +90,7 @@ pub struct RecordData {
 
 impl RecordData {
     pub fn new(
-        fields: HashMap<Ident, RichTerm>,
+        fields: IndexMap<Ident, RichTerm>,
         attrs: RecordAttrs,
         sealed_tail: Option<SealedTail>,
     ) -> Self {
@@ -106,7 +107,7 @@ impl RecordData {
     }
 
     /// A record with the provided fields & the default set of attributes.
-    pub fn with_fields(fields: HashMap<Ident, RichTerm>) -> Self {
+    pub fn with_fields(fields: IndexMap<Ident, RichTerm>) -> Self {
         let attrs = Default::default();
         let sealed_tail = Default::default();
         RecordData {
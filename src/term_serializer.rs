@@ -0,0 +1,719 @@
+//! Serialization of plain Rust values to Nickel terms.
+//!
+//! This is the converse of [`crate::deserialize`]: [`to_term`] turns any value implementing
+//! [`Serialize`] into a [`RichTerm`], which can then be bound in a Nickel program. The mapping
+//! follows the one used by the deserializer:
+//!
+//! - structs and maps are records. Map keys must be strings, characters or integers.
+//! - sequences and tuples are arrays.
+//! - unit variants are enum tags, as in `` `Foo ``, while other variants are enum variants whose
+//!   argument is the value of the variant, as in `` `Foo [1, 2] ``.
+//! - `None`, `()` and unit structs are `null`.
+use indexmap::IndexMap;
+use serde::ser::{
+    Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant, SerializeTuple,
+    SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+
+use crate::identifier::Ident;
+use crate::term::array::{Array, ArrayAttrs};
+use crate::term::{number::Number, record::RecordData, RichTerm, Term};
+
+/// An error occurred during serialization of a Rust value.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum RustSerializationError {
+    /// The key of a map is not a string, a character or an integer.
+    InvalidKey {
+        occurred: String,
+    },
+    Other(String),
+}
+
+impl std::fmt::Display for RustSerializationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            RustSerializationError::InvalidKey { ref occurred } => {
+                write!(f, "invalid record key: {occurred}, expected a string")
+            }
+            RustSerializationError::Other(ref err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for RustSerializationError {}
+
+impl serde::ser::Error for RustSerializationError {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        RustSerializationError::Other(msg.to_string())
+    }
+}
+
+/// Convert a Rust value to a Nickel term.
+pub fn to_term<T>(value: &T) -> Result<RichTerm, RustSerializationError>
+where
+    T: Serialize + ?Sized,
+{
+    value.serialize(TermSerializer)
+}
+
+fn mk_array(elts: Vec<RichTerm>) -> RichTerm {
+    Term::Array(Array::new(elts.into()), ArrayAttrs::default()).into()
+}
+
+fn mk_record(fields: IndexMap<Ident, RichTerm>) -> RichTerm {
+    Term::Record(RecordData::with_fields(fields)).into()
+}
+
+/// Build an enum variant whose argument is the value of a Rust variant.
+fn mk_variant(variant: &str, value: RichTerm) -> RichTerm {
+    Term::EnumVariant(Ident::from(variant), value).into()
+}
+
+/// A [`Serializer`] producing a [`RichTerm`].
+pub struct TermSerializer;
+
+impl Serializer for TermSerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    type SerializeSeq = ArraySerializer;
+    type SerializeTuple = ArraySerializer;
+    type SerializeTupleStruct = ArraySerializer;
+    type SerializeTupleVariant = VariantSerializer<ArraySerializer>;
+    type SerializeMap = RecordSerializer;
+    type SerializeStruct = RecordSerializer;
+    type SerializeStructVariant = VariantSerializer<RecordSerializer>;
+
+    fn serialize_bool(self, v: bool) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Bool(v).into())
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<RichTerm, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<RichTerm, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<RichTerm, Self::Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Num(Number::from(v)).into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Num(Number::Int(v)).into())
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<RichTerm, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<RichTerm, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<RichTerm, Self::Error> {
+        self.serialize_u64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Num(Number::from(v)).into())
+    }
+
+    /// Integers which don't fit in an `i128` are approximated by a floating-point value.
+    fn serialize_u128(self, v: u128) -> Result<RichTerm, Self::Error> {
+        let n = i128::try_from(v)
+            .map(Number::Int)
+            .unwrap_or(Number::Float(v as f64));
+        Ok(Term::Num(n).into())
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<RichTerm, Self::Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Num(Number::Float(v)).into())
+    }
+
+    fn serialize_char(self, v: char) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Str(v.to_string()).into())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Str(String::from(v)).into())
+    }
+
+    /// Bytes are serialized as an array of numbers.
+    fn serialize_bytes(self, v: &[u8]) -> Result<RichTerm, Self::Error> {
+        Ok(mk_array(
+            v.iter()
+                .map(|byte| Term::Num(Number::from(u32::from(*byte))).into())
+                .collect(),
+        ))
+    }
+
+    fn serialize_none(self) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Null.into())
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<RichTerm, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Null.into())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<RichTerm, Self::Error> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<RichTerm, Self::Error> {
+        Ok(Term::Enum(Ident::from(variant)).into())
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<RichTerm, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<RichTerm, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Ok(mk_variant(variant, value.serialize(self)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ArraySerializer, Self::Error> {
+        Ok(ArraySerializer::new(len.unwrap_or(0)))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ArraySerializer, Self::Error> {
+        Ok(ArraySerializer::new(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ArraySerializer, Self::Error> {
+        Ok(ArraySerializer::new(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<VariantSerializer<ArraySerializer>, Self::Error> {
+        Ok(VariantSerializer {
+            variant,
+            inner: ArraySerializer::new(len),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<RecordSerializer, Self::Error> {
+        Ok(RecordSerializer::new())
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<RecordSerializer, Self::Error> {
+        Ok(RecordSerializer::new())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<VariantSerializer<RecordSerializer>, Self::Error> {
+        Ok(VariantSerializer {
+            variant,
+            inner: RecordSerializer::new(),
+        })
+    }
+}
+
+/// Serializer for sequences and tuples, which are represented as arrays.
+pub struct ArraySerializer {
+    elts: Vec<RichTerm>,
+}
+
+impl ArraySerializer {
+    fn new(len: usize) -> Self {
+        ArraySerializer {
+            elts: Vec::with_capacity(len),
+        }
+    }
+}
+
+impl SerializeSeq for ArraySerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.elts.push(value.serialize(TermSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        Ok(mk_array(self.elts))
+    }
+}
+
+impl SerializeTuple for ArraySerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_element<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+impl SerializeTupleStruct for ArraySerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(self, value)
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        SerializeSeq::end(self)
+    }
+}
+
+/// Serializer for maps and structs, which are represented as records.
+pub struct RecordSerializer {
+    fields: IndexMap<Ident, RichTerm>,
+    next_key: Option<Ident>,
+}
+
+impl RecordSerializer {
+    fn new() -> Self {
+        RecordSerializer {
+            fields: IndexMap::new(),
+            next_key: None,
+        }
+    }
+}
+
+impl SerializeMap for RecordSerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_key<T>(&mut self, key: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.next_key = Some(Ident::from(key.serialize(KeySerializer)?));
+        Ok(())
+    }
+
+    fn serialize_value<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        let key = self.next_key.take().ok_or_else(|| {
+            RustSerializationError::Other(String::from("value serialized before its key"))
+        })?;
+        self.fields.insert(key, value.serialize(TermSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        Ok(mk_record(self.fields))
+    }
+}
+
+impl SerializeStruct for RecordSerializer {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        self.fields
+            .insert(Ident::from(key), value.serialize(TermSerializer)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        SerializeMap::end(self)
+    }
+}
+
+/// Serializer for tuple and struct variants, which are represented as enum variants whose argument
+/// is the array or the record of the fields of the variant.
+pub struct VariantSerializer<S> {
+    variant: &'static str,
+    inner: S,
+}
+
+impl SerializeTupleVariant for VariantSerializer<ArraySerializer> {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_field<T>(&mut self, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeSeq::serialize_element(&mut self.inner, value)
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        Ok(mk_variant(self.variant, SerializeSeq::end(self.inner)?))
+    }
+}
+
+impl SerializeStructVariant for VariantSerializer<RecordSerializer> {
+    type Ok = RichTerm;
+    type Error = RustSerializationError;
+
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<(), Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        SerializeStruct::serialize_field(&mut self.inner, key, value)
+    }
+
+    fn end(self) -> Result<RichTerm, Self::Error> {
+        Ok(mk_variant(self.variant, SerializeMap::end(self.inner)?))
+    }
+}
+
+/// Serializer for the keys of a map, which become field names. Strings, characters and integers
+/// are accepted.
+struct KeySerializer;
+
+macro_rules! serialize_int_key {
+    ($method:ident, $type:ty) => {
+        fn $method(self, v: $type) -> Result<String, Self::Error> {
+            Ok(v.to_string())
+        }
+    };
+}
+
+macro_rules! invalid_key {
+    ($method:ident, $occurred:expr $(, $arg:ident : $type:ty)*) => {
+        fn $method(self $(, $arg: $type)*) -> Result<String, Self::Error> {
+            Err(RustSerializationError::InvalidKey {
+                occurred: String::from($occurred),
+            })
+        }
+    };
+}
+
+impl Serializer for KeySerializer {
+    type Ok = String;
+    type Error = RustSerializationError;
+
+    type SerializeSeq = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeTuple = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeTupleStruct = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeTupleVariant = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeMap = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeStruct = serde::ser::Impossible<String, RustSerializationError>;
+    type SerializeStructVariant = serde::ser::Impossible<String, RustSerializationError>;
+
+    serialize_int_key!(serialize_i8, i8);
+    serialize_int_key!(serialize_i16, i16);
+    serialize_int_key!(serialize_i32, i32);
+    serialize_int_key!(serialize_i64, i64);
+    serialize_int_key!(serialize_i128, i128);
+    serialize_int_key!(serialize_u8, u8);
+    serialize_int_key!(serialize_u16, u16);
+    serialize_int_key!(serialize_u32, u32);
+    serialize_int_key!(serialize_u64, u64);
+    serialize_int_key!(serialize_u128, u128);
+
+    invalid_key!(serialize_bool, "Bool", _v: bool);
+    invalid_key!(serialize_f32, "Num", _v: f32);
+    invalid_key!(serialize_f64, "Num", _v: f64);
+    invalid_key!(serialize_bytes, "Array", _v: &[u8]);
+    invalid_key!(serialize_none, "Null");
+    invalid_key!(serialize_unit, "Null");
+    invalid_key!(serialize_unit_struct, "Null", _name: &'static str);
+
+    fn serialize_char(self, v: char) -> Result<String, Self::Error> {
+        Ok(v.to_string())
+    }
+
+    fn serialize_str(self, v: &str) -> Result<String, Self::Error> {
+        Ok(String::from(v))
+    }
+
+    fn serialize_some<T>(self, value: &T) -> Result<String, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    /// Unit variants are used as is, as for enum tags.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<String, Self::Error> {
+        Ok(String::from(variant))
+    }
+
+    fn serialize_newtype_struct<T>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<String, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<String, Self::Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Record"),
+        })
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Array"),
+        })
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Array"),
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Array"),
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Record"),
+        })
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Record"),
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Record"),
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Self::Error> {
+        Err(RustSerializationError::InvalidKey {
+            occurred: String::from("Record"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use serde::{Deserialize, Serialize};
+
+    use crate::cache::resolvers::DummyResolver;
+    use crate::eval::{cache::CBNCache, Environment, VirtualMachine};
+    use crate::parser::{grammar, lexer};
+    use crate::term::{make as mk_term, number::Number, BinaryOp, RichTerm, Term};
+    use crate::{mk_app, mk_fun};
+    use codespan::Files;
+
+    use super::{to_term, RustSerializationError};
+
+    fn parse(s: &str) -> RichTerm {
+        let id = Files::new().add("<test>", String::from(s));
+
+        grammar::TermParser::new()
+            .parse_term(id, lexer::Lexer::new(s))
+            .unwrap()
+    }
+
+    fn eval(t: RichTerm) -> Term {
+        VirtualMachine::<_, CBNCache>::new(DummyResolver {})
+            .eval(t, &Environment::new())
+            .map(Term::from)
+            .unwrap()
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "lowercase")]
+    enum Status {
+        Up,
+        Degraded(String),
+        Moved { host: String },
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Host {
+        name: String,
+        port: u16,
+        weight: f64,
+        tags: Vec<String>,
+        backup: Option<String>,
+        status: Status,
+        pair: (i64, bool),
+    }
+
+    #[test]
+    fn rust_serialize_struct() {
+        let host = Host {
+            name: String::from("db1"),
+            port: 5432,
+            weight: 0.5,
+            tags: vec![String::from("primary")],
+            backup: None,
+            status: Status::Up,
+            pair: (-1, true),
+        };
+
+        let expected = parse(
+            "{name = \"db1\", port = 5432, weight = 0.5, tags = [\"primary\"], backup = null, \
+             status = `up, pair = [-1, true]}",
+        );
+        let term = to_term(&host).unwrap();
+        assert_eq!(
+            eval(mk_term::op2(BinaryOp::Eq(), term.clone(), expected)),
+            Term::Bool(true)
+        );
+
+        let port = mk_app!(mk_fun!("host", parse("host.port + 1")), term.clone());
+        assert_eq!(eval(port), Term::Num(Number::from(5433u32)));
+
+        assert_eq!(Host::deserialize(term), Ok(host));
+    }
+
+    #[test]
+    fn rust_serialize_variants() {
+        for status in [
+            Status::Up,
+            Status::Degraded(String::from("slow")),
+            Status::Moved {
+                host: String::from("db2"),
+            },
+        ] {
+            let term = to_term(&status).unwrap();
+            assert_eq!(Status::deserialize(term), Ok(status));
+        }
+
+        assert_eq!(
+            eval(mk_term::op2(
+                BinaryOp::Eq(),
+                to_term(&Status::Degraded(String::from("slow"))).unwrap(),
+                parse("`degraded \"slow\"")
+            )),
+            Term::Bool(true)
+        );
+    }
+
+    #[test]
+    fn rust_serialize_map_keys() {
+        let map = BTreeMap::from([(1, "a"), (2, "b")]);
+        assert_eq!(
+            eval(mk_term::op2(
+                BinaryOp::Eq(),
+                to_term(&map).unwrap(),
+                parse("{\"1\" = \"a\", \"2\" = \"b\"}")
+            )),
+            Term::Bool(true)
+        );
+
+        let map = BTreeMap::from([((1, 2), "a")]);
+        assert_eq!(
+            to_term(&map),
+            Err(RustSerializationError::InvalidKey {
+                occurred: String::from("Array")
+            })
+        );
+    }
+}
@@ -18,6 +18,19 @@
 //! ...
 //! ```
 //!
+//! ## The array pattern
+//! ```text
+//! let [a, {b}, ..rest] = [1, {b=2}, 3] in ...
+//! ```
+//! will be transformed to:
+//! ```text
+//! let %unnamed% = [1, {b=2}, 3] in
+//! let a = %elem_at% %unnamed% 0 in
+//! let {b} = %elem_at% %unnamed% 1 in
+//! let rest = %tail% (%tail% %unnamed%) in
+//! ...
+//! ```
+//!
 //! ## The function pattern
 //! ```text
 //! let f = fun x@{a, b=c} {d ? 2, ..w} => <do_something> in ...
@@ -34,7 +47,12 @@ use crate::destruct::{Destruct, Match};
 use crate::identifier::Ident;
 use crate::match_sharedterm;
 use crate::term::make::{op1, op2};
-use crate::term::{BinaryOp::DynRemove, MetaValue, RichTerm, Term, UnaryOp::StaticAccess};
+use crate::term::{
+    number::Number,
+    BinaryOp::{ArrayElemAt, DynRemove},
+    MetaValue, RichTerm, Term,
+    UnaryOp::{ArrayTail, StaticAccess},
+};
 
 /// Entry point of the patterns desugaring.
 /// It desugar a `RichTerm` if possible (the term is a let pattern or a function with patterns in
@@ -128,9 +146,26 @@ pub fn desugar(rt: RichTerm) -> RichTerm {
 /// Having `let {a,..y} = {a=1, b=2, c=3} in <BODY>` will bind `y` to `{b=2,c=3}` in `BODY`. Here,
 /// `x` is the identifier pointing to the full record. If having `val @ {...} = ... in ...` the
 /// variable x should be `Ident("val")` but if we have a `@` binding less form, you will probably
-/// generate a fresh variable.
+/// generate a fresh variable. Similarly, `let [a, ..y] = [1, 2, 3] in <BODY>` will bind `y` to
+/// `[2, 3]` in `BODY`.
 fn bind_open_field(x: Ident, pat: &Destruct, body: RichTerm) -> RichTerm {
     let (matches, var) = match pat {
+        Destruct::Array {
+            elems,
+            rest: Some(rest),
+            ..
+        } => {
+            return Term::Let(
+                *rest,
+                elems
+                    .iter()
+                    .fold(Term::Var(x).into(), |x, _| op1(ArrayTail(), x)),
+                body,
+                Default::default(),
+            )
+            .into()
+        }
+        Destruct::Array { rest: None, .. } => return body,
         Destruct::Record {
             matches,
             open: true,
@@ -184,6 +219,25 @@ fn destruct_term(x: Ident, pat: &Destruct, body: RichTerm) -> RichTerm {
                 pos,
             )),
         }),
+        // The contract of an array pattern only checks the length of the array. The contracts of
+        // the sub-patterns are applied when the elements are bound.
+        Destruct::Array { elems, .. } => {
+            elems
+                .iter()
+                .enumerate()
+                .fold(body, move |t, (i, (id, pat))| {
+                    let elem = op2(ArrayElemAt(), Term::Var(x), Term::Num(Number::from(i)));
+                    match (id, pat) {
+                        (Some(id), Destruct::Empty) => {
+                            RichTerm::new(Term::Let(*id, elem, t, Default::default()), pos)
+                        }
+                        _ => desugar_with_contract(RichTerm::new(
+                            Term::LetPattern(*id, pat.clone(), elem, t),
+                            pos,
+                        )),
+                    }
+                })
+        }
         _ => body,
     }
 }
@@ -0,0 +1,343 @@
+//! Desugar match expressions with patterns.
+//!
+//! Replace a match expression whose branches are general patterns by a function testing each
+//! branch in order. A branch is selected if the matched value has the shape described by the
+//! pattern and if the guard, evaluated in the scope of the variables bound by the pattern, is
+//! true. If no branch is selected, the value is passed to an empty enum match expression, which
+//! fails with a non-exhaustive match error.
+//!
+//! # Example
+//!
+//! ```text
+//! match {
+//!   {name, port ? 80} => port,
+//!   [x, ..] if x > 0 => x,
+//!   `Some y => y,
+//!   _ => 0,
+//! }
+//! ```
+//! will be transformed to:
+//! ```text
+//! fun %x =>
+//!   let %port = if %has_field% "port" %x then %x.port else 80 in
+//!   if %typeof% %x == `Record
+//!     && %has_field% "name" %x
+//!     && %length% (%fields% %x) == 1 + (if %has_field% "port" %x then 1 else 0) then
+//!     let name = %x.name in
+//!     let port = %port in
+//!     port
+//!   else if %typeof% %x == `Array
+//!     && %length% %x >= 1
+//!     && (let x = %elem_at% %x 0 in x > 0) then
+//!     let x = %elem_at% %x 0 in
+//!     x
+//!   else if %typeof% %x == `Enum
+//!     && %enum_is_variant% %x
+//!     && %enum_get_tag% %x == `Some then
+//!     let y = %enum_unwrap_variant% %x in
+//!     y
+//!   else
+//!     0
+//! ```
+use std::collections::HashMap;
+
+use crate::destruct::{Destruct, Match, MatchBranch};
+use crate::identifier::Ident;
+use crate::term::make::{if_then_else, let_in, op1, op2};
+use crate::term::{number::Number, BinaryOp, MetaValue, RichTerm, Term, UnaryOp};
+use crate::types::TypeF;
+use crate::{match_sharedterm, mk_app};
+
+/// Entry point of the match desugaring. Desugar a `RichTerm` if it is a match expression with
+/// patterns.
+/// ## Warning:
+/// The transformation is not recursive. The guards and the bodies of the branches can contain
+/// patterns themselves.
+pub fn transform_one(rt: RichTerm) -> RichTerm {
+    match_sharedterm! {rt.term, with {
+        Term::MatchPattern(branches) => {
+            let x = Ident::fresh();
+            let no_match = mk_app!(
+                RichTerm::new(
+                    Term::Match {
+                        cases: HashMap::new(),
+                        default: None,
+                    },
+                    rt.pos,
+                ),
+                Term::Var(x)
+            );
+            let body = branches
+                .into_iter()
+                .rev()
+                .fold(no_match, |rest, branch| desugar_branch(x, branch, rest));
+
+            RichTerm::new(Term::Fun(x, body), rt.pos)
+        }
+    } else rt
+    }
+}
+
+/// Desugar one branch of a match expression on the variable `x`, where `rest` is the desugaring of
+/// the following branches.
+fn desugar_branch(x: Ident, branch: MatchBranch, rest: RichTerm) -> RichTerm {
+    let MatchBranch {
+        pattern: (alias, pat),
+        guard,
+        body,
+    } = branch;
+
+    let mut compiled = CompiledPattern::default();
+    compiled.compile(alias, &pat, Term::Var(x).into());
+
+    let CompiledPattern {
+        mut conditions,
+        aux,
+        bindings,
+    } = compiled;
+
+    if let Some(guard) = guard {
+        conditions.push(bind_all(&bindings, guard));
+    }
+
+    let body = bind_all(&bindings, body);
+    let result = match conjunction(conditions) {
+        Some(cond) => if_then_else(cond, body, rest),
+        // An irrefutable branch without guard always matches: the following branches are dead
+        // code.
+        None => body,
+    };
+
+    bind_all(&aux, result)
+}
+
+/// The conditions and the bindings generated by a pattern.
+#[derive(Default)]
+struct CompiledPattern {
+    /// The conditions for the pattern to match. A condition is only evaluated if the previous ones
+    /// are true.
+    conditions: Vec<RichTerm>,
+    /// Bindings of fresh variables to the values of record fields with default values or
+    /// annotations. They are in scope of the conditions, but not of the values they hold.
+    aux: Vec<(Ident, RichTerm)>,
+    /// The variables bound by the pattern, in scope of the guard and of the body.
+    bindings: Vec<(Ident, RichTerm)>,
+}
+
+impl CompiledPattern {
+    /// Compile the pattern `alias @ pat`, matched against `value`.
+    fn compile(&mut self, alias: Option<Ident>, pat: &Destruct, value: RichTerm) {
+        if let Some(id) = alias {
+            self.bindings.push((id, value.clone()));
+        }
+
+        match pat {
+            Destruct::Empty => (),
+            Destruct::Constant(c) => {
+                self.check_type(type_tag(c), &value);
+                self.conditions
+                    .push(op2(BinaryOp::Eq(), value, c.clone().without_pos()));
+            }
+            Destruct::Enum(id) => {
+                self.check_type("Enum", &value);
+                self.conditions
+                    .push(op2(BinaryOp::Eq(), value, Term::Enum(*id)));
+            }
+            Destruct::EnumVariant(id, pat) => {
+                self.check_type("Enum", &value);
+                self.conditions
+                    .push(op1(UnaryOp::EnumIsVariant(), value.clone()));
+                self.conditions.push(op2(
+                    BinaryOp::Eq(),
+                    op1(UnaryOp::EnumGetTag(), value.clone()),
+                    Term::Enum(*id),
+                ));
+
+                let (alias, pat) = pat.as_ref();
+                self.compile(*alias, pat, op1(UnaryOp::EnumUnwrapVariant(), value));
+            }
+            Destruct::Array {
+                elems, open, rest, ..
+            } => {
+                self.check_type("Array", &value);
+
+                let length = op1(UnaryOp::ArrayLength(), value.clone());
+                let expected = Term::Num(Number::from(elems.len()));
+                self.conditions.push(if *open {
+                    op2(BinaryOp::GreaterOrEq(), length, expected)
+                } else {
+                    op2(BinaryOp::Eq(), length, expected)
+                });
+
+                for (i, (alias, pat)) in elems.iter().enumerate() {
+                    let elem = op2(
+                        BinaryOp::ArrayElemAt(),
+                        value.clone(),
+                        Term::Num(Number::from(i)),
+                    );
+                    self.compile(*alias, pat, elem);
+                }
+
+                if let Some(rest) = rest {
+                    let tail = elems
+                        .iter()
+                        .fold(value, |acc, _| op1(UnaryOp::ArrayTail(), acc));
+                    self.bindings.push((*rest, tail));
+                }
+            }
+            Destruct::Record {
+                matches,
+                open,
+                rest,
+                ..
+            } => {
+                self.check_type("Record", &value);
+
+                let mut fields = Vec::with_capacity(matches.len());
+
+                for m in matches {
+                    let (id, meta) = match m {
+                        Match::Simple(id, meta) | Match::Assign(id, meta, _) => (*id, meta),
+                    };
+                    let optional = meta.value.is_some();
+                    let field = self.compile_field(id, meta, &value);
+                    fields.push((id, optional));
+
+                    match m {
+                        Match::Simple(..) => self.bindings.push((id, field)),
+                        Match::Assign(_, _, (alias, pat)) => self.compile(*alias, pat, field),
+                    }
+                }
+
+                if !open {
+                    let nb_fields = op1(
+                        UnaryOp::ArrayLength(),
+                        op1(UnaryOp::FieldsOf(), value.clone()),
+                    );
+                    let nb_required = fields.iter().filter(|(_, optional)| !optional).count();
+                    let expected = fields.iter().filter(|(_, optional)| *optional).fold(
+                        RichTerm::from(Term::Num(Number::from(nb_required))),
+                        |acc, (id, _)| {
+                            op2(
+                                BinaryOp::Plus(),
+                                acc,
+                                if_then_else(
+                                    has_field(*id, value.clone()),
+                                    Term::Num(Number::Int(1)),
+                                    Term::Num(Number::Int(0)),
+                                ),
+                            )
+                        },
+                    );
+                    self.conditions
+                        .push(op2(BinaryOp::Eq(), nb_fields, expected));
+                }
+
+                if let Some(rest) = rest {
+                    let remaining = fields.iter().fold(value, |acc, (id, optional)| {
+                        if *optional {
+                            let r = Ident::fresh();
+                            let_in(
+                                r,
+                                acc,
+                                if_then_else(
+                                    has_field(*id, Term::Var(r)),
+                                    remove_field(*id, Term::Var(r)),
+                                    Term::Var(r),
+                                ),
+                            )
+                        } else {
+                            remove_field(*id, acc)
+                        }
+                    });
+                    self.bindings.push((*rest, remaining));
+                }
+            }
+        }
+    }
+
+    /// Compile the access to the field `id` of `record`, given the annotations and the default
+    /// value of the field in the pattern. Return the value of the field.
+    fn compile_field(&mut self, id: Ident, meta: &MetaValue, record: &RichTerm) -> RichTerm {
+        let access = op1(UnaryOp::StaticAccess(id), record.clone());
+
+        let value = match &meta.value {
+            Some(default) => if_then_else(has_field(id, record.clone()), access, default.clone()),
+            None => {
+                self.conditions.push(has_field(id, record.clone()));
+                access
+            }
+        };
+
+        // The parser attaches a `Dyn` contract to fields without annotation.
+        let contracts: Vec<_> = meta
+            .contracts
+            .iter()
+            .filter(|ctr| !matches!(ctr.types.0, TypeF::Dyn))
+            .cloned()
+            .collect();
+
+        if meta.value.is_none() && meta.types.is_none() && contracts.is_empty() {
+            return value;
+        }
+
+        let value = if meta.types.is_none() && contracts.is_empty() {
+            value
+        } else {
+            Term::MetaValue(MetaValue {
+                types: meta.types.clone(),
+                contracts,
+                value: Some(value),
+                ..Default::default()
+            })
+            .into()
+        };
+
+        let aux = Ident::fresh();
+        self.aux.push((aux, value));
+        Term::Var(aux).into()
+    }
+
+    /// Add the condition that `value` has the type `tag`, as returned by `%typeof%`.
+    fn check_type(&mut self, tag: &str, value: &RichTerm) {
+        self.conditions.push(op2(
+            BinaryOp::Eq(),
+            op1(UnaryOp::Typeof(), value.clone()),
+            Term::Enum(Ident::from(tag)),
+        ));
+    }
+}
+
+/// The type of a constant pattern, as returned by `%typeof%`.
+fn type_tag(constant: &RichTerm) -> &'static str {
+    match constant.as_ref() {
+        Term::Bool(_) => "Bool",
+        Term::Num(_) => "Num",
+        Term::Str(_) => "Str",
+        _ => "Other",
+    }
+}
+
+fn has_field(id: Ident, record: impl Into<RichTerm>) -> RichTerm {
+    op2(BinaryOp::HasField(), Term::Str(id.to_string()), record)
+}
+
+fn remove_field(id: Ident, record: impl Into<RichTerm>) -> RichTerm {
+    op2(BinaryOp::DynRemove(), Term::Str(id.to_string()), record)
+}
+
+/// Wrap a term in let-bindings, the first binding being the outermost one.
+fn bind_all(bindings: &[(Ident, RichTerm)], t: RichTerm) -> RichTerm {
+    bindings
+        .iter()
+        .rev()
+        .fold(t, |t, (id, value)| let_in(*id, value.clone(), t))
+}
+
+/// Combine conditions with a lazy conjunction. Return `None` if there is no condition.
+fn conjunction(conditions: Vec<RichTerm>) -> Option<RichTerm> {
+    conditions
+        .into_iter()
+        .rev()
+        .reduce(|acc, cond| if_then_else(cond, acc, Term::Bool(false)))
+}
@@ -4,13 +4,13 @@
 //! the recursive fields that actually appear in the definition of each field when computing the
 //! fixpoint.
 use crate::{
-    destruct::{Destruct, Match},
+    destruct::{Destruct, Match, MatchBranch},
     identifier::Ident,
     term::{
         record::{FieldDeps, RecordDeps},
         RichTerm, SharedTerm, StrChunk, Term,
     },
-    types::{RecordRowF, RecordRows, RecordRowsF, TypeF, Types},
+    types::{EnumRowF, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types},
 };
 
 use std::collections::{HashMap, HashSet};
@@ -40,7 +40,7 @@ impl CollectFreeVars for RichTerm {
             | Term::Lbl(_)
             | Term::SealingKey(_)
             | Term::Enum(_)
-            | Term::Import(_)
+            | Term::Import(..)
             | Term::ResolvedImport(_) => (),
             Term::Fun(id, t) => {
                 let mut fresh = HashSet::new();
@@ -96,7 +96,27 @@ impl CollectFreeVars for RichTerm {
                     t.collect_free_vars(free_vars);
                 }
             }
-            Term::Op1(_, t) => t.collect_free_vars(free_vars),
+            Term::MatchPattern(branches) => {
+                for MatchBranch {
+                    pattern: (id, dest_pat),
+                    guard,
+                    body,
+                } in branches
+                {
+                    let mut fresh = HashSet::new();
+
+                    for t in guard.iter_mut().chain(std::iter::once(body)) {
+                        t.collect_free_vars(&mut fresh);
+                    }
+                    bind_pattern(dest_pat, &mut fresh);
+                    if let Some(id) = id {
+                        fresh.remove(id);
+                    }
+
+                    free_vars.extend(fresh);
+                }
+            }
+            Term::Op1(_, t) | Term::EnumVariant(_, t) => t.collect_free_vars(free_vars),
             Term::Op2(_, t1, t2) => {
                 t1.collect_free_vars(free_vars);
                 t2.collect_free_vars(free_vars);
@@ -190,8 +210,7 @@ impl CollectFreeVars for Types {
             TypeF::Forall { body: ty, .. } | TypeF::Dict(ty) | TypeF::Array(ty) => {
                 ty.as_mut().collect_free_vars(set)
             }
-            // No term can appear anywhere in a enum row type, hence we can stop here.
-            TypeF::Enum(_) => (),
+            TypeF::Enum(erows) => erows.collect_free_vars(set),
             TypeF::Record(rrows) => rrows.collect_free_vars(set),
             TypeF::Arrow(ty1, ty2) => {
                 ty1.as_mut().collect_free_vars(set);
@@ -217,6 +236,23 @@ impl CollectFreeVars for RecordRows {
     }
 }
 
+impl CollectFreeVars for EnumRows {
+    fn collect_free_vars(&mut self, set: &mut HashSet<Ident>) {
+        match &mut self.0 {
+            EnumRowsF::Empty | EnumRowsF::TailVar(_) => (),
+            EnumRowsF::Extend {
+                row: EnumRowF { typ, .. },
+                tail,
+            } => {
+                if let Some(ty) = typ {
+                    ty.collect_free_vars(set);
+                }
+                tail.collect_free_vars(set);
+            }
+        }
+    }
+}
+
 /// Remove the variables bound by a destructuring pattern from a set of free variables.
 fn bind_pattern(dest_pat: &Destruct, free_vars: &mut HashSet<Ident>) {
     match dest_pat {
@@ -229,12 +265,26 @@ fn bind_pattern(dest_pat: &Destruct, free_vars: &mut HashSet<Ident>) {
                 free_vars.remove(rest);
             }
         }
-        Destruct::Array { matches, .. } => {
-            for m in matches {
-                bind_match(m, free_vars);
+        Destruct::Array { elems, rest, .. } => {
+            for (id, sub_pat) in elems {
+                if let Some(id) = id {
+                    free_vars.remove(id);
+                }
+                bind_pattern(sub_pat, free_vars);
             }
+
+            if let Some(rest) = rest {
+                free_vars.remove(rest);
+            }
+        }
+        Destruct::EnumVariant(_, pat) => {
+            let (id, sub_pat) = pat.as_ref();
+            if let Some(id) = id {
+                free_vars.remove(id);
+            }
+            bind_pattern(sub_pat, free_vars);
         }
-        Destruct::Empty => {}
+        Destruct::Constant(_) | Destruct::Enum(_) | Destruct::Empty => {}
     }
 }
 
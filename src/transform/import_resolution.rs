@@ -71,8 +71,8 @@ where
 {
     let term = rt.as_ref();
     match term {
-        Term::Import(path) => {
-            let (_, file_id) = resolver.resolve(path, parent.clone(), &rt.pos)?;
+        Term::Import(path, format) => {
+            let (_, file_id) = resolver.resolve(path, *format, parent.clone(), &rt.pos)?;
             Ok(RichTerm::new(Term::ResolvedImport(file_id), rt.pos))
         }
         _ => Ok(rt),
@@ -10,6 +10,7 @@ use crate::{
 
 pub mod apply_contracts;
 pub mod desugar_destructuring;
+pub mod desugar_match;
 pub mod free_vars;
 pub mod import_resolution;
 pub mod share_normal_form;
@@ -42,6 +43,7 @@ pub fn transform_no_free_vars(
             }
             // before anything, we have to desugar the syntax
             let rt = desugar_destructuring::transform_one(rt);
+            let rt = desugar_match::transform_one(rt);
             // We need to do contract generation before wrapping stuff in variables
             let rt = apply_contracts::transform_one(rt)?;
             Ok(rt)
@@ -15,8 +15,8 @@
 //! two times here.
 //!
 //! The transformation replaces such subexpressions, namely the content of the fields
-//! of records and the elements of arrays - `(1 + 1)` in our example -, with fresh variables
-//! introduced by `let`  added at the head of the term:
+//! of records, the elements of arrays and the payloads of enum variants - `(1 + 1)` in our
+//! example -, with fresh variables introduced by `let`  added at the head of the term:
 //!
 //! ```text
 //! let x = (let var = 1 + 1 in {a = var}) in x.a + x.a
@@ -149,6 +149,19 @@ pub fn transform_one(rt: RichTerm) -> RichTerm {
 
                 with_bindings(Term::Array(ts, attrs), bindings, pos)
             },
+            Term::EnumVariant(tag, arg) if should_share(&arg.term) => {
+                let fresh_var = Ident::fresh();
+                let pos_arg = arg.pos;
+                let inner = RichTerm::new(
+                    Term::EnumVariant(tag, RichTerm::new(Term::Var(fresh_var), pos_arg)),
+                    pos,
+                );
+                let attrs = LetAttrs {
+                    binding_type: BindingType::Normal,
+                    rec: false,
+                };
+                RichTerm::new(Term::Let(fresh_var, arg, inner, attrs), pos)
+            },
             Term::MetaValue(meta) if meta.value.as_ref().map(|t| should_share(&t.term)).unwrap_or(false) => {
                     let mut meta = meta;
                     let fresh_var = Ident::fresh();
@@ -47,6 +47,7 @@ use crate::{
     eval::{self, cache::Cache},
     term::UnaryOp,
 };
+use indexmap::IndexMap;
 
 /// The maximal number of variable links we want to unfold before abandoning the check. It should
 /// stay low, but has been fixed arbitrarily: feel fee to increase reasonably if it turns out
@@ -348,9 +349,9 @@ fn contract_eq_bounded<E: TermEnvironment>(
 fn map_eq<V, F, E>(
     mut f: F,
     state: &mut State,
-    map1: &HashMap<Ident, V>,
+    map1: &IndexMap<Ident, V>,
     env1: &E,
-    map2: &HashMap<Ident, V>,
+    map2: &IndexMap<Ident, V>,
     env2: &E,
 ) -> bool
 where
@@ -370,8 +371,8 @@ where
 /// returned. `None` is returned as well if a type encountered is not row, or if it is a enum row.
 fn rows_as_map<E: TermEnvironment>(
     erows: &GenericUnifRecordRows<E>,
-) -> Option<HashMap<Ident, &GenericUnifType<E>>> {
-    let map: Option<HashMap<Ident, _>> = erows
+) -> Option<IndexMap<Ident, &GenericUnifType<E>>> {
+    let map: Option<IndexMap<Ident, _>> = erows
         .iter()
         .map(|item| match item {
             GenericUnifRecordRowsIteratorItem::Row(RecordRowF { id, types }) => Some((id, types)),
@@ -382,21 +383,23 @@ fn rows_as_map<E: TermEnvironment>(
     map
 }
 
-/// Convert enum rows to a hashset.
+/// Convert enum rows to a hashmap from tags to the type of their payload, if any.
 ///
 /// Require the rows to be closed (i.e. the last element must be `RowEmpty`), otherwise `None` is
 /// returned. `None` is returned as well if a type encountered is not row type, or if it is a
 /// record row.
-fn rows_as_set(erows: &UnifEnumRows) -> Option<HashSet<Ident>> {
-    let set: Option<HashSet<_>> = erows
+fn erows_as_map<E: TermEnvironment>(
+    erows: &GenericUnifEnumRows<E>,
+) -> Option<IndexMap<Ident, Option<&GenericUnifType<E>>>> {
+    let map: Option<IndexMap<Ident, _>> = erows
         .iter()
         .map(|item| match item {
-            UnifEnumRowsIteratorItem::Row(id) => Some(*id),
+            GenericUnifEnumRowsIteratorItem::Row(EnumRowF { id, typ }) => Some((id, typ)),
             _ => None,
         })
         .collect();
 
-    set
+    map
 }
 
 /// Perform the type equality comparison on types. Structurally recurse into type constructors and test
@@ -432,9 +435,28 @@ fn type_eq_bounded<E: TermEnvironment>(
                     && type_eq_bounded(state, t1, env1, t2, env2)
             }
             (TypeF::Enum(uty1), TypeF::Enum(uty2)) => {
-                let rows1 = rows_as_set(uty1);
-                let rows2 = rows_as_set(uty2);
-                rows1.is_some() && rows2.is_some() && rows1 == rows2
+                fn payload_eq<E: TermEnvironment>(
+                    state: &mut State,
+                    uty1: &Option<&GenericUnifType<E>>,
+                    env1: &E,
+                    uty2: &Option<&GenericUnifType<E>>,
+                    env2: &E,
+                ) -> bool {
+                    match (uty1, uty2) {
+                        (Some(uty1), Some(uty2)) => {
+                            type_eq_bounded(state, *uty1, env1, *uty2, env2)
+                        }
+                        (None, None) => true,
+                        _ => false,
+                    }
+                }
+
+                let map1 = erows_as_map(uty1);
+                let map2 = erows_as_map(uty2);
+
+                map1.zip(map2)
+                    .map(|(m1, m2)| map_eq(payload_eq, state, &m1, env1, &m2, env2))
+                    .unwrap_or(false)
             }
             (TypeF::Record(uty1), TypeF::Record(uty2)) => {
                 fn type_eq_bounded_wrapper<E: TermEnvironment>(
@@ -486,8 +508,8 @@ fn type_eq_bounded<E: TermEnvironment>(
                         body2.subst_rrows(var2, &GenericUnifRecordRows::Constant(cst_id)),
                     ),
                     VarKind::EnumRows => (
-                        body1.subst_erows(var1, &UnifEnumRows::Constant(cst_id)),
-                        body2.subst_erows(var2, &UnifEnumRows::Constant(cst_id)),
+                        body1.subst_erows(var1, &GenericUnifEnumRows::Constant(cst_id)),
+                        body2.subst_erows(var2, &GenericUnifEnumRows::Constant(cst_id)),
                     ),
                 };
 
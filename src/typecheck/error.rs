@@ -20,6 +20,8 @@ pub enum RowUnifError {
     RowMismatch(Ident, Box<UnifError>),
     /// Tried to unify an enum row and a record row.
     RowKindMismatch(Ident, Option<UnifType>, Option<UnifType>),
+    /// Tried to unify an enum row carrying a value with a bare enum row.
+    PayloadMismatch(Ident),
     /// A [row constraint][super::RowConstr] was violated.
     UnsatConstr(Ident, Option<UnifType>),
     /// Tried to unify a type constant with another different type.
@@ -45,6 +47,7 @@ impl RowUnifError {
             RowUnifError::MissingRow(id) => UnifError::MissingRow(id, left, right),
             RowUnifError::MissingDynTail() => UnifError::MissingDynTail(left, right),
             RowUnifError::ExtraRow(id) => UnifError::ExtraRow(id, left, right),
+            RowUnifError::PayloadMismatch(id) => UnifError::PayloadMismatch(id, left, right),
             RowUnifError::ExtraDynTail() => UnifError::ExtraDynTail(left, right),
             RowUnifError::RowKindMismatch(id, uty1, uty2) => {
                 UnifError::RowKindMismatch(id, uty1, uty2)
@@ -77,6 +80,9 @@ pub enum UnifError {
     ExtraRow(Ident, UnifType, UnifType),
     /// Tried to unify two rows, but the `Dyn` tail of the RHS was absent from the LHS.
     ExtraDynTail(UnifType, UnifType),
+    /// Tried to unify two enum rows, but the tag carries a value in one of them and is bare in the
+    /// other.
+    PayloadMismatch(Ident, UnifType, UnifType),
     /// Tried to unify a unification variable with a row type violating the [row
     /// constraints][super::RowConstr] of the variable.
     RowConflict(Ident, Option<UnifType>, UnifType, UnifType),
@@ -182,6 +188,12 @@ impl UnifError {
                 reporting::to_type(state.table, state.names, names, uty2),
                 pos_opt,
             ),
+            UnifError::PayloadMismatch(id, uty1, uty2) => TypecheckError::PayloadMismatch(
+                id,
+                reporting::to_type(state.table, state.names, names, uty1),
+                reporting::to_type(state.table, state.names, names, uty2),
+                pos_opt,
+            ),
             UnifError::RowConflict(id, uty, left, right) => TypecheckError::RowConflict(
                 id,
                 uty.map(|uty| reporting::to_type(state.table, state.names, names, uty)),
@@ -1,5 +1,7 @@
 //! Helpers for building `TypeWrapper`s.
-use super::{TypeF, UnifType};
+use super::{TypeF, UnifEnumRows, UnifType};
+use crate::identifier::Ident;
+use crate::types::{EnumRowF, EnumRowsF};
 
 /// Multi-ary arrow constructor for types implementing `Into<TypeWrapper>`.
 #[macro_export]
@@ -30,7 +32,10 @@ macro_rules! mk_uty_enum_row {
     ( $id:expr $(, $ids:expr )* $(; $tail:expr)?) => {
         $crate::typecheck::UnifEnumRows::Concrete(
             $crate::types::EnumRowsF::Extend {
-                row: Ident::from($id),
+                row: $crate::types::EnumRowF {
+                    id: Ident::from($id),
+                    typ: None,
+                },
                 tail: Box::new(mk_uty_enum_row!($( $ids ),* $(; $tail)?))
             }
         )
@@ -108,6 +113,20 @@ where
     UnifType::Concrete(TypeF::Array(Box::new(ty.into())))
 }
 
+/// Build the enum type `` [| `id ty ; tail |] `` of a variant carrying a value of type `ty`.
+pub fn enum_variant<T>(id: Ident, ty: T, tail: UnifEnumRows) -> UnifType
+where
+    T: Into<UnifType>,
+{
+    UnifType::Concrete(TypeF::Enum(UnifEnumRows::Concrete(EnumRowsF::Extend {
+        row: EnumRowF {
+            id,
+            typ: Some(Box::new(ty.into())),
+        },
+        tail: Box::new(tail),
+    })))
+}
+
 // dyn is a reserved keyword
 generate_builder!(dynamic, Dyn);
 generate_builder!(str, Str);
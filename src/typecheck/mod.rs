@@ -53,9 +53,10 @@ use crate::{
     environment::Environment as GenericEnvironment,
     error::TypecheckError,
     identifier::Ident,
+    position::TermPos,
     term::{Contract, MetaValue, RichTerm, StrChunk, Term, TraverseOrder},
     types::{
-        EnumRow, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types, VarKind,
+        EnumRowF, EnumRows, EnumRowsF, RecordRowF, RecordRows, RecordRowsF, TypeF, Types, VarKind,
     },
     {mk_uty_arrow, mk_uty_enum, mk_uty_enum_row, mk_uty_record, mk_uty_row},
 };
@@ -99,14 +100,14 @@ pub enum GenericUnifRecordRows<E: TermEnvironment + Clone> {
     UnifVar(VarId),
 }
 
-/// Unifiable enum rows. Same shape as [`crate::types::EnumRows`] but where each tail may be a
-/// unification variable (or a constant).
-///
-/// Enum rows don't store any type (they are just a sequence of identifiers), so there is no
-/// `GenericUnifEnumRows` taking an additional `E` parameter.
+/// A unifiable enum row.
+pub type GenericUnifEnumRow<E> = EnumRowF<Box<GenericUnifType<E>>>;
+
+/// Unifiable enum rows. Same shape as [`crate::types::EnumRows`] but where the type of each payload
+/// is unifiable, and each tail may be a unification variable (or a constant).
 #[derive(Clone, PartialEq, Debug)]
-pub enum UnifEnumRows {
-    Concrete(EnumRowsF<Box<UnifEnumRows>>),
+pub enum GenericUnifEnumRows<E: TermEnvironment + Clone> {
+    Concrete(EnumRowsF<Box<GenericUnifType<E>>, Box<GenericUnifEnumRows<E>>>),
     Constant(VarId),
     UnifVar(VarId),
 }
@@ -121,7 +122,7 @@ pub enum UnifEnumRows {
 #[derive(Clone, PartialEq, Debug)]
 pub enum GenericUnifType<E: TermEnvironment> {
     /// A concrete type (like `Num` or `Str -> Str`).
-    Concrete(TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, UnifEnumRows>),
+    Concrete(TypeF<Box<GenericUnifType<E>>, GenericUnifRecordRows<E>, GenericUnifEnumRows<E>>),
     /// A contract, seen as an opaque type. In order to compute type equality between contracts or
     /// between a contract and a type, we need to carry an additional environment. This is why we
     /// don't reuse the variant from [`crate::types::TypeF`].
@@ -152,14 +153,19 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<RecordRows> for GenericUn
     }
 }
 
-impl std::convert::TryInto<EnumRows> for UnifEnumRows {
+impl<E: TermEnvironment + Clone> std::convert::TryInto<EnumRows> for GenericUnifEnumRows<E> {
     type Error = ();
 
     fn try_into(self) -> Result<EnumRows, ()> {
         match self {
-            UnifEnumRows::Concrete(erows) => {
-                let converted: EnumRowsF<Box<EnumRows>> =
-                    erows.try_map(|erows| Ok(Box::new(UnifEnumRows::try_into(*erows)?)))?;
+            GenericUnifEnumRows::Concrete(erows) => {
+                let converted: EnumRowsF<Box<Types>, Box<EnumRows>> = erows.try_map(
+                    |uty| Ok(Box::new(GenericUnifType::try_into(*uty)?)),
+                    |uerows| {
+                        let erows: EnumRows = (*uerows).try_into()?;
+                        Ok(Box::new(erows))
+                    },
+                )?;
                 Ok(EnumRows(converted))
             }
             _ => Err(()),
@@ -179,7 +185,7 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Types> for GenericUnifTyp
                         Ok(Box::new(ty))
                     },
                     GenericUnifRecordRows::try_into,
-                    UnifEnumRows::try_into,
+                    GenericUnifEnumRows::try_into,
                 )?;
                 Ok(Types(converted))
             }
@@ -189,21 +195,26 @@ impl<E: TermEnvironment + Clone> std::convert::TryInto<Types> for GenericUnifTyp
     }
 }
 
-// As opposed to `UnifType` and `UnifRecordRows` which can contain types and thus contracts, with
-// all the subtleties associated with contract equality checking (see `typecheck::eq` module), we can
-// convert enum rows directly to unifiable enum rows without additional data: instead of
-// implementing a function `from_enum_rows`, we rather implement the more natural trait
-// `From<EnumRows>`.
-impl From<EnumRows> for UnifEnumRows {
-    fn from(erows: EnumRows) -> Self {
-        UnifEnumRows::Concrete(erows.0.map(|erows| Box::new(UnifEnumRows::from(*erows))))
+impl<E: TermEnvironment + Clone> GenericUnifEnumRows<E> {
+    /// Create `GenericUnifEnumRows` from `EnumRows`. Contracts are represented as the separate
+    /// variant [`GenericUnifType::Contract`] which also stores a term environment, required for
+    /// checking type equality involving contracts.
+    pub fn from_enum_rows(erows: EnumRows, env: &E) -> Self {
+        let f_erow = |ty: Box<Types>| Box::new(GenericUnifType::from_type(*ty, env));
+        let f_erows =
+            |erows: Box<EnumRows>| Box::new(GenericUnifEnumRows::from_enum_rows(*erows, env));
+
+        GenericUnifEnumRows::Concrete(erows.0.map(f_erow, f_erows))
     }
 }
 
-impl UnifEnumRows {
+impl<E: TermEnvironment> GenericUnifEnumRows<E> {
     /// Return an iterator producing immutable references to individual rows.
-    pub fn iter(&self) -> EnumRowsIterator<UnifEnumRows> {
-        EnumRowsIterator { erows: Some(self) }
+    pub fn iter(&self) -> EnumRowsIterator<GenericUnifType<E>, GenericUnifEnumRows<E>> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
+        }
     }
 }
 
@@ -237,8 +248,8 @@ trait SubstRRows<E: TermEnvironment> {
     fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self;
 }
 
-trait SubstERows {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self;
+trait SubstERows<E: TermEnvironment> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self;
 }
 
 impl<E: TermEnvironment> SubstType<E> for GenericUnifType<E> {
@@ -248,7 +259,7 @@ impl<E: TermEnvironment> SubstType<E> for GenericUnifType<E> {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_type(id, to)),
                 |rrows| rrows.subst_type(id, to),
-                |erows| erows,
+                |erows| erows.subst_type(id, to),
             )),
             _ => self,
         }
@@ -267,13 +278,25 @@ impl<E: TermEnvironment> SubstType<E> for GenericUnifRecordRows<E> {
     }
 }
 
+impl<E: TermEnvironment> SubstType<E> for GenericUnifEnumRows<E> {
+    fn subst_type(self, id: &Ident, to: &GenericUnifType<E>) -> Self {
+        match self {
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_type(id, to)),
+                |erows| Box::new(erows.subst_type(id, to)),
+            )),
+            _ => self,
+        }
+    }
+}
+
 impl<E: TermEnvironment> SubstRRows<E> for GenericUnifType<E> {
     fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self {
         match self {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_rrows(id, to)),
                 |rrows| rrows.subst_rrows(id, to),
-                |erows| erows,
+                |erows| erows.subst_rrows(id, to),
             )),
             _ => self,
         }
@@ -295,8 +318,20 @@ impl<E: TermEnvironment> SubstRRows<E> for GenericUnifRecordRows<E> {
     }
 }
 
-impl<E: TermEnvironment> SubstERows for GenericUnifType<E> {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstRRows<E> for GenericUnifEnumRows<E> {
+    fn subst_rrows(self, id: &Ident, to: &GenericUnifRecordRows<E>) -> Self {
+        match self {
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_rrows(id, to)),
+                |erows| Box::new(erows.subst_rrows(id, to)),
+            )),
+            _ => self,
+        }
+    }
+}
+
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifType<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
             GenericUnifType::Concrete(t) => GenericUnifType::Concrete(t.map(
                 |ty| Box::new(ty.subst_erows(id, to)),
@@ -308,8 +343,8 @@ impl<E: TermEnvironment> SubstERows for GenericUnifType<E> {
     }
 }
 
-impl<E: TermEnvironment> SubstERows for GenericUnifRecordRows<E> {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifRecordRows<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
             GenericUnifRecordRows::Concrete(rrows) => GenericUnifRecordRows::Concrete(rrows.map(
                 |ty| Box::new(ty.subst_erows(id, to)),
@@ -320,13 +355,16 @@ impl<E: TermEnvironment> SubstERows for GenericUnifRecordRows<E> {
     }
 }
 
-impl SubstERows for UnifEnumRows {
-    fn subst_erows(self, id: &Ident, to: &UnifEnumRows) -> Self {
+impl<E: TermEnvironment> SubstERows<E> for GenericUnifEnumRows<E> {
+    fn subst_erows(self, id: &Ident, to: &GenericUnifEnumRows<E>) -> Self {
         match self {
-            UnifEnumRows::Concrete(EnumRowsF::TailVar(var_id)) if var_id == *id => to.clone(),
-            UnifEnumRows::Concrete(rrows) => {
-                UnifEnumRows::Concrete(rrows.map(|erows| Box::new(erows.subst_erows(id, to))))
+            GenericUnifEnumRows::Concrete(EnumRowsF::TailVar(var_id)) if var_id == *id => {
+                to.clone()
             }
+            GenericUnifEnumRows::Concrete(erows) => GenericUnifEnumRows::Concrete(erows.map(
+                |ty| Box::new(ty.subst_erows(id, to)),
+                |erows| Box::new(erows.subst_erows(id, to)),
+            )),
             _ => self,
         }
     }
@@ -342,7 +380,7 @@ impl<E: TermEnvironment + Clone> GenericUnifType<E> {
             ty => GenericUnifType::Concrete(ty.map(
                 |ty_| Box::new(GenericUnifType::from_type(*ty_, env)),
                 |rrows| GenericUnifRecordRows::from_record_rows(rrows, env),
-                UnifEnumRows::from,
+                |erows| GenericUnifEnumRows::from_enum_rows(erows, env),
             )),
         }
     }
@@ -350,6 +388,8 @@ impl<E: TermEnvironment + Clone> GenericUnifType<E> {
 
 pub type UnifRecordRow = GenericUnifRecordRow<SimpleTermEnvironment>;
 pub type UnifRecordRows = GenericUnifRecordRows<SimpleTermEnvironment>;
+pub type UnifEnumRow = GenericUnifEnumRow<SimpleTermEnvironment>;
+pub type UnifEnumRows = GenericUnifEnumRows<SimpleTermEnvironment>;
 pub type UnifType = GenericUnifType<SimpleTermEnvironment>;
 
 impl UnifRecordRows {
@@ -393,7 +433,10 @@ impl UnifEnumRows {
             },
             UnifEnumRows::Constant(_) => EnumRows(EnumRowsF::Empty),
             UnifEnumRows::Concrete(t) => {
-                let mapped = t.map(|erows| Box::new(erows.into_erows(table)));
+                let mapped = t.map(
+                    |ty| Box::new(ty.into_type(table)),
+                    |erows| Box::new(erows.into_erows(table)),
+                );
                 EnumRows(mapped)
             }
         }
@@ -470,9 +513,9 @@ impl From<RecordRowsF<Box<UnifType>, Box<UnifRecordRows>>> for UnifRecordRows {
     }
 }
 
-impl From<EnumRowsF<Box<UnifEnumRows>>> for UnifEnumRows {
-    fn from(ty: EnumRowsF<Box<UnifEnumRows>>) -> Self {
-        UnifEnumRows::Concrete(ty)
+impl From<EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>> for UnifEnumRows {
+    fn from(erows: EnumRowsF<Box<UnifType>, Box<UnifEnumRows>>) -> Self {
+        UnifEnumRows::Concrete(erows)
     }
 }
 
@@ -528,40 +571,47 @@ impl<'a, E: TermEnvironment> Iterator
     }
 }
 
-/// Iterator items produced by [`EnumRowsIterator`].
-pub enum UnifEnumRowsIteratorItem<'a> {
+/// Iterator items produced by [`EnumRowsIterator`] on [GenericUnifEnumRows].
+pub enum GenericUnifEnumRowsIteratorItem<'a, E: TermEnvironment> {
     TailVar(&'a Ident),
     TailUnifVar(VarId),
     TailConstant(VarId),
-    Row(&'a EnumRow),
+    Row(EnumRowF<&'a GenericUnifType<E>>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, UnifEnumRows> {
-    type Item = UnifEnumRowsIteratorItem<'a>;
+pub type UnifEnumRowsIteratorItem<'a> = GenericUnifEnumRowsIteratorItem<'a, SimpleTermEnvironment>;
+
+impl<'a, E: TermEnvironment> Iterator
+    for EnumRowsIterator<'a, GenericUnifType<E>, GenericUnifEnumRows<E>>
+{
+    type Item = GenericUnifEnumRowsIteratorItem<'a, E>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next {
-            UnifEnumRows::Concrete(erows) => match erows {
+            GenericUnifEnumRows::Concrete(erows) => match erows {
                 EnumRowsF::Empty => {
                     self.erows = None;
                     None
                 }
                 EnumRowsF::TailVar(id) => {
                     self.erows = None;
-                    Some(UnifEnumRowsIteratorItem::TailVar(id))
+                    Some(GenericUnifEnumRowsIteratorItem::TailVar(id))
                 }
                 EnumRowsF::Extend { row, tail } => {
                     self.erows = Some(tail);
-                    Some(UnifEnumRowsIteratorItem::Row(row))
+                    Some(GenericUnifEnumRowsIteratorItem::Row(EnumRowF {
+                        id: row.id,
+                        typ: row.typ.as_deref(),
+                    }))
                 }
             },
-            UnifEnumRows::UnifVar(var_id) => {
+            GenericUnifEnumRows::UnifVar(var_id) => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailUnifVar(*var_id))
+                Some(GenericUnifEnumRowsIteratorItem::TailUnifVar(*var_id))
             }
-            UnifEnumRows::Constant(var_id) => {
+            GenericUnifEnumRows::Constant(var_id) => {
                 self.erows = None;
-                Some(UnifEnumRowsIteratorItem::TailConstant(*var_id))
+                Some(GenericUnifEnumRowsIteratorItem::TailConstant(*var_id))
             }
         })
     }
@@ -791,7 +841,7 @@ fn walk<L: Linearizer>(
         | Term::SealingKey(_)
         // This function doesn't recursively typecheck imports: this is the responsibility of the
         // caller.
-        | Term::Import(_)
+        | Term::Import(..)
         | Term::ResolvedImport(_) => Ok(()),
         Term::Var(x) => ctxt.type_env
             .get(x)
@@ -874,6 +924,22 @@ fn walk<L: Linearizer>(
                 walk(state, ctxt.clone(), lin, linearizer.scope(), case)
             })
         }
+        Term::MatchPattern(branches) => {
+            branches.iter().try_for_each(|branch| {
+                let mut ctxt = ctxt.clone();
+                let (alias, pat) = &branch.pattern;
+
+                if let Some(id) = alias {
+                    ctxt.type_env.insert(*id, mk_uniftype::dynamic());
+                }
+                inject_pat_vars(pat, &mut ctxt.type_env);
+
+                if let Some(guard) = &branch.guard {
+                    walk(state, ctxt.clone(), lin, linearizer.scope(), guard)?;
+                }
+                walk(state, ctxt, lin, linearizer.scope(), &branch.body)
+            })
+        }
         Term::RecRecord(record, dynamic, ..) => {
             for (id, field) in record.fields.iter() {
                 let binding_type = binding_type(
@@ -903,7 +969,7 @@ fn walk<L: Linearizer>(
                     walk(state, ctxt.clone(), lin, linearizer.scope(), t)
                 })
         }
-        Term::Op1(_, t) => walk(state, ctxt.clone(), lin, linearizer.scope(), t),
+        Term::Op1(_, t) | Term::EnumVariant(_, t) => walk(state, ctxt.clone(), lin, linearizer.scope(), t),
         Term::Op2(_, t1, t2) => {
             walk(state, ctxt.clone(), lin, linearizer.scope(), t1)?;
             walk(state, ctxt, lin, linearizer, t2)
@@ -964,14 +1030,13 @@ fn walk_type<L: Linearizer>(
        // Currently, the parser can't generate unbound type variables by construction. Thus we
        // don't check here for unbound type variables again.
        | TypeF::Var(_)
-       // An enum type can't contain a flat type inside.
-       | TypeF::Enum(_)
        | TypeF::Wildcard(_) => Ok(()),
        TypeF::Arrow(ty1, ty2) => {
            walk_type(state, ctxt.clone(), lin, linearizer.scope(), ty1.as_ref())?;
            walk_type(state, ctxt, lin, linearizer, ty2.as_ref())
        }
        TypeF::Record(rrows) => walk_rrows(state, ctxt, lin, linearizer, rrows),
+       TypeF::Enum(erows) => walk_erows(state, ctxt, lin, linearizer, erows),
        TypeF::Flat(t) => walk(state, ctxt, lin, linearizer, t),
        TypeF::Dict(ty2)
        | TypeF::Array(ty2)
@@ -1000,26 +1065,306 @@ fn walk_rrows<L: Linearizer>(
     }
 }
 
+/// Same as [`walk_type`] but operate on enum rows.
+fn walk_erows<L: Linearizer>(
+    state: &mut State,
+    ctxt: Context,
+    lin: &mut Linearization<L::Building>,
+    mut linearizer: L,
+    erows: &EnumRows,
+) -> Result<(), TypecheckError> {
+    match erows.0 {
+        EnumRowsF::Empty
+        // Currently, the parser can't generate unbound type variables by construction. Thus we
+        // don't check here for unbound type variables again.
+        | EnumRowsF::TailVar(_) => Ok(()),
+        EnumRowsF::Extend { ref row, ref tail } => {
+            if let Some(ty) = &row.typ {
+                walk_type(state, ctxt.clone(), lin, linearizer.scope(), ty)?;
+            }
+            walk_erows(state, ctxt, lin, linearizer, tail)
+        }
+    }
+}
+
 // TODO: The insertion of values in the type environment is done but everything is
 // typed as `Dyn`.
 fn inject_pat_vars(pat: &Destruct, env: &mut Environment) {
-    if let Destruct::Record { matches, rest, .. } = pat {
-        if let Some(id) = rest {
-            env.insert(*id, UnifType::Concrete(TypeF::Dyn));
-        }
-        matches.iter().for_each(|m| match m {
-            Match::Simple(id, ..) => env.insert(*id, UnifType::Concrete(TypeF::Dyn)),
-            Match::Assign(id, _, (bind_id, pat)) => {
-                let id = bind_id.as_ref().unwrap_or(id);
+    match pat {
+        Destruct::Record { matches, rest, .. } => {
+            if let Some(id) = rest {
+                env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+            }
+            matches.iter().for_each(|m| match m {
+                Match::Simple(id, ..) => env.insert(*id, UnifType::Concrete(TypeF::Dyn)),
+                Match::Assign(id, _, (bind_id, pat)) => {
+                    let id = bind_id.as_ref().unwrap_or(id);
+                    env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+                    if !pat.is_empty() {
+                        inject_pat_vars(pat, env);
+                    }
+                }
+            });
+        }
+        Destruct::Array { elems, rest, .. } => {
+            if let Some(id) = rest {
+                env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+            }
+            elems.iter().for_each(|(alias, pat)| {
+                if let Some(id) = alias {
+                    env.insert(*id, UnifType::Concrete(TypeF::Dyn));
+                }
+                inject_pat_vars(pat, env);
+            });
+        }
+        Destruct::EnumVariant(_, pat) => {
+            let (alias, pat) = pat.as_ref();
+            if let Some(id) = alias {
                 env.insert(*id, UnifType::Concrete(TypeF::Dyn));
-                if !pat.is_empty() {
-                    inject_pat_vars(pat, env);
+            }
+            inject_pat_vars(pat, env);
+        }
+        Destruct::Constant(_) | Destruct::Enum(_) | Destruct::Empty => (),
+    }
+}
+
+/// Typecheck a pattern of a match expression against the type `ty` of the matched value, and bind
+/// the variables of the pattern in `env`.
+///
+/// Record patterns are given a record type with one row per field, including fields with a
+/// default value, which can't be expressed as optional in a static type. A record pattern matched
+/// against a dictionary type gives the type of the elements of the dictionary to the fields
+/// instead.
+fn type_check_pattern<L: Linearizer>(
+    state: &mut State,
+    ctxt: &Context,
+    env: &mut Environment,
+    lin: &mut Linearization<L::Building>,
+    linearizer: &mut L,
+    (alias, pat): (&Option<Ident>, &Destruct),
+    ty: UnifType,
+) -> Result<(), TypecheckError> {
+    if let Some(id) = alias {
+        env.insert(*id, ty.clone());
+    }
+
+    match pat {
+        Destruct::Empty => Ok(()),
+        Destruct::Constant(c) => type_check_(state, ctxt.clone(), lin, linearizer.scope(), c, ty),
+        Destruct::Enum(id) => {
+            let row = state.table.fresh_erows_uvar();
+            unify(state, ctxt, ty, mk_uty_enum!(*id; row))
+                .map_err(|err| err.into_typecheck_err(state, id.pos))
+        }
+        Destruct::EnumVariant(id, pat) => {
+            let row = state.table.fresh_erows_uvar();
+            let ty_arg = state.table.fresh_type_uvar();
+            unify(
+                state,
+                ctxt,
+                ty,
+                mk_uniftype::enum_variant(*id, ty_arg.clone(), row),
+            )
+            .map_err(|err| err.into_typecheck_err(state, id.pos))?;
+
+            let (alias, pat) = pat.as_ref();
+            type_check_pattern(state, ctxt, env, lin, linearizer, (alias, pat), ty_arg)
+        }
+        Destruct::Array {
+            elems, rest, span, ..
+        } => {
+            let ty_elts = state.table.fresh_type_uvar();
+            unify(state, ctxt, ty, mk_uniftype::array(ty_elts.clone()))
+                .map_err(|err| err.into_typecheck_err(state, TermPos::Original(*span)))?;
+
+            if let Some(id) = rest {
+                env.insert(*id, mk_uniftype::array(ty_elts.clone()));
+            }
+
+            elems.iter().try_for_each(|(alias, pat)| {
+                type_check_pattern(
+                    state,
+                    ctxt,
+                    env,
+                    lin,
+                    linearizer,
+                    (alias, pat),
+                    ty_elts.clone(),
+                )
+            })
+        }
+        Destruct::Record {
+            matches,
+            open,
+            rest,
+            span,
+        } => {
+            let pos = TermPos::Original(*span);
+            let dict_ty = match ty.clone().into_root(state.table) {
+                UnifType::Concrete(TypeF::Dict(ty_elts)) => Some(*ty_elts),
+                _ => None,
+            };
+
+            let mut rows = Vec::with_capacity(matches.len());
+
+            for m in matches {
+                let (id, meta) = match m {
+                    Match::Simple(id, meta) | Match::Assign(id, meta, _) => (id, meta),
+                };
+                let field_ty = dict_ty
+                    .clone()
+                    .unwrap_or_else(|| state.table.fresh_type_uvar());
+
+                if let Some(Contract { types, .. }) = &meta.types {
+                    let annot_ty = UnifType::from_type(types.clone(), &ctxt.term_env);
+                    unify(state, ctxt, field_ty.clone(), annot_ty)
+                        .map_err(|err| err.into_typecheck_err(state, pos))?;
+                }
+
+                if let Some(default) = &meta.value {
+                    type_check_(
+                        state,
+                        ctxt.clone(),
+                        lin,
+                        linearizer.scope(),
+                        default,
+                        field_ty.clone(),
+                    )?;
+                }
+
+                match m {
+                    Match::Simple(..) => env.insert(*id, field_ty.clone()),
+                    Match::Assign(_, _, (alias, pat)) => type_check_pattern(
+                        state,
+                        ctxt,
+                        env,
+                        lin,
+                        linearizer,
+                        (alias, pat),
+                        field_ty.clone(),
+                    )?,
+                }
+
+                rows.push((*id, field_ty));
+            }
+
+            match dict_ty {
+                Some(ty_elts) => {
+                    if let Some(id) = rest {
+                        env.insert(*id, mk_uniftype::dyn_record(ty_elts));
+                    }
+                    Ok(())
+                }
+                None => {
+                    let tail = if *open {
+                        state.table.fresh_rrows_uvar()
+                    } else {
+                        mk_uty_row!()
+                    };
+
+                    if let Some(id) = rest {
+                        env.insert(*id, mk_uty_record!(; tail.clone()));
+                    }
+
+                    let rows = rows
+                        .into_iter()
+                        .rev()
+                        .fold(tail, |acc, (id, ty)| mk_uty_row!((id, ty); acc));
+                    unify(state, ctxt, ty, mk_uty_record!(; rows))
+                        .map_err(|err| err.into_typecheck_err(state, pos))
                 }
             }
-        });
+        }
+    }
+}
+
+/// Check if a pattern of a typed match expression matches all the values of its type. The fields
+/// of a record type are known statically, so a record pattern is irrefutable if its sub-patterns
+/// are.
+fn is_irrefutable(pat: &Destruct) -> bool {
+    match pat {
+        Destruct::Empty => true,
+        Destruct::Record { matches, .. } => matches.iter().all(|m| match m {
+            Match::Simple(..) => true,
+            Match::Assign(_, _, (_, pat)) => is_irrefutable(pat),
+        }),
+        _ => false,
     }
 }
 
+/// Check if array patterns match arrays of any length, that is if there is an open pattern with
+/// `n` elements and a closed pattern for each length below `n`. Only the patterns whose elements
+/// are irrefutable are taken into account.
+fn covers_arrays<'a>(pats: impl Iterator<Item = &'a Destruct>) -> bool {
+    let mut lengths = HashSet::new();
+    let mut min_open: Option<usize> = None;
+
+    for pat in pats {
+        match pat {
+            Destruct::Array { elems, open, .. }
+                if elems.iter().all(|(_, pat)| is_irrefutable(pat)) =>
+            {
+                if *open {
+                    min_open = Some(min_open.map_or(elems.len(), |n| n.min(elems.len())));
+                } else {
+                    lengths.insert(elems.len());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    match min_open {
+        Some(n) => (0..n).all(|len| lengths.contains(&len)),
+        None => false,
+    }
+}
+
+/// Build the closed enum rows of a typed match expression whose patterns are all enum patterns.
+/// Return `None` if some branch isn't an enum pattern, or if some tag has no branch without guard
+/// matching all its values.
+fn closed_enum_rows(state: &mut State, branches: &[MatchBranch]) -> Option<UnifEnumRows> {
+    let mut tags: Vec<(Ident, Option<UnifType>)> = Vec::new();
+    let mut covered = HashSet::new();
+
+    for branch in branches {
+        let (id, arg) = match &branch.pattern.1 {
+            Destruct::Enum(id) => (*id, None),
+            Destruct::EnumVariant(id, pat) => (*id, Some(&pat.1)),
+            _ => return None,
+        };
+
+        if !tags.iter().any(|(tag, _)| *tag == id) {
+            tags.push((id, arg.map(|_| state.table.fresh_type_uvar())));
+        }
+        let irrefutable = match arg {
+            Some(pat) => is_irrefutable(pat),
+            None => true,
+        };
+        if branch.guard.is_none() && irrefutable {
+            covered.insert(id);
+        }
+    }
+
+    if tags.iter().any(|(id, _)| !covered.contains(id)) {
+        return None;
+    }
+
+    Some(
+        tags.into_iter()
+            .rev()
+            .fold(EnumRowsF::Empty.into(), |tail, (id, typ)| {
+                UnifEnumRows::Concrete(EnumRowsF::Extend {
+                    row: EnumRowF {
+                        id,
+                        typ: typ.map(Box::new),
+                    },
+                    tail: Box::new(tail),
+                })
+            }),
+    )
+}
+
 /// Typecheck a term against a specific type.
 ///
 /// # Arguments
@@ -1227,6 +1572,67 @@ fn type_check_<L: Linearizer>(
             unify(state, &ctxt, ty, mk_uty_arrow!(arg_type, return_type))
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::MatchPattern(branches) => {
+            // As for enum match expressions, a match expression with patterns is typed as
+            // `arg_type -> return_type`, where `arg_type` is the type of the matched values.
+            let arg_type = state.table.fresh_type_uvar();
+            let return_type = state.table.fresh_type_uvar();
+
+            // A typed match expression must be exhaustive. If the patterns don't cover all the
+            // values, they must all be enum patterns: as for enum match expressions, the type of
+            // the matched values is then closed to the matched tags.
+            let unguarded = || {
+                branches
+                    .iter()
+                    .filter(|branch| branch.guard.is_none())
+                    .map(|branch| &branch.pattern.1)
+            };
+
+            if !unguarded().any(is_irrefutable) && !covers_arrays(unguarded()) {
+                let erows = closed_enum_rows(state, branches)
+                    .ok_or(TypecheckError::NonExhaustiveMatch(rt.pos))?;
+                unify(state, &ctxt, arg_type.clone(), mk_uty_enum!(; erows))
+                    .map_err(|err| err.into_typecheck_err(state, rt.pos))?;
+            }
+
+            for branch in branches {
+                let mut branch_ctxt = ctxt.clone();
+                let (alias, pat) = &branch.pattern;
+
+                type_check_pattern(
+                    state,
+                    &ctxt,
+                    &mut branch_ctxt.type_env,
+                    lin,
+                    &mut linearizer,
+                    (alias, pat),
+                    arg_type.clone(),
+                )?;
+
+                if let Some(guard) = &branch.guard {
+                    type_check_(
+                        state,
+                        branch_ctxt.clone(),
+                        lin,
+                        linearizer.scope(),
+                        guard,
+                        mk_uniftype::bool(),
+                    )?;
+                }
+
+                type_check_(
+                    state,
+                    branch_ctxt,
+                    lin,
+                    linearizer.scope(),
+                    &branch.body,
+                    return_type.clone(),
+                )?;
+            }
+
+            unify(state, &ctxt, ty, mk_uty_arrow!(arg_type, return_type))
+                .map_err(|err| err.into_typecheck_err(state, rt.pos))
+        }
         Term::Var(x) => {
             let x_ty = ctxt
                 .type_env
@@ -1243,6 +1649,27 @@ fn type_check_<L: Linearizer>(
             unify(state, &ctxt, ty, mk_uty_enum!(*id; row))
                 .map_err(|err| err.into_typecheck_err(state, rt.pos))
         }
+        Term::EnumVariant(id, t) => {
+            let row = state.table.fresh_erows_uvar();
+            let ty_arg = state.table.fresh_type_uvar();
+
+            type_check_(
+                state,
+                ctxt.clone(),
+                lin,
+                linearizer.scope(),
+                t,
+                ty_arg.clone(),
+            )?;
+
+            unify(
+                state,
+                &ctxt,
+                ty,
+                mk_uniftype::enum_variant(*id, ty_arg, row),
+            )
+            .map_err(|err| err.into_typecheck_err(state, rt.pos))
+        }
         // If some fields are defined dynamically, the only potential type that works is `{_ : a}`
         // for some `a`
         Term::RecRecord(record, dynamic, ..) if !dynamic.is_empty() => {
@@ -1428,7 +1855,7 @@ fn type_check_<L: Linearizer>(
         Term::SealingKey(_) => unify(state, &ctxt, ty, mk_uniftype::sym())
             .map_err(|err| err.into_typecheck_err(state, rt.pos)),
         Term::Sealed(_, t, _) => type_check_(state, ctxt, lin, linearizer, t, ty),
-        Term::Import(_) => unify(state, &ctxt, ty, mk_uniftype::dynamic())
+        Term::Import(..) => unify(state, &ctxt, ty, mk_uniftype::dynamic())
             .map_err(|err| err.into_typecheck_err(state, rt.pos)),
         // We use the apparent type of the import for checking. This function doesn't recursively
         // typecheck imports: this is the responsibility of the caller.
@@ -1498,6 +1925,23 @@ fn replace_wildcards_with_var(
         ))
     }
 
+    fn replace_erows(
+        table: &mut UnifTable,
+        wildcard_vars: &mut Vec<UnifType>,
+        erows: EnumRows,
+        env: &SimpleTermEnvironment,
+    ) -> UnifEnumRows {
+        UnifEnumRows::Concrete(erows.0.map_state(
+            |ty, (table, wildcard_vars)| {
+                Box::new(replace_wildcards_with_var(table, wildcard_vars, *ty, env))
+            },
+            |erows, (table, wildcard_vars)| {
+                Box::new(replace_erows(table, wildcard_vars, *erows, env))
+            },
+            &mut (table, wildcard_vars),
+        ))
+    }
+
     match ty.0 {
         TypeF::Wildcard(i) => get_wildcard_var(table, wildcard_vars, i),
         TypeF::Flat(t) => UnifType::Contract(t, env.clone()),
@@ -1506,8 +1950,7 @@ fn replace_wildcards_with_var(
                 Box::new(replace_wildcards_with_var(table, wildcard_vars, *ty, env))
             },
             |rrows, (table, wildcard_vars)| replace_rrows(table, wildcard_vars, rrows, env),
-            // Enum rows contain neither wildcards nor contracts
-            |erows, _| UnifEnumRows::from(erows),
+            |erows, (table, wildcard_vars)| replace_erows(table, wildcard_vars, erows, env),
             &mut (table, wildcard_vars),
         )),
     }
@@ -1729,34 +2172,39 @@ fn rrows_add(
 
 /// Try to find a specific row (ident) inside enum rows, or add it if permitted.
 ///
-/// If the row is present, this function returns the tail corresponding to the remaining rows coming after
-/// the found one.
+/// If the row is present, this function returns the type of its payload, if any, together with the
+/// tail corresponding to the remaining rows coming after the found one.
 ///
 /// If the row is not present:
 ///
 /// - If the given enum rows are extensible, i.e. they end with a free unification variable, this
-///   function adds a new row (if allowed by [row constraints][RowConstr]). Returns the new tail (a
-///   fresh unification variable).
+///   function adds a new row with the provided payload type `ty` (if allowed by [row
+///   constraints][RowConstr]). Returns `ty` together with the new tail (a fresh unification
+///   variable).
 /// - Otherwise, raise a missing row error.
 fn erows_add(
     state: &mut State,
     id: &Ident,
+    ty: Option<Box<UnifType>>,
     uerows: UnifEnumRows,
-) -> Result<UnifEnumRows, RowUnifError> {
+) -> Result<(Option<Box<UnifType>>, UnifEnumRows), RowUnifError> {
     let uerows = uerows.into_root(state.table);
 
     match uerows {
         UnifEnumRows::Concrete(erows) => match erows {
             EnumRowsF::Empty | EnumRowsF::TailVar(_) => Err(RowUnifError::MissingRow(*id)),
             EnumRowsF::Extend { row, tail } => {
-                if *id == row {
-                    Ok(*tail)
+                if *id == row.id {
+                    Ok((row.typ, *tail))
                 } else {
-                    let subrow = erows_add(state, id, *tail)?;
-                    Ok(UnifEnumRows::Concrete(EnumRowsF::Extend {
-                        row,
-                        tail: Box::new(subrow),
-                    }))
+                    let (extracted_type, subrow) = erows_add(state, id, ty, *tail)?;
+                    Ok((
+                        extracted_type,
+                        UnifEnumRows::Concrete(EnumRowsF::Extend {
+                            row,
+                            tail: Box::new(subrow),
+                        }),
+                    ))
                 }
             }
         },
@@ -1767,11 +2215,14 @@ fn erows_add(
                 .map(|set| set.contains(id))
                 .unwrap_or(false)
             {
-                return Err(RowUnifError::UnsatConstr(*id, None));
+                return Err(RowUnifError::UnsatConstr(*id, ty.map(|ty| *ty)));
             }
             let tail_var_id = state.table.fresh_erows_var_id();
             let new_tail = UnifEnumRows::Concrete(EnumRowsF::Extend {
-                row: *id,
+                row: EnumRowF {
+                    id: *id,
+                    typ: ty.clone(),
+                },
                 tail: Box::new(UnifEnumRows::UnifVar(tail_var_id)),
             });
 
@@ -1779,7 +2230,7 @@ fn erows_add(
 
             state.table.assign_erows(uvar, new_tail);
 
-            Ok(UnifEnumRows::UnifVar(tail_var_id))
+            Ok((ty, UnifEnumRows::UnifVar(tail_var_id)))
         }
         UnifEnumRows::Constant(_) => Err(RowUnifError::MissingRow(*id)),
     }
@@ -1828,7 +2279,7 @@ pub fn unify(
             }
             (TypeF::Flat(s), TypeF::Flat(t)) => Err(UnifError::IncomparableFlatTypes(s, t)),
             (TypeF::Enum(erows1), TypeF::Enum(erows2)) => {
-                unify_erows(state, erows1.clone(), erows2.clone()).map_err(|err| {
+                unify_erows(state, ctxt, erows1.clone(), erows2.clone()).map_err(|err| {
                     err.into_unif_err(mk_uty_enum!(; erows1), mk_uty_enum!(; erows2))
                 })
             }
@@ -1969,6 +2420,7 @@ pub fn unify_rrows(
                 }
             }
         }
+        (UnifRecordRows::UnifVar(p1), UnifRecordRows::UnifVar(p2)) if p1 == p2 => Ok(()),
         (UnifRecordRows::UnifVar(p), urrows) | (urrows, UnifRecordRows::UnifVar(p)) => {
             constr_unify_rrows(state.constr, p, &urrows)?;
             state.table.assign_rrows(p, urrows);
@@ -1991,6 +2443,7 @@ pub fn unify_rrows(
 /// Try to unify two enum row types.
 pub fn unify_erows(
     state: &mut State,
+    ctxt: &Context,
     uerows1: UnifEnumRows,
     uerows2: UnifEnumRows,
 ) -> Result<(), RowUnifError> {
@@ -2004,18 +2457,34 @@ pub fn unify_erows(
                     Err(RowUnifError::UnboundTypeVariable(id))
                 }
                 (EnumRowsF::Empty, EnumRowsF::Empty) => Ok(()),
-                (EnumRowsF::Empty, EnumRowsF::Extend { row: ident, .. }) => {
-                    Err(RowUnifError::ExtraRow(ident))
+                (EnumRowsF::Empty, EnumRowsF::Extend { row, .. }) => {
+                    Err(RowUnifError::ExtraRow(row.id))
                 }
-                (EnumRowsF::Extend { row: ident, .. }, EnumRowsF::Empty) => {
-                    Err(RowUnifError::MissingRow(ident))
+                (EnumRowsF::Extend { row, .. }, EnumRowsF::Empty) => {
+                    Err(RowUnifError::MissingRow(row.id))
                 }
-                (EnumRowsF::Extend { row: id, tail }, erows2 @ EnumRowsF::Extend { .. }) => {
-                    let t2_tail = erows_add(state, &id, UnifEnumRows::Concrete(erows2))?;
-                    unify_erows(state, *tail, t2_tail)
+                (
+                    EnumRowsF::Extend {
+                        row: UnifEnumRow { id, typ },
+                        tail,
+                    },
+                    erows2 @ EnumRowsF::Extend { .. },
+                ) => {
+                    let (typ2, t2_tail) =
+                        erows_add(state, &id, typ.clone(), UnifEnumRows::Concrete(erows2))?;
+
+                    match (typ, typ2) {
+                        (Some(ty1), Some(ty2)) => unify(state, ctxt, *ty1, *ty2)
+                            .map_err(|err| RowUnifError::RowMismatch(id, Box::new(err)))?,
+                        (None, None) => (),
+                        _ => return Err(RowUnifError::PayloadMismatch(id)),
+                    }
+
+                    unify_erows(state, ctxt, *tail, t2_tail)
                 }
             }
         }
+        (UnifEnumRows::UnifVar(p1), UnifEnumRows::UnifVar(p2)) if p1 == p2 => Ok(()),
         (UnifEnumRows::UnifVar(p), uerows) | (uerows, UnifEnumRows::UnifVar(p)) => {
             constr_unify_erows(state.constr, p, &uerows)?;
             state.table.assign_erows(p, uerows);
@@ -2334,25 +2803,25 @@ impl ConstrainFreshRRowsVar for UnifType {
                 ty => ty.constrain_fresh_rrows_var(state, var_id),
             },
             UnifType::Concrete(ty) => match ty {
-                    TypeF::Arrow(uty1, uty2) => {
-                        uty1.constrain_fresh_rrows_var(state, var_id);
-                        uty2.constrain_fresh_rrows_var(state, var_id);
-                    }
-                    TypeF::Forall {body, ..} => body.constrain_fresh_rrows_var(state, var_id),
-                    TypeF::Dyn
-                    | TypeF::Num
-                    | TypeF::Bool
-                    | TypeF::Str
-                    | TypeF::Sym
-                    | TypeF::Flat(_)
-                    | TypeF::Var(_)
-                    // There can be no record rows unification variable inside an enum type
-                    | TypeF::Enum(_) => (),
-                    | TypeF::Wildcard(_) => (),
-                    TypeF::Record(rrows) => rrows.constrain_fresh_rrows_var(state, var_id),
-                    TypeF::Array(uty)
-                    | TypeF::Dict(uty) => uty.constrain_fresh_rrows_var(state, var_id),
-                },
+                TypeF::Arrow(uty1, uty2) => {
+                    uty1.constrain_fresh_rrows_var(state, var_id);
+                    uty2.constrain_fresh_rrows_var(state, var_id);
+                }
+                TypeF::Forall { body, .. } => body.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Dyn
+                | TypeF::Num
+                | TypeF::Bool
+                | TypeF::Str
+                | TypeF::Sym
+                | TypeF::Flat(_)
+                | TypeF::Var(_)
+                | TypeF::Wildcard(_) => (),
+                TypeF::Enum(erows) => erows.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Record(rrows) => rrows.constrain_fresh_rrows_var(state, var_id),
+                TypeF::Array(uty) | TypeF::Dict(uty) => {
+                    uty.constrain_fresh_rrows_var(state, var_id)
+                }
+            },
             UnifType::Constant(_) | UnifType::Contract(..) => (),
         }
     }
@@ -2390,6 +2859,27 @@ impl ConstrainFreshRRowsVar for UnifRecordRows {
     }
 }
 
+impl ConstrainFreshRRowsVar for UnifEnumRows {
+    fn constrain_fresh_rrows_var(&self, state: &mut State, var_id: VarId) {
+        match self {
+            UnifEnumRows::UnifVar(u) => match state.table.root_erows(*u) {
+                UnifEnumRows::UnifVar(_) => (),
+                erows => erows.constrain_fresh_rrows_var(state, var_id),
+            },
+            UnifEnumRows::Concrete(ty) => match ty {
+                EnumRowsF::Empty | EnumRowsF::TailVar(_) => (),
+                EnumRowsF::Extend { row, tail } => {
+                    if let Some(ty) = &row.typ {
+                        ty.constrain_fresh_rrows_var(state, var_id);
+                    }
+                    tail.constrain_fresh_rrows_var(state, var_id);
+                }
+            },
+            UnifEnumRows::Constant(_) => (),
+        }
+    }
+}
+
 impl ConstrainFreshERowsVar for UnifType {
     fn constrain_fresh_erows_var(&self, state: &mut State, var_id: VarId) {
         match self {
@@ -2460,7 +2950,10 @@ impl ConstrainFreshERowsVar for UnifEnumRows {
                 UnifEnumRows::Concrete(ty) => match ty {
                     EnumRowsF::Empty | EnumRowsF::TailVar(_) => (),
                     EnumRowsF::Extend { row, tail } => {
-                        constr.insert(*row);
+                        constr.insert(row.id);
+                        if let Some(ty) = &row.typ {
+                            ty.constrain_fresh_erows_var(state, var_id);
+                        }
                         constrain_var(state, constr, tail, var_id);
                     }
                 },
@@ -2533,9 +3026,9 @@ pub fn constr_unify_erows(
 ) -> Result<(), RowUnifError> {
     if let Some(p_constr) = constr.remove(&var_id) {
         match erows {
-            UnifEnumRows::Concrete(EnumRowsF::Extend { row, .. }) if p_constr.contains(row) => {
+            UnifEnumRows::Concrete(EnumRowsF::Extend { row, .. }) if p_constr.contains(&row.id) => {
                 Err(RowUnifError::UnsatConstr(
-                    *row,
+                    row.id,
                     Some(UnifType::Concrete(TypeF::Enum(erows.clone()))),
                 ))
             }
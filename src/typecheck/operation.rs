@@ -170,6 +170,15 @@ pub fn get_uop_type(
             mk_uniftype::str(),
             mk_uty_enum!(; state.table.fresh_erows_const()),
         ),
+        // Dyn -> Bool
+        UnaryOp::EnumIsVariant() => (mk_uniftype::dynamic(), mk_uniftype::bool()),
+        // Dyn -> < | a> for a rigid type variable a
+        UnaryOp::EnumGetTag() => (
+            mk_uniftype::dynamic(),
+            mk_uty_enum!(; state.table.fresh_erows_const()),
+        ),
+        // Dyn -> Dyn
+        UnaryOp::EnumUnwrapVariant() => (mk_uniftype::dynamic(), mk_uniftype::dynamic()),
         // Str -> Str -> Bool
         UnaryOp::StrIsMatch() => (
             mk_uniftype::str(),
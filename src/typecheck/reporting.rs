@@ -153,8 +153,11 @@ pub fn to_type(
                 EnumRows(EnumRowsF::TailVar(cst_name(reported_names, names, c)))
             }
             UnifEnumRows::Concrete(t) => {
-                let mapped =
-                    t.map(|erows| Box::new(erows_to_type(table, reported_names, names, *erows)));
+                let mapped = t.map_state(
+                    |btyp, names| Box::new(to_type(table, reported_names, names, *btyp)),
+                    |erows, names| Box::new(erows_to_type(table, reported_names, names, *erows)),
+                    names,
+                );
                 EnumRows(mapped)
             }
         }
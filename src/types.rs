@@ -54,9 +54,11 @@ use crate::{
     identifier::Ident,
     mk_app, mk_fun,
     term::make as mk_term,
-    term::{record::RecordData, RichTerm, Term, TraverseOrder},
+    term::{record::RecordData, BinaryOp, RichTerm, Term, TraverseOrder, UnaryOp},
 };
 
+use indexmap::IndexMap;
+
 use std::{
     collections::HashMap,
     fmt::{self, Display},
@@ -76,14 +78,20 @@ pub struct RecordRowF<Ty> {
     pub types: Ty,
 }
 
-/// An enum row, which is just an identifier. An enum type is a set of identifiers, represented as
-/// a sequence of `EnumRow`s, ending potentially with a type variable tail position.
+/// An enum row, mapping an identifier to the optional type of its payload. An enum type is a set
+/// of tags, represented as a sequence of `EnumRowF`s, ending potentially with a type variable tail
+/// position. A bare tag such as `` `foo`` has no payload, while a variant such as `` `Some Num``
+/// carries a value of the given type.
+///
+/// # Type parameters
 ///
-/// `EnumRowF` is the same as `EnumRow` and doesn't have any type parameter. We introduce the alias
-/// nontheless for consistency with other parametrized type definitions. See [`TypeF`] for more
-/// details.
-pub type EnumRowF = Ident;
-pub type EnumRow = EnumRowF;
+/// As other types with the `F` suffix, this type is parametrized by one or more recursive
+/// unfoldings (here, `Ty` for `TypeF`). See [`TypeF`] for more details.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct EnumRowF<Ty> {
+    pub id: Ident,
+    pub typ: Option<Ty>,
+}
 
 /// Generic sequence of record rows potentially with a type variable or `Dyn` in tail position.
 ///
@@ -111,12 +119,14 @@ pub enum RecordRowsF<Ty, RRows> {
 ///
 /// # Type parameters
 ///
+/// - `Ty` is the recursive unfolding of a Nickel type stored inside one row, as the type of the
+///   payload. In practice, a wrapper around an instantiation of `TypeF`.
 /// - `ERows` is the recursive unfolding of enum rows (the tail of this row sequence). In practice,
 ///   a wrapper around `EnumRowsF`.
 #[derive(Clone, PartialEq, Eq, Debug)]
-pub enum EnumRowsF<ERows> {
+pub enum EnumRowsF<Ty, ERows> {
     Empty,
-    Extend { row: EnumRowF, tail: ERows },
+    Extend { row: EnumRowF<Ty>, tail: ERows },
     TailVar(Ident),
 }
 
@@ -254,9 +264,11 @@ pub enum TypeF<Ty, RRows, ERows> {
 // `RecordRow` itself potentially contains occurrences of `Types` and `RecordRows`, which need to
 // be boxed. Hence, we don't need to additionally box `RecordRow`.
 
+/// Concrete, recursive definition for an enum row.
+pub type EnumRow = EnumRowF<Box<Types>>;
 /// Concrete, recursive definition for enum rows.
 #[derive(Clone, PartialEq, Debug)]
-pub struct EnumRows(pub EnumRowsF<Box<EnumRows>>);
+pub struct EnumRows(pub EnumRowsF<Box<Types>, Box<EnumRows>>);
 /// Concrete, recursive definition for a record row.
 pub type RecordRow = RecordRowF<Box<Types>>;
 #[derive(Clone, PartialEq, Debug)]
@@ -360,31 +372,42 @@ impl<Ty, RRows> RecordRowsF<Ty, RRows> {
     }
 }
 
-impl<ERows> EnumRowsF<ERows> {
-    /// Map functions over the tail of enum rows. The mutable state ( `S`) is threaded through the
-    /// calls to the mapped function. The function is fallible and may return an error `E`, which
-    /// causes `try_map_state` to return early with the same error.
+impl<Ty, ERows> EnumRowsF<Ty, ERows> {
+    /// Map functions over the children nodes of enum rows, that is the types of the payloads and
+    /// the tail of the rows. The mutable state ( `S`) is threaded through the calls to the mapped
+    /// functions. Functions are fallible and may return an error `E`, which causes
+    /// `try_map_state` to return early with the same error.
     ///
     /// If we put aside the state and the error (see [EnumRowsF::map), this function makes
     /// `EnumRowsF` a functor. As hinted by the type signature, this function just maps on
     /// "one-level" of recursion, so to speak. Take the instantiated version `EnumRows`, and
-    /// enum rows of the form ``[| `foo, `bar, `baz |]``. Then, calling `try_map_state(f_erows,
-    /// state)` on these rows will map `f_erows` onto ``[| `bar, `baz |]``.
+    /// enum rows of the form ``[| `foo Num, `bar, `baz |]``. Then, calling `try_map_state(f_ty,
+    /// f_erows, state)` on these rows will map `f_ty` onto `Num` and `f_erows` onto
+    /// ``[| `bar, `baz |]``.
     ///
-    /// Note that `f_erows` is just mapped once. Map isn't a recursive operation. It's however a
-    /// building block to express recursive operations: as an example, see [RecordRows::traverse].
-    pub fn try_map_state<ERowsO, FERows, S, E>(
+    /// Note that `f_ty` and `f_erows` are just mapped once. Map isn't a recursive operation. It's
+    /// however a building block to express recursive operations: as an example, see
+    /// [RecordRows::traverse].
+    pub fn try_map_state<TyO, ERowsO, FTy, FERows, S, E>(
         self,
+        mut f_ty: FTy,
         f_erows: FERows,
         state: &mut S,
-    ) -> Result<EnumRowsF<ERowsO>, E>
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
+        FTy: FnMut(Ty, &mut S) -> Result<TyO, E>,
         FERows: FnOnce(ERows, &mut S) -> Result<ERowsO, E>,
     {
         match self {
             EnumRowsF::Empty => Ok(EnumRowsF::Empty),
-            EnumRowsF::Extend { row, tail } => Ok(EnumRowsF::Extend {
-                row,
+            EnumRowsF::Extend {
+                row: EnumRowF { id, typ },
+                tail,
+            } => Ok(EnumRowsF::Extend {
+                row: EnumRowF {
+                    id,
+                    typ: typ.map(|ty| f_ty(ty, state)).transpose()?,
+                },
                 tail: f_erows(tail, state)?,
             }),
             EnumRowsF::TailVar(id) => Ok(EnumRowsF::TailVar(id)),
@@ -392,35 +415,51 @@ impl<ERows> EnumRowsF<ERows> {
     }
 
     /// Variant of `try_map_state` without threaded state.
-    pub fn try_map<ERowsO, FERows, E>(self, mut f_erows: FERows) -> Result<EnumRowsF<ERowsO>, E>
+    pub fn try_map<TyO, ERowsO, FTy, FERows, E>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> Result<EnumRowsF<TyO, ERowsO>, E>
     where
+        FTy: FnMut(Ty) -> Result<TyO, E>,
         FERows: FnMut(ERows) -> Result<ERowsO, E>,
     {
+        let f_ty_lifted = |ty: Ty, _: &mut ()| -> Result<TyO, E> { f_ty(ty) };
         let f_erows_lifted = |erows: ERows, _: &mut ()| -> Result<ERowsO, E> { f_erows(erows) };
-        self.try_map_state(f_erows_lifted, &mut ())
+        self.try_map_state(f_ty_lifted, f_erows_lifted, &mut ())
     }
 
     /// Variant of `try_map_state` with infallible functions.
-    pub fn map_state<ERowsO, FERows, S>(
+    pub fn map_state<TyO, ERowsO, FTy, FERows, S>(
         self,
+        mut f_ty: FTy,
         mut f_erows: FERows,
         state: &mut S,
-    ) -> EnumRowsF<ERowsO>
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty, &mut S) -> TyO,
         FERows: FnMut(ERows, &mut S) -> ERowsO,
     {
+        let f_ty_lifted = |ty: Ty, state: &mut S| -> Result<TyO, ()> { Ok(f_ty(ty, state)) };
         let f_erows_lifted =
             |erows: ERows, state: &mut S| -> Result<ERowsO, ()> { Ok(f_erows(erows, state)) };
-        self.try_map_state(f_erows_lifted, state).unwrap()
+        self.try_map_state(f_ty_lifted, f_erows_lifted, state)
+            .unwrap()
     }
 
     /// Variant of `try_map_state` without threaded state and with infallible functions.
-    pub fn map<ERowsO, FERows>(self, mut f_erows: FERows) -> EnumRowsF<ERowsO>
+    pub fn map<TyO, ERowsO, FTy, FERows>(
+        self,
+        mut f_ty: FTy,
+        mut f_erows: FERows,
+    ) -> EnumRowsF<TyO, ERowsO>
     where
+        FTy: FnMut(Ty) -> TyO,
         FERows: FnMut(ERows) -> ERowsO,
     {
+        let f_ty_lifted = |ty: Ty, _: &mut ()| -> TyO { f_ty(ty) };
         let f_erows_lifted = |erows: ERows, _: &mut ()| -> ERowsO { f_erows(erows) };
-        self.map_state(f_erows_lifted, &mut ())
+        self.map_state(f_ty_lifted, f_erows_lifted, &mut ())
     }
 }
 
@@ -569,6 +608,27 @@ impl RecordRows {
     }
 }
 
+impl EnumRows {
+    /// Apply a transformation on the types of the payloads of enum rows. See [Types::traverse].
+    fn traverse<FTy, S, E>(
+        self,
+        f: &FTy,
+        state: &mut S,
+        order: TraverseOrder,
+    ) -> Result<EnumRows, E>
+    where
+        FTy: Fn(Types, &mut S) -> Result<Types, E>,
+    {
+        let inner = self.0.try_map_state(
+            |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
+            |erows, state| Ok(Box::new(erows.traverse(f, state, order)?)),
+            state,
+        )?;
+
+        Ok(EnumRows(inner))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct UnboundTypeVariableError(pub Ident);
 
@@ -631,17 +691,18 @@ impl<'a> Iterator for RecordRowsIterator<'a, Types, RecordRows> {
     }
 }
 
-pub struct EnumRowsIterator<'a, ERows> {
+pub struct EnumRowsIterator<'a, Ty, ERows> {
     pub(crate) erows: Option<&'a ERows>,
+    pub(crate) ty: std::marker::PhantomData<Ty>,
 }
 
-pub enum EnumRowsIteratorItem<'a> {
+pub enum EnumRowsIteratorItem<'a, Ty> {
     TailVar(&'a Ident),
-    Row(&'a EnumRowF),
+    Row(EnumRowF<&'a Ty>),
 }
 
-impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
-    type Item = EnumRowsIteratorItem<'a>;
+impl<'a> Iterator for EnumRowsIterator<'a, Types, EnumRows> {
+    type Item = EnumRowsIteratorItem<'a, Types>;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.erows.and_then(|next| match next.0 {
@@ -655,7 +716,10 @@ impl<'a> Iterator for EnumRowsIterator<'a, EnumRows> {
             }
             EnumRowsF::Extend { ref row, ref tail } => {
                 self.erows = Some(tail);
-                Some(EnumRowsIteratorItem::Row(row))
+                Some(EnumRowsIteratorItem::Row(EnumRowF {
+                    id: row.id,
+                    typ: row.typ.as_deref(),
+                }))
             }
         })
     }
@@ -678,18 +742,44 @@ fn get_var_contract(
 }
 
 impl EnumRows {
-    fn subcontract(&self) -> Result<RichTerm, UnboundTypeVariableError> {
+    fn subcontract(
+        &self,
+        h: HashMap<Ident, (RichTerm, RichTerm)>,
+        pol: bool,
+        sy: &mut i32,
+    ) -> Result<RichTerm, UnboundTypeVariableError> {
         use crate::stdlib::contract;
 
         let mut cases = HashMap::new();
         let mut has_tail = false;
-        let value_arg = Ident::from("x");
-        let label_arg = Ident::from("l");
+        // The contracts of the payloads are user-provided terms: we use fresh identifiers to avoid
+        // capturing their free variables.
+        let value_arg = Ident::fresh();
+        let label_arg = Ident::fresh();
+        let fail = || mk_app!(contract::enum_fail(), mk_term::var(label_arg));
+        let is_variant = || mk_term::op1(UnaryOp::EnumIsVariant(), mk_term::var(value_arg));
 
         for row in self.iter() {
             match row {
-                EnumRowsIteratorItem::Row(id) => {
-                    cases.insert(*id, mk_term::var(value_arg));
+                EnumRowsIteratorItem::Row(EnumRowF { id, typ: None }) => {
+                    cases.insert(
+                        id,
+                        mk_term::if_then_else(is_variant(), fail(), mk_term::var(value_arg)),
+                    );
+                }
+                EnumRowsIteratorItem::Row(EnumRowF { id, typ: Some(ty) }) => {
+                    let payload = mk_app!(
+                        mk_term::op2(
+                            BinaryOp::Assume(),
+                            ty.subcontract(h.clone(), pol, sy)?,
+                            mk_term::var(label_arg)
+                        ),
+                        mk_term::op1(UnaryOp::EnumUnwrapVariant(), mk_term::var(value_arg))
+                    );
+                    cases.insert(
+                        id,
+                        mk_term::if_then_else(is_variant(), Term::EnumVariant(id, payload), fail()),
+                    );
                 }
                 EnumRowsIteratorItem::TailVar(_) => {
                     has_tail = true;
@@ -698,42 +788,48 @@ impl EnumRows {
             }
         }
 
-        // If the enum type has a tail, the tail must be a universally quantified variable,
-        // and this means that the tag can be anything.
-        let case_body = if has_tail {
-            mk_term::var(value_arg)
-        }
-        // Otherwise, we build a match with all the tags as cases, which just returns the
-        // original argument, and a default case that blames.
+        // We build a match on the tag of the value, which checks that the tag is part of the enum
+        // type and that the value carries a payload if and only if the corresponding row has a
+        // type, in which case the contract of this type is applied to the payload.
         //
-        // For example, for an enum type [| `foo, `bar, `baz |], the `case` function looks
-        // like:
+        // For example, for an enum type [| `foo, `bar Num |], the `case` function looks like:
         //
         // ```
         // fun l x =>
         //   match {
-        //     `foo => x,
-        //     `bar => x,
-        //     `baz => x,
+        //     `foo => if %enum_is_variant% x then $enum_fail l else x,
+        //     `bar => if %enum_is_variant% x then
+        //         `bar (%assume% $num l (%enum_unwrap_variant% x))
+        //       else
+        //         $enum_fail l,
         //     _ => $enum_fail l
-        //   } x
+        //   } (%enum_get_tag% x)
         // ```
-        else {
-            mk_app!(
-                Term::Match {
-                    cases,
-                    default: Some(mk_app!(contract::enum_fail(), mk_term::var(label_arg))),
-                },
-                mk_term::var(value_arg)
-            )
+        //
+        // If the enum type has a tail, the tail must be a universally quantified variable, and
+        // this means that any other tag is accepted as it is.
+        let default = if has_tail {
+            mk_term::var(value_arg)
+        } else {
+            fail()
         };
+        let case_body = mk_app!(
+            Term::Match {
+                cases,
+                default: Some(default),
+            },
+            mk_term::op1(UnaryOp::EnumGetTag(), mk_term::var(value_arg))
+        );
         let case = mk_fun!(label_arg, value_arg, case_body);
 
         Ok(mk_app!(contract::enums(), case))
     }
 
-    pub fn iter(&self) -> EnumRowsIterator<EnumRows> {
-        EnumRowsIterator { erows: Some(self) }
+    pub fn iter(&self) -> EnumRowsIterator<Types, EnumRows> {
+        EnumRowsIterator {
+            erows: Some(self),
+            ty: std::marker::PhantomData,
+        }
     }
 }
 
@@ -750,7 +846,7 @@ impl RecordRows {
         // We begin by building a record whose arguments are contracts
         // derived from the types of the statically known fields.
         let mut rrows = self;
-        let mut fcs = HashMap::new();
+        let mut fcs = IndexMap::new();
 
         while let RecordRowsF::Extend {
             row: RecordRowF { id, types: ty },
@@ -881,7 +977,7 @@ impl Types {
                 *sy += 1;
                 body.subcontract(h, pol, sy)?
             }
-            TypeF::Enum(ref erows) => erows.subcontract()?,
+            TypeF::Enum(ref erows) => erows.subcontract(h, pol, sy)?,
             TypeF::Record(ref rrows) => rrows.subcontract(h, pol, sy)?,
             TypeF::Dict(ref ty) => {
                 mk_app!(contract::dyn_record(), ty.subcontract(h, pol, sy)?)
@@ -923,7 +1019,7 @@ impl Types {
                 let inner = f(self, state)?.0.try_map_state(
                     |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
                     |rrows, state| rrows.traverse(f, state, order),
-                    |erows, _| Ok(erows),
+                    |erows, state| erows.traverse(f, state, order),
                     state,
                 )?;
 
@@ -933,7 +1029,7 @@ impl Types {
                 let traversed_depth_first = self.0.try_map_state(
                     |ty, state| Ok(Box::new(ty.traverse(f, state, order)?)),
                     |rrows, state| rrows.traverse(f, state, order),
-                    |erows, _| Ok(erows),
+                    |erows, state| erows.traverse(f, state, order),
                     state,
                 )?;
 
@@ -965,7 +1061,13 @@ impl Display for EnumRows {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
             EnumRowsF::Extend { ref row, ref tail } => {
-                write!(f, "`{}", row)?;
+                write!(f, "`{}", row.id)?;
+
+                match row.typ {
+                    Some(ref ty) if ty.fmt_is_atom() => write!(f, " {}", ty)?,
+                    Some(ref ty) => write!(f, " ({})", ty)?,
+                    None => (),
+                }
 
                 match tail.0 {
                     EnumRowsF::Extend { .. } => write!(f, ", {}", tail),
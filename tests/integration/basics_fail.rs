@@ -62,3 +62,19 @@ fn string_chunks() {
         Err(Error::EvalError(EvalError::TypeError(..)))
     );
 }
+
+#[test]
+fn non_exhaustive_match() {
+    assert_matches!(
+        eval("(match { `foo => 1, `bar => 2 }) `baz"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+    assert_matches!(
+        eval("(match { {x} => x, [x] => x }) 1"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+    assert_matches!(
+        eval("(match { {x} if x > 0 => x }) {x = 0}"),
+        Err(Error::EvalError(EvalError::NonExhaustiveMatch { .. }))
+    );
+}
@@ -60,6 +60,17 @@ fn enum_simple() {
     assert_raise_blame!("`foo | [| |]");
 }
 
+#[test]
+fn enum_variants() {
+    assert_raise_blame!("`Other 1 | [| `Some Num |]");
+    assert_raise_blame!("`Some | [| `Some Num |]");
+    assert_raise_blame!("`Some 1 | [| `Some |]");
+    assert_raise_blame!("(match { `Some x => x + 1 }) (`Some \"a\" | [| `Some Num |])");
+    assert_raise_blame!(
+        "let f | [| `Some Num |] -> Num = match { `Some x => x } in f (`Some true) + 1"
+    );
+}
+
 #[test]
 fn metavalue_contract_default_fail() {
     assert_raise_blame!("{val | default | Num = true}.val");
@@ -88,3 +88,20 @@ fn typecontract_fail() {
 fn fun() {
     assert_eq!(eval_file("destructuring/fun.ncl"), Ok(Term::Bool(true)));
 }
+
+#[test]
+fn array() {
+    assert_eq!(eval_file("destructuring/array.ncl"), Ok(Term::Bool(true)));
+}
+
+#[test]
+fn array_fail() {
+    assert_matches!(
+        eval_file("destructuring/array_fail.ncl"),
+        Err(Error::EvalError(EvalError::BlameError { .. }))
+    );
+    assert_matches!(
+        eval_file("destructuring/array_open_fail.ncl"),
+        Err(Error::EvalError(EvalError::BlameError { .. }))
+    );
+}
@@ -1,6 +1,7 @@
 use assert_matches::assert_matches;
-use nickel_lang::error::{Error, EvalError, ImportError, TypecheckError};
-use nickel_lang::term::Term;
+use nickel_lang::error::{Error, EvalError, ImportError, ParseError, TypecheckError};
+use nickel_lang::serialize::{self, ExportFormat, FieldOrder};
+use nickel_lang::term::{number::Number, Term};
 use nickel_lang_utilities::TestProgram;
 use std::io::BufReader;
 use std::path::PathBuf;
@@ -21,7 +22,7 @@ fn nested() {
         "should_be = 3",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(3.)));
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(3.))));
 }
 
 #[test]
@@ -31,7 +32,10 @@ fn root_path() {
         "should_be = 44",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(44.)));
+    assert_eq!(
+        prog.eval().map(Term::from),
+        Ok(Term::Num(Number::from(44.)))
+    );
 }
 
 #[test]
@@ -41,7 +45,7 @@ fn multi_imports() {
         "should_be = 5",
     )
     .unwrap();
-    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(5.)));
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Num(Number::from(5.))));
 }
 
 #[test]
@@ -100,6 +104,151 @@ fn serialize() {
     assert_eq!(prog.eval().map(Term::from), Ok(Term::Bool(true)));
 }
 
+#[test]
+fn yaml_stream() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(
+            format!(
+                "array.map (fun x => x.name) ({}) == [\"api\", \"worker\"]",
+                mk_import("stream.yaml")
+            )
+            .as_bytes(),
+        ),
+        "should_be = true",
+    )
+    .unwrap();
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Bool(true)));
+}
+
+#[test]
+fn yaml_single_document() {
+    // A single document imports as its value, even when it starts with an explicit `---` marker
+    for file in ["single_document.yaml", "service.yaml"] {
+        let mut prog = TestProgram::new_from_source(
+            BufReader::new(format!("({}).replicas != null", mk_import(file)).as_bytes()),
+            "should_be = true",
+        )
+        .unwrap();
+        assert_eq!(prog.eval().map(Term::from), Ok(Term::Bool(true)));
+    }
+}
+
+#[test]
+fn text() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(
+            format!(
+                "{} == \"#!/bin/sh\\necho \\\"Hello, ${{NAME}}!\\\"\\n\"",
+                mk_import("hello.sh")
+            )
+            .as_bytes(),
+        ),
+        "should_be = true",
+    )
+    .unwrap();
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Bool(true)));
+}
+
+#[test]
+fn toml_datetimes() {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("tests/integration/imports/datetimes.toml");
+    let src = std::fs::read_to_string(path).unwrap();
+
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(mk_import("datetimes.toml").as_bytes()),
+        "should_roundtrip",
+    )
+    .unwrap();
+    let rt = prog.eval_full_annotated(None).unwrap();
+    assert_eq!(
+        serialize::to_string_with_order(ExportFormat::Toml, &rt, FieldOrder::Definition).unwrap(),
+        src
+    );
+
+    let json = serialize::to_string(ExportFormat::Json, &rt).unwrap();
+    assert!(json.contains("\"released\": \"1979-05-27T07:32:00Z\""));
+}
+
+#[test]
+fn toml_datetimes_multi_export() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(
+            format!(
+                "{{\"d.toml\" = {{format = `Toml, content = {}}}}}",
+                mk_import("datetimes.toml")
+            )
+            .as_bytes(),
+        ),
+        "should_roundtrip",
+    )
+    .unwrap();
+    let rt = prog.eval_full_annotated(None).unwrap();
+    let files = serialize::multi_export_files(&rt).unwrap();
+    let toml = serialize::to_string(files[0].format, &files[0].content).unwrap();
+    assert!(toml.contains("released = 1979-05-27T07:32:00Z"));
+}
+
+#[test]
+fn text_explicit_format() {
+    // The extension of `greeting.tpl` isn't known, and `nested.ncl` would be parsed as Nickel
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(
+            format!(
+                "{} as `Text == \"Hello, {{{{name}}}}!\\n\" && builtin.is_str ({} as `Text)",
+                mk_import("greeting.tpl"),
+                mk_import("nested.ncl")
+            )
+            .as_bytes(),
+        ),
+        "should_be = true",
+    )
+    .unwrap();
+    assert_eq!(prog.eval().map(Term::from), Ok(Term::Bool(true)));
+}
+
+#[test]
+fn invalid_import_format() {
+    let mut prog = TestProgram::new_from_source(
+        BufReader::new(format!("{} as `Csv", mk_import("greeting.tpl")).as_bytes()),
+        "should_fail",
+    )
+    .unwrap();
+    assert_matches!(
+        prog.eval(),
+        Err(Error::ParseErrors(errs)) if matches!(errs.errors.as_slice(), [ParseError::InvalidImportFormat { .. }])
+    );
+}
+
+#[test]
+fn data_positions() {
+    for file in ["service.yaml", "service.json", "service.toml"] {
+        let mut prog = TestProgram::new_from_source(
+            BufReader::new(format!("{} | {{replicas | Num, ..}}", mk_import(file)).as_bytes()),
+            "should_fail",
+        )
+        .unwrap();
+
+        match prog.eval_full() {
+            Err(Error::EvalError(EvalError::BlameError {
+                evaluated_arg: Some(arg),
+                ..
+            })) => {
+                let span = arg.pos.into_opt().unwrap();
+                let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+                path.push(format!("tests/integration/imports/{}", file));
+                let src = std::fs::read_to_string(path).unwrap();
+
+                assert_eq!(
+                    &src[span.start.to_usize()..span.end.to_usize()],
+                    "\"three\""
+                );
+            }
+            res => panic!("expected a blame error for {}, got {:?}", file, res),
+        }
+    }
+}
+
 #[test]
 fn circular_imports_fail() {
     let mut prog = TestProgram::new_from_source(
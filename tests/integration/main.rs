@@ -7,6 +7,7 @@ mod free_vars;
 mod imports;
 mod infinite_rec;
 mod merge_fail;
+mod merge_files;
 mod parse_fail;
 mod pass;
 mod pretty;
@@ -32,3 +32,11 @@ fn merge_conflict_inside_metavalue() {
         "{ foo | default = (fun x => x) 1, foo | default = (fun x => x) 1} & {foo | default = 2 }"
     );
 }
+
+#[test]
+fn merge_forced_default_value() {
+    // Forcing a record drops the annotations of its fields, including the default priority
+    assert_merge_fails!(
+        "let C = fun l x => x in let r = %force% {a | C | default = 1} in r & {a = 2}"
+    );
+}
@@ -0,0 +1,66 @@
+use assert_matches::assert_matches;
+use nickel_lang::error::{Error, EvalError};
+use nickel_lang::identifier::Ident;
+use nickel_lang::term::{number::Number, RichTerm, Term};
+use nickel_lang_utilities::TestProgram;
+use std::path::PathBuf;
+
+fn mk_program(files: &[&str]) -> TestProgram {
+    TestProgram::new_from_files(files.iter().map(|file| {
+        let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        path.push(format!("tests/integration/merge_files/{}", file));
+        path
+    }))
+    .unwrap()
+}
+
+fn field(rt: &RichTerm, id: &str) -> Term {
+    match rt.as_ref() {
+        Term::Record(record) => record
+            .fields
+            .get(&Ident::from(id))
+            .unwrap()
+            .as_ref()
+            .clone(),
+        t => panic!("expected a record, got {:?}", t),
+    }
+}
+
+#[test]
+fn merge_left_to_right() {
+    let result = mk_program(&["base.ncl", "eu.ncl"]).eval_full().unwrap();
+    assert_eq!(field(&result, "region"), Term::Str(String::from("eu")));
+    assert_eq!(field(&result, "replicas"), Term::Num(Number::from(2.)));
+
+    let result = mk_program(&["base.ncl", "eu.ncl", "prod.ncl"])
+        .eval_full()
+        .unwrap();
+    assert_eq!(field(&result, "replicas"), Term::Num(Number::from(5.)));
+}
+
+#[test]
+fn query_merged_files() {
+    let query_value = |path: &str| match mk_program(&["base.ncl", "eu.ncl"])
+        .query(Some(String::from(path)))
+        .unwrap()
+    {
+        Term::MetaValue(meta) => meta.value.unwrap().as_ref().clone(),
+        t => panic!("expected a metavalue, got {:?}", t),
+    };
+
+    assert_eq!(query_value("region"), Term::Str(String::from("eu")));
+    assert_eq!(query_value("replicas"), Term::Num(Number::from(2.)));
+}
+
+#[test]
+fn errors_point_to_the_original_file() {
+    let mut prog = mk_program(&["base.ncl", "wrong_region.ncl"]);
+    let error = prog.eval_full().unwrap_err();
+    assert_matches!(error, Error::EvalError(EvalError::BlameError { .. }));
+    assert!(prog.report_as_str(error).contains("wrong_region.ncl:2:12"));
+}
+
+#[test]
+fn no_input_file() {
+    assert!(TestProgram::new_from_files(Vec::<PathBuf>::new()).is_err());
+}
@@ -87,6 +87,16 @@ fn arrays() {
 //    check_file("contracts.ncl");
 //}
 
+#[test]
+fn enum_variants() {
+    check_file("enum_variants.ncl");
+}
+
+#[test]
+fn match_patterns() {
+    check_file("match.ncl");
+}
+
 #[test]
 fn records() {
     check_file("records.ncl");
@@ -1,4 +1,4 @@
-use nickel_lang::term::{MetaValue, SharedTerm, Term};
+use nickel_lang::term::{number::Number, MetaValue, SharedTerm, Term};
 use nickel_lang_utilities::TestProgram;
 
 #[test]
@@ -12,7 +12,10 @@ pub fn test_query_metadata_basic() {
 
     if let Term::MetaValue(meta) = result {
         assert_eq!(meta.doc, Some(String::from("Test basic")));
-        assert_eq!(meta.value.unwrap().term, SharedTerm::new(Term::Num(2.0)));
+        assert_eq!(
+            meta.value.unwrap().term,
+            SharedTerm::new(Term::Num(Number::from(2.0)))
+        );
     } else {
         panic!();
     }
@@ -105,6 +105,68 @@ fn enum_complex() {
     );
 }
 
+#[test]
+fn enum_variants() {
+    assert_typecheck_fails!("(`Some \"a\") : [| `Some Num |]");
+    assert_typecheck_fails!("(`Some 1) : [| `None |]");
+    assert_matches!(
+        type_check_expr("`Some : [| `Some Num |]"),
+        Err(TypecheckError::PayloadMismatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(`Some 1) : [| `Some |]"),
+        Err(TypecheckError::PayloadMismatch(..))
+    );
+    assert_typecheck_fails!("(match { `Some x => x + 1 }) : [| `Some Str |] -> Num");
+    assert_typecheck_fails!("(match { `Some x => x, `None => 0 }) : [| `Some Num |] -> Num");
+}
+
+#[test]
+fn match_patterns() {
+    assert_typecheck_fails!("(match { {x} => x }) : Num -> Num");
+    assert_typecheck_fails!("(match { {x} => x, [x] => x }) : Dyn");
+    assert_typecheck_fails!("(match { 1 => true, \"a\" => false }) : Dyn");
+    assert_typecheck_fails!("(match { {x} if x => x }) : {x: Num} -> Num");
+    assert_typecheck_fails!("(match { [x, ..rest] => rest }) : Array Num -> Num");
+}
+
+#[test]
+fn non_exhaustive_match_patterns() {
+    assert_matches!(
+        type_check_expr("let f : Array Num -> Num = match { [x] => x } in f [1, 2]"),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(match { [] => 0, [x, y, ..rest] => x }) : Array Num -> Num"),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(match { 0 => 1, 1 => 0 }) : Num -> Num"),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(match { {x = 0} => 0 }) : {x : Num} -> Num"),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    assert_matches!(
+        type_check_expr("(match { `Some 0 => 0, `None => 1 }) : [| `Some Num, `None |] -> Num"),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    assert_matches!(
+        type_check_expr(
+            "(match { `Some x if x > 0 => x, `None => 0 }) : [| `Some Num, `None |] -> Num"
+        ),
+        Err(TypecheckError::NonExhaustiveMatch(..))
+    );
+    // Enum patterns without a catch-all branch close the type of the matched values
+    assert_matches!(
+        type_check_expr(
+            "(match { `Some x => x, `None => 0 }) : forall r. [| `Some Num, `None ; r |] -> Num"
+        ),
+        Err(TypecheckError::ArrowTypeMismatch(..))
+    );
+}
+
 #[test]
 fn static_record_simple() {
     assert_typecheck_fails!("{bla = true} : {bla : Num}");
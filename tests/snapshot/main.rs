@@ -17,6 +17,30 @@ fn check_pretty_print_snapshots(file: &str) {
     insta::assert_snapshot!(file.prefixed_test_name("pretty"), snapshot)
 }
 
+#[test_resources("tests/snapshot/inputs/format/*.ncl")]
+fn check_format_snapshots(file: &str) {
+    let file = TestFile::from_project_path(file);
+
+    let snapshot = NickelInvocation::new()
+        .subcommand("format")
+        .file(&file)
+        .snapshot_stdout();
+
+    insta::assert_snapshot!(file.prefixed_test_name("format"), snapshot)
+}
+
+#[test_resources("tests/snapshot/inputs/convert/*")]
+fn check_convert_snapshots(file: &str) {
+    let file = TestFile::from_project_path(file);
+
+    let snapshot = NickelInvocation::new()
+        .subcommand("convert")
+        .file(&file)
+        .snapshot_stdout();
+
+    insta::assert_snapshot!(file.prefixed_test_name("convert"), snapshot)
+}
+
 #[test_resources("tests/snapshot/inputs/export/*.ncl")]
 fn check_export_stdout_snapshots(file: &str) {
     let file = TestFile::from_project_path(file);